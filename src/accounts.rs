@@ -0,0 +1,103 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+use serde_json::json;
+
+use crate::{output, Currency};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccountPolicy {
+    // cycle through the accounts leg by leg
+    #[default]
+    RoundRobin,
+    // send each leg to the account that has executed the fewest so far
+    LeastUsed,
+}
+
+#[derive(Clone, Debug)]
+pub struct Account {
+    pub name: String,
+    pub balance: HashMap<Currency, f64>,
+    pub legs: u32,
+}
+
+// sub-accounts that capital is split across; every executed leg is booked against one
+#[derive(Clone, Debug)]
+pub struct Accounts {
+    policy: AccountPolicy,
+    accounts: Vec<Account>,
+    next: usize,
+}
+
+impl Accounts {
+    // splits `balance` evenly; without any names there is a single "default" account
+    pub fn new(names: &[String], balance: &HashMap<Currency, f64>, policy: AccountPolicy) -> Self {
+        let names = if names.is_empty() {
+            vec!["default".to_string()]
+        } else {
+            names.to_vec()
+        };
+        let share = 1.0 / names.len() as f64;
+        let accounts = names
+            .into_iter()
+            .map(|name| Account {
+                name,
+                balance: balance
+                    .iter()
+                    .map(|(currency, amount)| (*currency, amount * share))
+                    .collect(),
+                legs: 0,
+            })
+            .collect();
+        Self {
+            policy,
+            accounts,
+            next: 0,
+        }
+    }
+
+    // picks the account for the next leg and counts the leg against it
+    pub fn pick(&mut self) -> &mut Account {
+        let index = match self.policy {
+            AccountPolicy::RoundRobin => {
+                let index = self.next % self.accounts.len();
+                self.next = index + 1;
+                index
+            }
+            AccountPolicy::LeastUsed => self
+                .accounts
+                .iter()
+                .enumerate()
+                .min_by_key(|(_, account)| account.legs)
+                .map(|(index, _)| index)
+                .unwrap_or(0),
+        };
+        let account = &mut self.accounts[index];
+        account.legs += 1;
+        account
+    }
+
+    pub fn report(&self) {
+        if self.accounts.len() < 2 {
+            return;
+        }
+        for account in &self.accounts {
+            let mut balance: Vec<(Currency, f64)> = account
+                .balance
+                .iter()
+                .map(|(currency, amount)| (*currency, *amount))
+                .collect();
+            balance.sort_by_key(|(currency, _)| *currency);
+            output::emit(
+                "account",
+                json!({ "name": account.name, "legs": account.legs, "balance": balance }),
+                format_args!(
+                    "account {}: legs = {}, balance = {:?}",
+                    account.name, account.legs, balance
+                ),
+            );
+        }
+    }
+}
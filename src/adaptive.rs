@@ -0,0 +1,126 @@
+use std::time::Duration;
+
+use crate::{config::AdaptivePoll, OrderBook};
+
+// best bid and ask of both venues as of the previous cycle
+type Tops = [(f64, f64); 2];
+
+// polls faster while the tops of the books move and slower while they sit still
+pub struct AdaptiveInterval {
+    settings: Option<AdaptivePoll>,
+    interval: Duration,
+    last_tops: Option<Tops>,
+}
+
+impl AdaptiveInterval {
+    pub fn new(base: Duration, settings: Option<AdaptivePoll>) -> Self {
+        Self {
+            settings,
+            interval: base,
+            last_tops: None,
+        }
+    }
+
+    // starts over from a new base interval
+    pub fn reset(&mut self, base: Duration) {
+        self.interval = base;
+    }
+
+    pub fn interval(&self) -> Duration {
+        self.interval
+    }
+
+    pub fn observe(&mut self, binance_order_book: &OrderBook, mb_order_book: &OrderBook) {
+        let Some(settings) = self.settings else {
+            return;
+        };
+        let top = |order_book: &OrderBook| {
+            Some((
+                order_book.bids.first()?.price.0,
+                order_book.asks.first()?.price.0,
+            ))
+        };
+        let (Some(binance_top), Some(mb_top)) = (top(binance_order_book), top(mb_order_book))
+        else {
+            return;
+        };
+        let tops = [binance_top, mb_top];
+
+        if let Some(last_tops) = self.last_tops {
+            let change_bps = largest_change_bps(&last_tops, &tops);
+            let interval = if change_bps >= settings.volatile_bps {
+                self.interval.mul_f64(0.5)
+            } else if change_bps <= settings.calm_bps {
+                self.interval.mul_f64(1.5)
+            } else {
+                self.interval
+            };
+            // not clamp, which panics on inverted bounds; the max wins if they are
+            self.interval = interval
+                .max(Duration::from_millis(settings.min_interval_ms))
+                .min(Duration::from_millis(settings.max_interval_ms));
+        }
+        self.last_tops = Some(tops);
+    }
+}
+
+fn largest_change_bps(last: &Tops, current: &Tops) -> f64 {
+    last.iter()
+        .zip(current)
+        .flat_map(|((last_bid, last_ask), (bid, ask))| [(last_bid, bid), (last_ask, ask)])
+        .map(|(last, current)| ((current - last) / last).abs() * 10_000.0)
+        .fold(0.0, f64::max)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn books(bid: f64) -> (OrderBook, OrderBook) {
+        (
+            OrderBook::builder()
+                .bid(bid, 1.0)
+                .ask(bid + 1.0, 1.0)
+                .build(),
+            OrderBook::builder()
+                .bid(bid, 1.0)
+                .ask(bid + 1.0, 1.0)
+                .build(),
+        )
+    }
+
+    #[test]
+    fn inverted_bounds_do_not_panic() {
+        let mut interval = AdaptiveInterval::new(
+            Duration::from_millis(1_000),
+            Some(AdaptivePoll {
+                min_interval_ms: 2_000,
+                max_interval_ms: 500,
+                volatile_bps: 10.0,
+                calm_bps: 1.0,
+            }),
+        );
+        let (binance, mb) = books(100.0);
+        interval.observe(&binance, &mb);
+        interval.observe(&binance, &mb);
+        assert_eq!(interval.interval(), Duration::from_millis(500));
+    }
+
+    #[test]
+    fn volatile_books_halve_the_interval_down_to_the_minimum() {
+        let mut interval = AdaptiveInterval::new(
+            Duration::from_millis(1_000),
+            Some(AdaptivePoll {
+                min_interval_ms: 600,
+                max_interval_ms: 5_000,
+                volatile_bps: 10.0,
+                calm_bps: 1.0,
+            }),
+        );
+        let (binance, mb) = books(100.0);
+        interval.observe(&binance, &mb);
+        let (binance, mb) = books(110.0);
+        interval.observe(&binance, &mb);
+        assert_eq!(interval.interval(), Duration::from_millis(600));
+    }
+}
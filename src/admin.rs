@@ -0,0 +1,291 @@
+use std::{collections::HashMap, io};
+
+use ring::constant_time::verify_slices_are_equal;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+
+use crate::{config::Config, output::log, state::SharedState, Symbol};
+
+const MAX_REQUEST_BYTES: usize = 64 * 1024;
+
+// settings the admin API can change while the bot runs; the loop copies them into its config
+// at the start of every cycle
+#[derive(Clone, Debug, Serialize)]
+pub struct RuntimeSettings {
+    pub min_profit: f64,
+    pub poll_interval_ms: u64,
+    pub enabled_symbols: HashMap<Symbol, bool>,
+    // skip every cycle until resumed
+    pub paused: bool,
+}
+
+impl RuntimeSettings {
+    pub fn from_config(config: &Config) -> Self {
+        Self {
+            min_profit: config.min_profit,
+            poll_interval_ms: config.poll_interval_ms,
+            enabled_symbols: config.enabled_symbols.clone(),
+            paused: false,
+        }
+    }
+}
+
+// a POST /settings body; only the fields present change, and enabled_symbols is merged into
+// the current map
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct SettingsUpdate {
+    min_profit: Option<f64>,
+    poll_interval_ms: Option<u64>,
+    enabled_symbols: Option<HashMap<Symbol, bool>>,
+    paused: Option<bool>,
+}
+
+impl SettingsUpdate {
+    fn apply(self, settings: &mut RuntimeSettings) {
+        if let Some(min_profit) = self.min_profit {
+            settings.min_profit = min_profit;
+        }
+        if let Some(poll_interval_ms) = self.poll_interval_ms {
+            settings.poll_interval_ms = poll_interval_ms;
+        }
+        if let Some(enabled_symbols) = self.enabled_symbols {
+            settings.enabled_symbols.extend(enabled_symbols);
+        }
+        if let Some(paused) = self.paused {
+            settings.paused = paused;
+        }
+    }
+}
+
+struct Request {
+    method: String,
+    path: String,
+    authorization: Option<String>,
+    body: Vec<u8>,
+}
+
+// GET /settings and POST /settings, each needing `Authorization: Bearer <token>`
+pub async fn serve(bind: String, token: String, state: SharedState) {
+    let listener = match TcpListener::bind(&bind).await {
+        Ok(listener) => listener,
+        Err(error) => {
+            log!("admin API could not listen on {}: {}", bind, error);
+            return;
+        }
+    };
+    log!("admin API listening on {}", bind);
+    loop {
+        let stream = match listener.accept().await {
+            Ok((stream, _)) => stream,
+            Err(error) => {
+                log!("admin API could not accept a connection: {}", error);
+                continue;
+            }
+        };
+        let (token, state) = (token.clone(), state.clone());
+        tokio::spawn(async move {
+            if let Err(error) = handle(stream, &token, &state).await {
+                log!("admin API request failed: {}", error);
+            }
+        });
+    }
+}
+
+async fn handle(mut stream: TcpStream, token: &str, state: &SharedState) -> io::Result<()> {
+    let (status, body) = match read_request(&mut stream).await? {
+        Some(request) => respond(&request, token, state),
+        None => (400, json!({ "error": "malformed request" })),
+    };
+    let reason = match status {
+        200 => "OK",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        404 => "Not Found",
+        _ => "Method Not Allowed",
+    };
+    let body = body.to_string();
+    let response = format!(
+        "HTTP/1.1 {} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        reason,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await
+}
+
+// None for anything that isn't a complete HTTP/1.x request within MAX_REQUEST_BYTES
+async fn read_request(stream: &mut TcpStream) -> io::Result<Option<Request>> {
+    let mut buffer = Vec::new();
+    let mut chunk = [0; 4096];
+    let header_end = loop {
+        if let Some(position) = buffer.windows(4).position(|window| window == b"\r\n\r\n") {
+            break position + 4;
+        }
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 || buffer.len() + read > MAX_REQUEST_BYTES {
+            return Ok(None);
+        }
+        buffer.extend_from_slice(&chunk[..read]);
+    };
+
+    let head = String::from_utf8_lossy(&buffer[..header_end]).into_owned();
+    let mut lines = head.split("\r\n");
+    let mut request_line = lines.next().unwrap_or_default().split_whitespace();
+    let (Some(method), Some(path)) = (request_line.next(), request_line.next()) else {
+        return Ok(None);
+    };
+    let mut content_length = 0;
+    let mut authorization = None;
+    for line in lines {
+        let Some((name, value)) = line.split_once(':') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            "content-length" => content_length = value.trim().parse().unwrap_or(0),
+            "authorization" => authorization = Some(value.trim().to_string()),
+            _ => {}
+        }
+    }
+    if header_end + content_length > MAX_REQUEST_BYTES {
+        return Ok(None);
+    }
+
+    let mut body = buffer[header_end..].to_vec();
+    while body.len() < content_length {
+        let read = stream.read(&mut chunk).await?;
+        if read == 0 {
+            return Ok(None);
+        }
+        body.extend_from_slice(&chunk[..read]);
+    }
+    body.truncate(content_length);
+
+    Ok(Some(Request {
+        method: method.to_string(),
+        path: path.to_string(),
+        authorization,
+        body,
+    }))
+}
+
+fn respond(request: &Request, token: &str, state: &SharedState) -> (u16, Value) {
+    let authorized = request
+        .authorization
+        .as_deref()
+        .and_then(|authorization| authorization.strip_prefix("Bearer "))
+        .is_some_and(|given| verify_slices_are_equal(given.as_bytes(), token.as_bytes()).is_ok());
+    if !authorized {
+        return (401, json!({ "error": "unauthorized" }));
+    }
+
+    match (request.method.as_str(), request.path.as_str()) {
+        ("GET", "/settings") => (200, json!(state.read().settings)),
+        ("POST", "/settings") => match serde_json::from_slice::<SettingsUpdate>(&request.body) {
+            Ok(update) => {
+                let settings = {
+                    let mut state = state.write();
+                    update.apply(&mut state.settings);
+                    state.settings.clone()
+                };
+                log!("admin API updated settings to {:?}", settings);
+                (200, json!(settings))
+            }
+            Err(error) => (400, json!({ "error": error.to_string() })),
+        },
+        (_, "/settings") => (405, json!({ "error": "method not allowed" })),
+        _ => (404, json!({ "error": "not found" })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::state::tests::shared_state;
+
+    fn request(method: &str, authorization: Option<&str>, body: &str) -> Request {
+        Request {
+            method: method.to_string(),
+            path: "/settings".to_string(),
+            authorization: authorization.map(str::to_string),
+            body: body.as_bytes().to_vec(),
+        }
+    }
+
+    #[test]
+    fn a_wrong_or_missing_token_is_unauthorized() {
+        let state = shared_state(1_000.0);
+        for authorization in [None, Some("Bearer wrong"), Some("secret")] {
+            let (status, _) = respond(&request("GET", authorization, ""), "secret", &state);
+            assert_eq!(status, 401, "{:?}", authorization);
+        }
+        let (status, _) = respond(&request("GET", Some("Bearer secret"), ""), "secret", &state);
+        assert_eq!(status, 200);
+    }
+
+    #[test]
+    fn unknown_fields_are_rejected_without_changing_anything() {
+        let state = shared_state(1_000.0);
+        let body = r#"{"min_profit": 5.0, "max_profit": 10.0}"#;
+        let (status, _) = respond(
+            &request("POST", Some("Bearer secret"), body),
+            "secret",
+            &state,
+        );
+        assert_eq!(status, 400);
+        assert_eq!(state.read().settings.min_profit, 0.0);
+    }
+
+    #[test]
+    fn enabled_symbols_are_merged_into_the_current_ones() {
+        let state = shared_state(1_000.0);
+        let post = |body| {
+            respond(
+                &request("POST", Some("Bearer secret"), body),
+                "secret",
+                &state,
+            )
+        };
+        assert_eq!(post(r#"{"enabled_symbols": {"ETHBRL": false}}"#).0, 200);
+        let (status, settings) = post(r#"{"enabled_symbols": {"USDTBRL": false}, "paused": true}"#);
+        assert_eq!(status, 200);
+        assert_eq!(
+            settings["enabled_symbols"],
+            json!({ "ETHBRL": false, "USDTBRL": false })
+        );
+        // fields left out keep their value
+        assert_eq!(settings["paused"], json!(true));
+        assert_eq!(settings["min_profit"], json!(0.0));
+    }
+
+    #[tokio::test]
+    async fn an_oversized_body_is_a_malformed_request() {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let client = tokio::spawn(async move {
+            let mut stream = TcpStream::connect(address).await.unwrap();
+            let head = format!(
+                "POST /settings HTTP/1.1\r\nAuthorization: Bearer secret\r\nContent-Length: {}\r\n\r\n",
+                MAX_REQUEST_BYTES
+            );
+            stream.write_all(head.as_bytes()).await.unwrap();
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await.unwrap();
+            response
+        });
+        let (stream, _) = listener.accept().await.unwrap();
+        handle(stream, "secret", &shared_state(1_000.0))
+            .await
+            .unwrap();
+        assert!(client
+            .await
+            .unwrap()
+            .starts_with("HTTP/1.1 400 Bad Request"));
+    }
+}
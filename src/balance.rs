@@ -0,0 +1,189 @@
+use std::collections::HashMap;
+
+use serde::Serialize;
+
+use crate::{
+    buy_fill_price, config::Config, modeled_fee_rate, valuation::Marks, Currency, TradeAction,
+};
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Shortfall {
+    pub currency: Currency,
+    pub required: f64,
+    pub available: f64,
+}
+
+// anything below the smallest amount the currency is kept at
+pub fn default_dust_thresholds() -> HashMap<Currency, f64> {
+    [Currency::BRL, Currency::BTC, Currency::ETH, Currency::USDT]
+        .into_iter()
+        .map(|currency| (currency, currency.unit()))
+        .collect()
+}
+
+// balance usable for decisions; residuals below the dust threshold count as zero
+pub fn tradeable_balance(
+    balance: &HashMap<Currency, f64>,
+    currency: Currency,
+    config: &Config,
+) -> f64 {
+    let amount = balance.get(&currency).copied().unwrap_or(0.0);
+    let threshold = config
+        .dust_thresholds
+        .get(&currency)
+        .copied()
+        .unwrap_or(0.0);
+    if amount.abs() < threshold {
+        0.0
+    } else {
+        amount
+    }
+}
+
+// the single float comparison policy for balances: equal when within `tolerance`
+pub fn amounts_equal(a: f64, b: f64, tolerance: f64) -> bool {
+    (a - b).abs() <= tolerance
+}
+
+// currencies missing from either side count as zero there
+pub fn balances_equal(
+    a: &HashMap<Currency, f64>,
+    b: &HashMap<Currency, f64>,
+    tolerance: impl Fn(Currency) -> f64,
+) -> bool {
+    a.keys().chain(b.keys()).all(|currency| {
+        amounts_equal(
+            a.get(currency).copied().unwrap_or(0.0),
+            b.get(currency).copied().unwrap_or(0.0),
+            tolerance(*currency),
+        )
+    })
+}
+
+pub fn dust_balance(balance: &HashMap<Currency, f64>, currency: Currency, config: &Config) -> f64 {
+    let amount = balance.get(&currency).copied().unwrap_or(0.0);
+    amount - tradeable_balance(balance, currency, config)
+}
+
+const MS_PER_YEAR: f64 = 365.0 * 24.0 * 60.0 * 60.0 * 1000.0;
+
+// the share of the annual holding cost for `elapsed_ms`, in the base currency; long and short
+// positions both tie up capital, holdings without a known rate are not charged
+pub fn holding_cost(
+    balance: &HashMap<Currency, f64>,
+    config: &Config,
+    marks: &Marks,
+    elapsed_ms: u64,
+) -> f64 {
+    let share = config.holding_cost_rate * elapsed_ms as f64 / MS_PER_YEAR;
+    balance
+        .keys()
+        .filter(|currency| **currency != config.base_currency)
+        .filter_map(|currency| {
+            let amount = tradeable_balance(balance, *currency, config);
+            marks.convert(amount.abs(), *currency, config.base_currency)
+        })
+        .sum::<f64>()
+        * share
+}
+
+// what the action has to hold up front, quote first; the buy is priced at the worst case the
+// decision allowed for, its slippage-adjusted price with both legs' fees at the buffered rate
+pub fn required_funds(action: &TradeAction, config: &Config) -> Vec<(Currency, f64)> {
+    let symbol = action.symbol();
+    let qty = action.qty();
+    let (buy_venue, sell_venue) = action.venues();
+    let buy_price = buy_fill_price(action.ask_price(), config.slippage_bps_for(symbol, qty));
+    let fees = buy_price * qty * modeled_fee_rate(config, buy_venue, buy_price, qty)
+        + action.bid_price() * qty * modeled_fee_rate(config, sell_venue, action.bid_price(), qty);
+    let mut funds = vec![(symbol.quote_currency(), buy_price * qty + fees)];
+    if !config.allow_short {
+        funds.push((symbol.base_currency(), action.qty()));
+    }
+    funds
+}
+
+pub fn check_sufficiency(
+    action: &TradeAction,
+    balance: &HashMap<Currency, f64>,
+    config: &Config,
+) -> Result<(), Shortfall> {
+    for (currency, required) in required_funds(action, config) {
+        let available = tradeable_balance(balance, currency, config);
+        if available < required {
+            return Err(Shortfall {
+                currency,
+                required,
+                available,
+            });
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{Symbol, TimeInForce, Venue};
+
+    pub(crate) fn action(ask_price: f64, qty: f64) -> TradeAction {
+        TradeAction::ShortMb {
+            ask_price,
+            bid_price: ask_price * 1.05,
+            qty,
+            symbol: Symbol::BTCBRL,
+            costs: 0.0,
+            net_profit: 0.0,
+            time_in_force: TimeInForce::IOC,
+            post_only_venue: None,
+        }
+    }
+
+    #[test]
+    fn the_quote_is_reserved_at_the_buffered_price_and_fees() {
+        let config = Config {
+            binance_fee_rate: 0.001,
+            slippage_bps: 100.0,
+            fee_buffer: 2.0,
+            allow_short: true,
+            ..Config::default()
+        };
+        let [(currency, required)] = required_funds(&action(10_000.0, 1.0), &config)[..] else {
+            panic!("only the quote is needed when shorting is allowed");
+        };
+        let buy_price = 10_100.0;
+        let fees = buy_price * 0.002
+            + 10_500.0 * modeled_fee_rate(&config, Venue::MercadoBitcoin, 10_500.0, 1.0);
+        assert_eq!(currency, Currency::BRL);
+        assert!((required - (buy_price + fees)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn balances_within_the_tolerance_are_equal() {
+        let a = HashMap::from([(Currency::BTC, 0.5), (Currency::BRL, 1_000.0)]);
+        let b = HashMap::from([(Currency::BTC, 0.5 + 1e-12), (Currency::BRL, 1_000.0)]);
+        let c = HashMap::from([(Currency::BTC, 0.501), (Currency::BRL, 1_000.0)]);
+        assert!(balances_equal(&a, &b, |_| 1e-8));
+        assert!(!balances_equal(&a, &c, |_| 1e-8));
+    }
+
+    #[test]
+    fn tolerances_apply_per_currency() {
+        let a = HashMap::from([(Currency::BTC, 0.5), (Currency::BRL, 1_000.0)]);
+        let b = HashMap::from([(Currency::BTC, 0.5), (Currency::BRL, 1_000.001)]);
+        let tolerance = |currency| match currency {
+            Currency::BRL => 0.01,
+            _ => 1e-8,
+        };
+        assert!(balances_equal(&a, &b, tolerance));
+        assert!(!balances_equal(&a, &b, |_| 1e-8));
+    }
+
+    #[test]
+    fn a_missing_currency_counts_as_zero() {
+        let a = HashMap::from([(Currency::BTC, 0.5), (Currency::ETH, 0.0)]);
+        let b = HashMap::from([(Currency::BTC, 0.5)]);
+        assert!(balances_equal(&a, &b, |_| 1e-8));
+        assert!(!balances_equal(&a, &HashMap::new(), |_| 1e-8));
+    }
+}
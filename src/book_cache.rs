@@ -0,0 +1,34 @@
+use std::{
+    collections::HashMap,
+    sync::{Mutex, PoisonError},
+    time::{Duration, Instant},
+};
+
+use crate::{OrderBook, Symbol, Venue};
+
+// the latest book fetched per venue and symbol, served again while it is younger than a TTL
+#[derive(Debug, Default)]
+pub struct BookCache {
+    books: Mutex<HashMap<(Venue, Symbol), (Instant, OrderBook)>>,
+}
+
+impl BookCache {
+    pub fn get(
+        &self,
+        venue: Venue,
+        symbol: Symbol,
+        ttl: Duration,
+        now: Instant,
+    ) -> Option<OrderBook> {
+        let books = self.books.lock().unwrap_or_else(PoisonError::into_inner);
+        let (fetched_at, order_book) = books.get(&(venue, symbol))?;
+        (now.duration_since(*fetched_at) < ttl).then(|| order_book.clone())
+    }
+
+    pub fn insert(&self, venue: Venue, symbol: Symbol, now: Instant, order_book: &OrderBook) {
+        self.books
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .insert((venue, symbol), (now, order_book.clone()));
+    }
+}
@@ -0,0 +1,161 @@
+use crate::config::{ExecutionMode, RunMode};
+
+pub const USAGE: &str =
+    "usage: arbitrage_bot [--config <path>] [--loop | --once] [--mode <simulated|shadow|live>]
+                     [--pnl-export <path>] [--json]
+       arbitrage_bot --replay <path> [--json]
+       arbitrage_bot --validate-config [--config <path>] [--mode <mode>] [--json]
+       arbitrage_bot --what-if-fees <path> [--fee-rates <rate,...>] [--json]
+
+  --config <path>  load settings from a JSON config file
+  --loop           poll continuously until interrupted; the normal way to run the bot
+  --once           evaluate a single cycle and exit, for cron-style invocation and testing
+  --mode <mode>    simulated, shadow or live; live requires BINANCE_API_KEY,
+                   BINANCE_API_SECRET, MB_API_KEY and MB_API_SECRET, or a secrets_path in
+                   the config; shadow uses them for test orders when present
+  --pnl-export <path>
+                   append timestamp, holdings value and P&L to this CSV file every cycle
+  --replay <path>  print the decision for a captured decision input and exit
+  --what-if-fees <path>
+                   replay a captured decision input, or a directory of them, at a grid
+                   of Binance fee rates and print the net profit at each; a .csv of
+                   exchange,symbol,timestamp,side,price,qty rows is imported as books,
+                   under --config if given
+  --fee-rates <rate,...>
+                   the grid for --what-if-fees, e.g. 0.0005,0.001
+  --validate-config
+                   check the config for incoherent settings, and for credentials when
+                   live, print each problem and exit non-zero if there are any
+  --json           print everything as one JSON object per line instead of text
+  --help, -h       print this and exit
+
+without --loop or --once the config's run_mode is used, without --mode its mode";
+
+#[derive(Clone, Debug, Default)]
+pub struct Args {
+    pub config_path: Option<String>,
+    pub run_mode: Option<RunMode>,
+    pub mode: Option<ExecutionMode>,
+    pub pnl_export_path: Option<String>,
+    pub replay_path: Option<String>,
+    pub what_if_fees_path: Option<String>,
+    pub fee_rates: Option<Vec<f64>>,
+    pub validate_config: bool,
+    pub json: bool,
+    pub help: bool,
+}
+
+impl Args {
+    pub fn parse() -> Result<Self, String> {
+        Self::parse_from(std::env::args().skip(1))
+    }
+
+    pub fn parse_from(args: impl IntoIterator<Item = String>) -> Result<Self, String> {
+        let mut parsed = Self::default();
+        let mut args = args.into_iter();
+
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--config" => match args.next() {
+                    Some(path) => parsed.config_path = Some(path),
+                    None => return Err(format!("--config needs a path\n\n{}", USAGE)),
+                },
+                "--replay" => match args.next() {
+                    Some(path) => parsed.replay_path = Some(path),
+                    None => return Err(format!("--replay needs a path\n\n{}", USAGE)),
+                },
+                "--pnl-export" => match args.next() {
+                    Some(path) => parsed.pnl_export_path = Some(path),
+                    None => return Err(format!("--pnl-export needs a path\n\n{}", USAGE)),
+                },
+                "--what-if-fees" => match args.next() {
+                    Some(path) => parsed.what_if_fees_path = Some(path),
+                    None => return Err(format!("--what-if-fees needs a path\n\n{}", USAGE)),
+                },
+                "--fee-rates" => {
+                    let rates = args.next().and_then(|rates| {
+                        rates
+                            .split(',')
+                            .map(|rate| rate.trim().parse::<f64>().ok())
+                            .collect::<Option<Vec<f64>>>()
+                    });
+                    match rates {
+                        Some(rates) => parsed.fee_rates = Some(rates),
+                        None => {
+                            return Err(format!(
+                                "--fee-rates needs comma-separated numbers\n\n{}",
+                                USAGE
+                            ))
+                        }
+                    }
+                }
+                "--validate-config" => parsed.validate_config = true,
+                "--json" => parsed.json = true,
+                "--loop" => parsed.set_run_mode(RunMode::Loop)?,
+                "--once" => parsed.set_run_mode(RunMode::Once)?,
+                "--mode" => match args.next().as_deref() {
+                    Some("simulated") => parsed.mode = Some(ExecutionMode::Simulated),
+                    Some("shadow") => parsed.mode = Some(ExecutionMode::Shadow),
+                    Some("live") => parsed.mode = Some(ExecutionMode::Live),
+                    _ => {
+                        return Err(format!(
+                            "--mode needs simulated, shadow or live\n\n{}",
+                            USAGE
+                        ))
+                    }
+                },
+                "--help" | "-h" => {
+                    parsed.help = true;
+                    return Ok(parsed);
+                }
+                other => return Err(format!("unknown argument {}\n\n{}", other, USAGE)),
+            }
+        }
+
+        Ok(parsed)
+    }
+
+    fn set_run_mode(&mut self, run_mode: RunMode) -> Result<(), String> {
+        match self.run_mode {
+            Some(existing) if existing != run_mode => Err(format!(
+                "--loop and --once are mutually exclusive\n\n{}",
+                USAGE
+            )),
+            _ => {
+                self.run_mode = Some(run_mode);
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Args, String> {
+        Args::parse_from(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn loop_and_once_pick_the_run_mode() {
+        assert_eq!(parse(&["--loop"]).unwrap().run_mode, Some(RunMode::Loop));
+        assert_eq!(parse(&["--once"]).unwrap().run_mode, Some(RunMode::Once));
+        assert_eq!(parse(&[]).unwrap().run_mode, None);
+        assert!(parse(&["--loop", "--once"]).is_err());
+    }
+
+    #[test]
+    fn misspelled_flags_are_rejected() {
+        let error = parse(&["--valdiate-config"]).unwrap_err();
+        assert!(error.starts_with("unknown argument --valdiate-config"));
+        assert!(error.ends_with(USAGE));
+    }
+
+    #[test]
+    fn help_asks_for_the_usage() {
+        assert!(parse(&["--help"]).unwrap().help);
+        // whatever follows is not parsed, so it can't turn help into an error
+        assert!(parse(&["-h", "--bogus"]).unwrap().help);
+    }
+}
@@ -0,0 +1,83 @@
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::{Duration, Instant, SystemTime, UNIX_EPOCH},
+};
+
+// source of "now" for time-dependent decisions, so they can be replayed deterministically
+pub trait Clock: Send + Sync {
+    fn now_ms(&self) -> u64;
+
+    // monotonic reading for measuring durations
+    fn instant(&self) -> Instant;
+}
+
+pub struct SystemClock;
+
+impl Clock for SystemClock {
+    fn now_ms(&self) -> u64 {
+        unix_millis()
+    }
+
+    fn instant(&self) -> Instant {
+        Instant::now()
+    }
+}
+
+// a clock pinned to a given instant, which only moves when advanced
+pub struct MockClock {
+    now_ms: AtomicU64,
+    started: Instant,
+    advanced_ms: AtomicU64,
+}
+
+impl MockClock {
+    pub fn new(now_ms: u64) -> Self {
+        Self {
+            now_ms: AtomicU64::new(now_ms),
+            started: Instant::now(),
+            advanced_ms: AtomicU64::new(0),
+        }
+    }
+
+    // only tests move time; a fixed_clock_ms run stays pinned
+    #[cfg(test)]
+    pub fn advance(&self, ms: u64) {
+        self.now_ms.fetch_add(ms, Ordering::Relaxed);
+        self.advanced_ms.fetch_add(ms, Ordering::Relaxed);
+    }
+}
+
+impl Clock for MockClock {
+    fn now_ms(&self) -> u64 {
+        self.now_ms.load(Ordering::Relaxed)
+    }
+
+    // durations measured against it are whatever it has been advanced by
+    fn instant(&self) -> Instant {
+        self.started + Duration::from_millis(self.advanced_ms.load(Ordering::Relaxed))
+    }
+}
+
+pub fn unix_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .unwrap()
+        .as_millis() as u64
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_mock_clock_only_moves_when_advanced() {
+        let clock = MockClock::new(1_000);
+        let started = clock.instant();
+        assert_eq!(clock.now_ms(), 1_000);
+        assert_eq!(clock.instant() - started, Duration::ZERO);
+
+        clock.advance(250);
+        assert_eq!(clock.now_ms(), 1_250);
+        assert_eq!(clock.instant() - started, Duration::from_millis(250));
+    }
+}
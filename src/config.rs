@@ -0,0 +1,582 @@
+use std::{collections::HashMap, error::Error, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    accounts::AccountPolicy,
+    balance::default_dust_thresholds,
+    conversion::ConversionTarget,
+    executor::{MakerQueue, TimeInForce},
+    hysteresis::ProfitHysteresis,
+    sizing::KlineSizing,
+    slippage::SlippageModel,
+    transfer::WithdrawalFees,
+    volume::VipTier,
+    Currency, Symbol, Venue, BINANCE_FEE_RATE,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ExecutionMode {
+    #[default]
+    Simulated,
+    // executes against books refetched at execution time and validates Binance legs with
+    // test orders, but never books the result
+    Shadow,
+    Live,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RunMode {
+    // evaluate a single cycle and exit, e.g. from cron
+    #[default]
+    Once,
+    // keep polling every poll_interval_ms until interrupted
+    Loop,
+}
+
+// what a book is taken to be worth, for market making and the reference-price check
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FairValue {
+    #[default]
+    Mid,
+    // the mid weighted towards the side with less size behind it, see OrderBook::microprice
+    Microprice,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeeCurrency {
+    // fees are charged in the pair's quote currency
+    #[default]
+    Quote,
+    // fees come out of the asset the fill delivers: base on buys, quote on sells
+    Received,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct PegBand {
+    pub min: f64,
+    pub max: f64,
+}
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct AdaptivePoll {
+    pub min_interval_ms: u64,
+    pub max_interval_ms: u64,
+    // a top-of-book move at least this large halves the interval
+    pub volatile_bps: f64,
+    // moves no larger than this stretch the interval by half
+    pub calm_bps: f64,
+}
+
+impl PegBand {
+    pub fn contains(&self, price: f64) -> bool {
+        price >= self.min && price <= self.max
+    }
+}
+
+impl AdaptivePoll {
+    pub fn check(&self) -> Result<(), String> {
+        if self.min_interval_ms > self.max_interval_ms {
+            return Err(format!(
+                "min_interval_ms {} is above max_interval_ms {}",
+                self.min_interval_ms, self.max_interval_ms
+            ));
+        }
+        Ok(())
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub symbol: Symbol,
+    // symbols mapped to false are neither fetched nor evaluated; unlisted ones are enabled
+    pub enabled_symbols: HashMap<Symbol, bool>,
+    // the only venues a symbol may be bought or sold on; unlisted symbols trade anywhere
+    pub buy_venues: HashMap<Symbol, Vec<Venue>>,
+    pub sell_venues: HashMap<Symbol, Vec<Venue>>,
+    // further symbols fetched every cycle whose opportunities are reported, and traded when
+    // execute_scan_opportunities is set
+    pub scan_symbols: Vec<Symbol>,
+    // also scan every well-known pair both venues list, refetching the listings once they are
+    // symbol_cache_ttl_ms old so the scan set follows listings and delistings
+    pub scan_listed_symbols: bool,
+    // trade the scanned opportunities concurrently, each reserving its funds first
+    pub execute_scan_opportunities: bool,
+    pub mode: ExecutionMode,
+    pub run_mode: RunMode,
+    pub poll_interval_ms: u64,
+    // in loop mode, pause while Binance's system status reports maintenance or MB answers
+    // with 503s, retrying this often until a cycle goes through
+    pub maintenance_backoff_ms: Option<u64>,
+    // in loop mode, halt once a single cycle has been running this long, e.g. on a fetch that
+    // never returns
+    pub watchdog_stall_ms: Option<u64>,
+    // once halted, how long the stalled cycle gets to cancel its open orders and correct
+    // whatever filled before it is abandoned
+    pub watchdog_unwind_ms: u64,
+    // each sleep between cycles is randomized within poll_interval_ms ± this percentage
+    pub poll_jitter_pct: f64,
+    // adapt the poll interval to how fast the books move, starting from poll_interval_ms
+    pub adaptive_poll: Option<AdaptivePoll>,
+    // Binance's request weight allowance per minute
+    pub binance_weight_limit: u32,
+    // past this share of the allowance the poll interval stretches with the used weight
+    pub binance_weight_throttle_pct: f64,
+    // max absolute difference tolerated between internal and reported balances
+    pub reconcile_tolerance: f64,
+    // per-currency overrides of reconcile_tolerance
+    pub reconcile_tolerances: HashMap<Currency, f64>,
+    // overwrite the internal balance with the exchange-reported one on mismatch
+    pub snap_to_reported_balances: bool,
+    // post quotes on the cheaper venue when there is no arbitrage to take; experimental, the
+    // quotes are only logged, so it is refused outside simulated mode
+    pub market_making: bool,
+    // base-currency quantity held on each venue to trade either direction from, see
+    // HedgedPair; trades the sell venue can't cover from it are skipped
+    pub hedged_inventory: Option<f64>,
+    // extra margin, on top of both venues' fees, between the quotes and the reference mid
+    pub mm_edge_bps: f64,
+    // how far the reference mid may move before active quotes are replaced
+    pub mm_requote_bps: f64,
+    pub fair_value: FairValue,
+    // assumed adverse move on every fill, applied to balances and the profit check
+    pub slippage_bps: f64,
+    // read imported CSV numbers as "1.234,56" rather than "1,234.56", with the fields then
+    // separated by semicolons; the venues' own books are always dot-decimal
+    pub decimal_comma: bool,
+    // treat the rows of an imported CSV as updates to the previous book, a zero quantity
+    // removing the level, rather than as full snapshots
+    pub import_diffs: bool,
+    // calibrated per-symbol, size-dependent slippage that replaces slippage_bps for the
+    // symbols it covers, see SlippageModel
+    pub slippage_model_path: Option<String>,
+    // loaded from slippage_model_path; kept in the config so that captured decisions replay
+    // with the same slippage
+    pub slippage_model: SlippageModel,
+    // skip books whose exchange timestamp is older than this, measured in server time
+    pub max_book_age_ms: Option<u64>,
+    pub clock_skew_refresh_secs: u64,
+    // skip evaluation when the two venues' books were last updated further apart than this
+    pub max_book_age_gap_ms: Option<u64>,
+    // load tradeable symbols and their filters from exchangeInfo on startup
+    pub discover_symbols: bool,
+    // keep what discover_symbols loads in this file and reuse it for symbol_cache_ttl_ms
+    pub symbol_cache_path: Option<String>,
+    pub symbol_cache_ttl_ms: u64,
+    // smallest order value each venue accepts, in the symbol's quote currency; Binance's is
+    // filled in from its MIN_NOTIONAL filter by discover_symbols unless set here, MB's has to
+    // be taken from its published limits
+    pub min_notional: HashMap<Venue, f64>,
+    pub spread_ema_period: usize,
+    // only trade when the net spread exceeds its EMA by this factor
+    pub spread_ema_factor: Option<f64>,
+    // balances below these are treated as zero when deciding and reporting
+    pub dust_thresholds: HashMap<Currency, f64>,
+    // present the amounts in the stats report at each currency's precision; accounting
+    // keeps full precision either way
+    pub round_reported_amounts: bool,
+    // sell legs may short the asset instead of requiring inventory on hand
+    pub allow_short: bool,
+    // pin decision time to this unix timestamp, for deterministic replays; durations measured
+    // against a pinned clock stay at zero, so resting GTC orders never time out under it
+    pub fixed_clock_ms: Option<u64>,
+    pub time_in_force: TimeInForce,
+    // the leg on this venue is sent post-only so it can only pay the maker fee; it goes out
+    // first and the trade is abandoned if it doesn't fill
+    pub post_only_venue: Option<Venue>,
+    // in simulation, fill resting post-only orders from a queue-position model instead of
+    // never filling them
+    pub maker_queue: Option<MakerQueue>,
+    // how long to poll a resting order's status before cancelling it and booking its partial
+    // fill, overridable per venue; a leg that times out also cancels the other leg in flight
+    pub order_status_timeout_ms: u64,
+    pub leg_timeouts_ms: HashMap<Venue, u64>,
+    pub order_status_poll_ms: u64,
+    // executed trades still waiting to settle beyond which new opportunities are skipped; a
+    // trade settles position_settle_ms after execution or at the next netting settlement
+    pub max_open_positions: Option<usize>,
+    pub position_settle_ms: u64,
+    // largest base quantity a single trade takes, whatever the book offers
+    pub max_trade_qty: Option<f64>,
+    // when max_trade_qty isn't set, derive it at startup from the average trade size in
+    // recent Binance klines
+    pub kline_sizing: Option<KlineSizing>,
+    // split corrective orders across both venues' depth for the lowest cost after fees,
+    // instead of sending them whole to the venue with the best top of book
+    pub route_corrective_orders: bool,
+    // send both legs at once and book neither until both have confirmed
+    pub concurrent_legs: bool,
+    // split each trade into this many child trades, ladder_spacing_ms apart, to reduce its
+    // market impact; 1 sends it whole
+    pub ladder_slices: usize,
+    pub ladder_spacing_ms: u64,
+    // re-fetch both books before executing and only trade if the same direction still
+    // clears min_profit on them
+    pub confirm_before_execution: bool,
+    // net base quantity left open between legs before a corrective order is sent
+    pub leg_imbalance_tolerance: f64,
+    // charge the withdrawals needed to rebalance both venues against each trade's profit
+    pub charge_transfer_costs: bool,
+    pub withdrawal_fees: WithdrawalFees,
+    // instead of charging each trade, net the flows and charge the rebalance of the net
+    // quantity once every this many trades
+    pub transfer_settlement_trades: Option<u32>,
+    // expected USDTBRL range; USDT arbitrage is suppressed outside of it
+    pub usdt_peg_band: Option<PegBand>,
+    // suppress trading while a venue's mid is further than this from the reference spot price
+    pub max_reference_deviation_pct: Option<f64>,
+    // how long a fetched reference price is used before it is refetched
+    pub reference_price_ttl_ms: u64,
+    // also look for arbitrage between Binance's USDT-quoted pair and MB's BRL pair
+    pub cross_quote: bool,
+    // cross-quote arbitrage is refused while the cached USDTBRL rate is older than this
+    pub fx_rate_ttl_ms: u64,
+    // home currency for the starting balance, reported totals and min_profit
+    pub base_currency: Currency,
+    // simulated capital, all of it in base_currency
+    pub starting_balance: f64,
+    // smallest net profit worth trading, in base_currency
+    pub min_profit: f64,
+    // replaces min_profit with a higher threshold to start trading and a lower one to stop
+    pub profit_hysteresis: Option<ProfitHysteresis>,
+    // held back from every net profit estimate against model error before it's compared to
+    // min_profit: a flat amount in the symbol's quote currency plus a percentage of notional
+    pub safety_margin: f64,
+    pub safety_margin_pct: f64,
+    // a spread wider than this is taken for bad data and skipped: bid minus ask in the
+    // symbol's quote currency, and as a percentage of the ask
+    pub max_spread: Option<f64>,
+    pub max_spread_pct: Option<f64>,
+    // among equally profitable directions, prefer selling on the venue listed first
+    pub sell_venue_priority: Vec<Venue>,
+    // annualized funding cost of holding non-base inventory, charged every cycle for the time
+    // since the previous charge
+    pub holding_cost_rate: f64,
+    // serve GET/POST /settings on admin_api_bind to retune min_profit, the poll interval and
+    // enabled symbols or pause trading while running; requests need the ADMIN_API_TOKEN
+    // environment variable as a bearer token
+    pub admin_api: bool,
+    pub admin_api_bind: String,
+    // route every exchange request through this proxy, e.g. "http://proxy.local:3128"
+    pub http_proxy: Option<String>,
+    // idle connections kept open per host for reuse, and how long they may stay idle;
+    // reqwest's defaults (unbounded, 90s) when unset
+    pub http_pool_max_idle_per_host: Option<usize>,
+    pub http_pool_idle_timeout_ms: Option<u64>,
+    // speak HTTP/2 from the first request instead of negotiating it; only for endpoints
+    // known to support it, as the others will refuse the connection
+    pub http2_prior_knowledge: bool,
+    // JSON file with binance/mercado_bitcoin api_key and api_secret, overridden by env vars
+    pub secrets_path: Option<String>,
+    // sub-accounts the starting capital is split evenly across
+    pub accounts: Vec<String>,
+    // how each executed leg picks its account
+    pub account_policy: AccountPolicy,
+    // book levels per side summed into the imbalance signal
+    pub imbalance_levels: usize,
+    // only execute when the books lean towards the trade by at least this much, in -1..=1
+    pub min_direction_imbalance: Option<f64>,
+    // discard levels with no quantity, which would otherwise pass for the best price
+    pub drop_zero_qty_levels: bool,
+    // keep the side of a book that parses when the other is malformed, instead of failing the
+    // fetch; a one-sided book can hide a broken feed, so this is off by default
+    pub lenient_book_parsing: bool,
+    // re-fetch a book once when a side has fewer levels than this, and skip the cycle if it
+    // still does
+    pub min_book_levels: Option<usize>,
+    // likewise re-fetch a book whose best bid is at or above its best ask, which a consistent
+    // snapshot never shows
+    pub resync_crossed_books: bool,
+    // likewise re-fetch a book that doesn't match the checksum its venue sent with it, and
+    // with import_diffs stop using a venue's imported book from a mismatch until an update
+    // matches again
+    pub validate_book_checksums: bool,
+    pub book_retry_delay_ms: u64,
+    // serve a symbol's book from the last fetch while it is younger than this, instead of
+    // fetching it again; for slow-moving pairs, never cached when unlisted
+    pub book_ttl_ms: HashMap<Symbol, u64>,
+    // write the input of every decision that leads to a trade here, for `--replay`
+    pub decision_capture_dir: Option<String>,
+    // with every executed trade, also print both books as fetched, every level; verbose
+    pub log_trade_depth: bool,
+    // append the value of the holdings and the P&L so far to this CSV file every cycle
+    pub pnl_export_path: Option<String>,
+    // how each venue deducts its trading fees, quote when missing
+    pub fee_currencies: HashMap<Venue, FeeCurrency>,
+    // multiplies the modeled fees when deciding whether a trade pays, e.g. 1.2 to allow for
+    // fees above the schedule; booked fees stay at the schedule
+    pub fee_buffer: f64,
+    // per-venue API hosts, e.g. "https://testnet.binance.vision"; production when missing
+    pub base_urls: HashMap<Venue, String>,
+    // extra headers sent with every request to a venue, e.g. a User-Agent; values are never
+    // logged
+    pub http_headers: HashMap<Venue, HashMap<String, String>>,
+    // share of each profitable trade's realized profit moved into a reserve that is never
+    // traded, 0 to keep everything in the trading balance
+    pub profit_reserve_fraction: f64,
+    // after every cycle, convert the traded symbol's base currency back towards this holding
+    // on each venue, through its quote currency, whenever the venue's holding drifts outside
+    // the tolerance; unlisted currencies are never converted
+    pub conversion_targets: HashMap<Currency, ConversionTarget>,
+    // taker rate charged on Binance; replaced each cycle by the VIP tier the rolling volume
+    // reaches when binance_vip_tiers is set
+    pub binance_fee_rate: f64,
+    pub binance_vip_tiers: Vec<VipTier>,
+    pub binance_volume_window_ms: u64,
+    // where the rolling volume is kept across restarts
+    pub binance_volume_path: Option<String>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            symbol: Symbol::BTCBRL,
+            enabled_symbols: HashMap::new(),
+            buy_venues: HashMap::new(),
+            sell_venues: HashMap::new(),
+            scan_symbols: Vec::new(),
+            scan_listed_symbols: false,
+            execute_scan_opportunities: false,
+            mode: ExecutionMode::Simulated,
+            run_mode: RunMode::Once,
+            poll_interval_ms: 5_000,
+            maintenance_backoff_ms: None,
+            watchdog_stall_ms: None,
+            watchdog_unwind_ms: 10_000,
+            poll_jitter_pct: 10.0,
+            adaptive_poll: None,
+            binance_weight_limit: 6_000,
+            binance_weight_throttle_pct: 80.0,
+            reconcile_tolerance: 1e-8,
+            reconcile_tolerances: HashMap::new(),
+            snap_to_reported_balances: false,
+            market_making: false,
+            hedged_inventory: None,
+            mm_edge_bps: 10.0,
+            mm_requote_bps: 5.0,
+            fair_value: FairValue::Mid,
+            slippage_bps: 0.0,
+            decimal_comma: false,
+            import_diffs: false,
+            slippage_model_path: None,
+            slippage_model: SlippageModel::default(),
+            max_book_age_ms: None,
+            clock_skew_refresh_secs: 300,
+            max_book_age_gap_ms: None,
+            discover_symbols: false,
+            symbol_cache_path: None,
+            symbol_cache_ttl_ms: 24 * 60 * 60 * 1000,
+            min_notional: HashMap::new(),
+            spread_ema_period: 20,
+            spread_ema_factor: None,
+            dust_thresholds: default_dust_thresholds(),
+            round_reported_amounts: false,
+            allow_short: true,
+            fixed_clock_ms: None,
+            time_in_force: TimeInForce::default(),
+            post_only_venue: None,
+            maker_queue: None,
+            order_status_timeout_ms: 5_000,
+            leg_timeouts_ms: HashMap::new(),
+            order_status_poll_ms: 250,
+            leg_imbalance_tolerance: 1e-8,
+            confirm_before_execution: false,
+            concurrent_legs: false,
+            ladder_slices: 1,
+            ladder_spacing_ms: 0,
+            route_corrective_orders: false,
+            max_trade_qty: None,
+            kline_sizing: None,
+            max_open_positions: None,
+            position_settle_ms: 60_000,
+            charge_transfer_costs: false,
+            withdrawal_fees: WithdrawalFees::default(),
+            transfer_settlement_trades: None,
+            usdt_peg_band: None,
+            max_reference_deviation_pct: None,
+            reference_price_ttl_ms: 60_000,
+            cross_quote: false,
+            fx_rate_ttl_ms: 60_000,
+            base_currency: Currency::BRL,
+            starting_balance: 50_000.0,
+            min_profit: 0.0,
+            profit_hysteresis: None,
+            safety_margin: 0.0,
+            safety_margin_pct: 0.0,
+            max_spread: None,
+            max_spread_pct: None,
+            sell_venue_priority: vec![Venue::MercadoBitcoin, Venue::Binance],
+            holding_cost_rate: 0.0,
+            admin_api: false,
+            admin_api_bind: "127.0.0.1:8787".to_string(),
+            http_proxy: None,
+            http_pool_max_idle_per_host: None,
+            http_pool_idle_timeout_ms: None,
+            http2_prior_knowledge: false,
+            secrets_path: None,
+            accounts: Vec::new(),
+            account_policy: AccountPolicy::default(),
+            imbalance_levels: 5,
+            min_direction_imbalance: None,
+            drop_zero_qty_levels: true,
+            lenient_book_parsing: false,
+            min_book_levels: None,
+            resync_crossed_books: false,
+            validate_book_checksums: false,
+            book_retry_delay_ms: 100,
+            book_ttl_ms: HashMap::new(),
+            decision_capture_dir: None,
+            log_trade_depth: false,
+            pnl_export_path: None,
+            fee_currencies: HashMap::new(),
+            binance_fee_rate: BINANCE_FEE_RATE,
+            fee_buffer: 1.0,
+            base_urls: HashMap::new(),
+            http_headers: HashMap::new(),
+            profit_reserve_fraction: 0.0,
+            conversion_targets: HashMap::new(),
+            binance_vip_tiers: Vec::new(),
+            binance_volume_window_ms: 30 * 24 * 60 * 60 * 1000,
+            binance_volume_path: None,
+        }
+    }
+}
+
+impl Config {
+    pub fn symbol_enabled(&self, symbol: Symbol) -> bool {
+        self.enabled_symbols.get(&symbol).copied().unwrap_or(true)
+    }
+
+    // whether buying on `buy` and selling on `sell` is within the venue restrictions
+    pub fn direction_allowed(&self, symbol: Symbol, buy: Venue, sell: Venue) -> bool {
+        let allows = |venues: &HashMap<Symbol, Vec<Venue>>, venue: Venue| {
+            venues
+                .get(&symbol)
+                .is_none_or(|allowed| allowed.contains(&venue))
+        };
+        allows(&self.buy_venues, buy) && allows(&self.sell_venues, sell)
+    }
+
+    pub fn fee_currency_for(&self, venue: Venue) -> FeeCurrency {
+        self.fee_currencies.get(&venue).copied().unwrap_or_default()
+    }
+
+    // the calibrated slippage for this size when there is one, the flat slippage_bps otherwise
+    pub fn slippage_bps_for(&self, symbol: Symbol, qty: f64) -> f64 {
+        self.slippage_model
+            .bps(symbol, qty)
+            .unwrap_or(self.slippage_bps)
+    }
+
+    pub fn reconcile_tolerance_for(&self, currency: Currency) -> f64 {
+        self.reconcile_tolerances
+            .get(&currency)
+            .copied()
+            .unwrap_or(self.reconcile_tolerance)
+    }
+
+    pub fn check_market_making(&self) -> Result<(), String> {
+        if self.market_making && self.mode != ExecutionMode::Simulated {
+            return Err(format!(
+                "market_making is experimental and only runs in simulated mode, not {:?}",
+                self.mode
+            ));
+        }
+        Ok(())
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, Box<dyn Error>> {
+        let contents = fs::read_to_string(path)?;
+        let mut config: Config = serde_json::from_str(&contents)?;
+        if let Some(path) = &config.slippage_model_path {
+            config.slippage_model = SlippageModel::load(path)?;
+        }
+        if let Some(adaptive_poll) = &config.adaptive_poll {
+            adaptive_poll
+                .check()
+                .map_err(|message| format!("adaptive_poll: {}", message))?;
+        }
+        if let Some(profit_hysteresis) = &config.profit_hysteresis {
+            profit_hysteresis
+                .check()
+                .map_err(|message| format!("profit_hysteresis: {}", message))?;
+        }
+        Ok(config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{get_default_balance, get_fee_rate};
+
+    #[test]
+    fn loading_rejects_inverted_adaptive_poll_bounds() {
+        let path = std::env::temp_dir().join("inverted_adaptive_poll.json");
+        fs::write(
+            &path,
+            r#"{"adaptive_poll": {"min_interval_ms": 2000, "max_interval_ms": 500,
+                "volatile_bps": 10.0, "calm_bps": 1.0}}"#,
+        )
+        .unwrap();
+        let error = Config::from_file(path.to_str().unwrap()).unwrap_err();
+        assert!(error.to_string().contains("min_interval_ms"), "{}", error);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn loading_rejects_an_entry_profit_below_the_exit() {
+        let path = std::env::temp_dir().join("inverted_profit_hysteresis.json");
+        fs::write(
+            &path,
+            r#"{"profit_hysteresis": {"enter_profit": 5.0, "exit_profit": 10.0}}"#,
+        )
+        .unwrap();
+        let error = Config::from_file(path.to_str().unwrap()).unwrap_err();
+        assert!(error.to_string().contains("enter_profit"), "{}", error);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn the_default_keeps_the_legacy_constants() {
+        let config = Config::default();
+        assert_eq!(config.symbol, Symbol::BTCBRL);
+        assert_eq!(config.mode, ExecutionMode::Simulated);
+        assert_eq!(config.run_mode, RunMode::Once);
+        assert_eq!(config.binance_fee_rate, 0.001);
+        assert_eq!(config.fee_buffer, 1.0);
+        assert_eq!(
+            get_default_balance(config.base_currency, config.starting_balance),
+            HashMap::from([
+                (Currency::BRL, 50_000.0),
+                (Currency::BTC, 0.0),
+                (Currency::ETH, 0.0),
+                (Currency::USDT, 0.0),
+            ])
+        );
+
+        // MB's tiers by deal total, each edge in its own tier
+        let tiers = [
+            (500_000.0, 0.007),
+            (1_000_000.0, 0.006),
+            (2_000_000.0, 0.005),
+            (5_000_000.0, 0.0045),
+            (10_000_000.0, 0.004),
+            (20_000_000.0, 0.003),
+            (20_000_001.0, 0.0025),
+        ];
+        for (deal_total, rate) in tiers {
+            assert_eq!(
+                get_fee_rate(&config, Venue::MercadoBitcoin, deal_total, 1.0),
+                rate,
+                "deal total {}",
+                deal_total
+            );
+        }
+    }
+}
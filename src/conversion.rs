@@ -0,0 +1,18 @@
+use serde::{Deserialize, Serialize};
+
+// how much of a currency to hold, converting what drifts further than `tolerance` from
+// `target` back through the traded symbol's quote currency
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ConversionTarget {
+    pub target: f64,
+    pub tolerance: f64,
+}
+
+impl ConversionTarget {
+    // quantity to sell, or to buy when negative, to bring `held` back to the target; None
+    // while it is within the band
+    pub fn excess(&self, held: f64) -> Option<f64> {
+        let excess = held - self.target;
+        (excess.abs() > self.tolerance).then_some(excess)
+    }
+}
@@ -0,0 +1,173 @@
+use std::{collections::HashMap, env, error::Error, fmt, fs};
+
+use serde::Deserialize;
+
+// a key pair for an exchange's authenticated endpoints; Debug never prints the secret
+#[derive(Clone, Deserialize)]
+pub struct ApiCredentials {
+    pub api_key: String,
+    pub api_secret: String,
+}
+
+impl ApiCredentials {
+    fn from_env(prefix: &str) -> Option<Self> {
+        Some(Self {
+            api_key: env::var(format!("{}_API_KEY", prefix)).ok()?,
+            api_secret: env::var(format!("{}_API_SECRET", prefix)).ok()?,
+        })
+    }
+
+    fn is_complete(&self) -> bool {
+        !self.api_key.is_empty() && !self.api_secret.is_empty()
+    }
+
+    // enough of the key to tell accounts apart in logs
+    pub fn masked_key(&self) -> String {
+        let prefix: String = self.api_key.chars().take(4).collect();
+        format!("{}…", prefix)
+    }
+}
+
+impl fmt::Debug for ApiCredentials {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ApiCredentials")
+            .field("api_key", &self.masked_key())
+            .field("api_secret", &"<redacted>")
+            .finish()
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize)]
+#[serde(default)]
+pub struct Credentials {
+    pub binance: Option<ApiCredentials>,
+    pub mercado_bitcoin: Option<ApiCredentials>,
+    // key pairs of named sub-accounts, only read from the secrets file
+    pub accounts: HashMap<String, Credentials>,
+}
+
+impl Credentials {
+    // the secrets file, if any, is read first; BINANCE_* and MB_* environment variables
+    // take precedence over it
+    pub fn load(secrets_path: Option<&str>) -> Result<Self, Box<dyn Error>> {
+        let mut credentials = match secrets_path {
+            Some(path) => serde_json::from_str(&fs::read_to_string(path)?)?,
+            None => Self::default(),
+        };
+        if let Some(binance) = ApiCredentials::from_env("BINANCE") {
+            credentials.binance = Some(binance);
+        }
+        if let Some(mercado_bitcoin) = ApiCredentials::from_env("MB") {
+            credentials.mercado_bitcoin = Some(mercado_bitcoin);
+        }
+        Ok(credentials)
+    }
+
+    // without sub-accounts the top-level pairs are required, otherwise every named account
+    // needs its own
+    pub fn missing(&self, account_names: &[String]) -> Vec<String> {
+        if account_names.is_empty() {
+            return self
+                .missing_pairs()
+                .into_iter()
+                .map(str::to_string)
+                .collect();
+        }
+        account_names
+            .iter()
+            .flat_map(|name| match self.accounts.get(name) {
+                Some(account) => account
+                    .missing_pairs()
+                    .into_iter()
+                    .map(|pair| format!("{} for account {}", pair, name))
+                    .collect(),
+                None => vec![format!("account {}", name)],
+            })
+            .collect()
+    }
+
+    fn missing_pairs(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if !self
+            .binance
+            .as_ref()
+            .is_some_and(ApiCredentials::is_complete)
+        {
+            missing.push("BINANCE_API_KEY/BINANCE_API_SECRET");
+        }
+        if !self
+            .mercado_bitcoin
+            .as_ref()
+            .is_some_and(ApiCredentials::is_complete)
+        {
+            missing.push("MB_API_KEY/MB_API_SECRET");
+        }
+        missing
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pair(api_key: &str, api_secret: &str) -> Option<ApiCredentials> {
+        Some(ApiCredentials {
+            api_key: api_key.to_string(),
+            api_secret: api_secret.to_string(),
+        })
+    }
+
+    #[test]
+    fn a_pair_needs_both_variables() {
+        // prefixes of their own, so no other test or the real environment interferes
+        assert!(ApiCredentials::from_env("CREDENTIALS_TEST_UNSET").is_none());
+        env::set_var("CREDENTIALS_TEST_HALF_API_KEY", "key");
+        assert!(ApiCredentials::from_env("CREDENTIALS_TEST_HALF").is_none());
+        env::set_var("CREDENTIALS_TEST_FULL_API_KEY", "key");
+        env::set_var("CREDENTIALS_TEST_FULL_API_SECRET", "secret");
+        let credentials = ApiCredentials::from_env("CREDENTIALS_TEST_FULL").unwrap();
+        assert_eq!(credentials.api_key, "key");
+        assert_eq!(credentials.api_secret, "secret");
+    }
+
+    #[test]
+    fn missing_and_empty_pairs_are_reported() {
+        let credentials = Credentials {
+            binance: pair("key", ""),
+            ..Credentials::default()
+        };
+        assert_eq!(
+            credentials.missing(&[]),
+            [
+                "BINANCE_API_KEY/BINANCE_API_SECRET",
+                "MB_API_KEY/MB_API_SECRET"
+            ]
+        );
+
+        let credentials = Credentials {
+            accounts: HashMap::from([(
+                "alice".to_string(),
+                Credentials {
+                    binance: pair("key", "secret"),
+                    ..Credentials::default()
+                },
+            )]),
+            ..Credentials::default()
+        };
+        assert_eq!(
+            credentials.missing(&["alice".to_string(), "bob".to_string()]),
+            ["MB_API_KEY/MB_API_SECRET for account alice", "account bob"]
+        );
+    }
+
+    #[test]
+    fn debug_output_never_shows_the_secret() {
+        let credentials = pair("abcdefgh", "hunter2").unwrap();
+        let debug = format!("{:?}", credentials);
+        assert_eq!(
+            debug,
+            r#"ApiCredentials { api_key: "abcd…", api_secret: "<redacted>" }"#
+        );
+        assert!(!debug.contains("hunter2") && !debug.contains("efgh"));
+    }
+}
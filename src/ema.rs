@@ -0,0 +1,101 @@
+use std::collections::HashMap;
+
+use crate::Symbol;
+
+#[derive(Clone, Copy, Debug)]
+pub struct Ema {
+    alpha: f64,
+    value: Option<f64>,
+}
+
+impl Ema {
+    pub fn with_period(period: usize) -> Self {
+        Self {
+            alpha: 2.0 / (period as f64 + 1.0),
+            value: None,
+        }
+    }
+
+    pub fn value(&self) -> Option<f64> {
+        self.value
+    }
+
+    pub fn update(&mut self, sample: f64) -> f64 {
+        let value = match self.value {
+            Some(previous) => previous + self.alpha * (sample - previous),
+            None => sample,
+        };
+        self.value = Some(value);
+        value
+    }
+}
+
+// net spread EMA per symbol, carried across cycles
+pub struct SpreadEmas {
+    period: usize,
+    emas: HashMap<Symbol, Ema>,
+}
+
+impl SpreadEmas {
+    pub fn new(period: usize) -> Self {
+        Self {
+            period,
+            emas: HashMap::new(),
+        }
+    }
+
+    pub fn get(&self, symbol: Symbol) -> Option<f64> {
+        self.emas.get(&symbol).and_then(Ema::value)
+    }
+
+    pub fn observe(&mut self, symbol: Symbol, net_spread: f64) -> f64 {
+        self.emas
+            .entry(symbol)
+            .or_insert_with(|| Ema::with_period(self.period))
+            .update(net_spread)
+    }
+}
+
+// true when the spread beats its average by `factor`, scaling away from zero so that a
+// negative average still demands a proportionally better spread
+pub fn exceeds_ema(net_spread: f64, ema: f64, factor: f64) -> bool {
+    net_spread > ema + ema.abs() * (factor - 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_first_sample_seeds_the_average_and_later_ones_are_smoothed() {
+        // a period of 3 weighs each new sample by 2 / (3 + 1)
+        let mut ema = Ema::with_period(3);
+        assert_eq!(ema.value(), None);
+        assert_eq!(ema.update(10.0), 10.0);
+        assert_eq!(ema.update(20.0), 15.0);
+        assert_eq!(ema.update(15.0), 15.0);
+        assert_eq!(ema.update(7.0), 11.0);
+        assert_eq!(ema.value(), Some(11.0));
+    }
+
+    #[test]
+    fn each_symbol_keeps_its_own_average() {
+        let mut emas = SpreadEmas::new(3);
+        emas.observe(Symbol::BTCBRL, 10.0);
+        emas.observe(Symbol::BTCBRL, 20.0);
+        emas.observe(Symbol::ETHBRL, -4.0);
+        assert_eq!(emas.get(Symbol::BTCBRL), Some(15.0));
+        assert_eq!(emas.get(Symbol::ETHBRL), Some(-4.0));
+        assert_eq!(emas.get(Symbol::USDTBRL), None);
+    }
+
+    #[test]
+    fn the_spread_has_to_beat_the_average_by_the_factor() {
+        assert!(exceeds_ema(16.0, 10.0, 1.5));
+        assert!(!exceeds_ema(15.0, 10.0, 1.5));
+        // a negative average still asks for a better spread, not a worse one
+        assert!(exceeds_ema(-4.0, -10.0, 1.5));
+        assert!(!exceeds_ema(-6.0, -10.0, 1.5));
+        assert!(!exceeds_ema(-10.0, -10.0, 1.0));
+    }
+}
@@ -0,0 +1,761 @@
+use std::{
+    collections::HashMap,
+    sync::atomic::{AtomicU32, Ordering},
+};
+
+use reqwest::{
+    header::{HeaderMap, HeaderName, HeaderValue},
+    Client, Error, IntoUrl, RequestBuilder, Response,
+};
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{json, Value};
+
+use crate::{
+    clock::unix_millis,
+    credentials::ApiCredentials,
+    executor::Order,
+    number::parse_number,
+    output::log,
+    signing::SignedRequest,
+    sizing::Kline,
+    symbols::{BinanceExchangeInfo, MBSymbolsData},
+    BinanceOrderBookData, Currency, MBOrderBookData, OrderBook, Side, Symbol, Venue,
+};
+
+const BINANCE_BASE_URL: &str = "https://api.binance.com";
+// MB serves books from its public data API and everything else from API v4
+const MB_DATA_BASE_URL: &str = "https://www.mercadobitcoin.net";
+const MB_API_BASE_URL: &str = "https://api.mercadobitcoin.net";
+
+pub trait Exchange {
+    const VENUE: Venue;
+
+    async fn fetch_order_book(&self, symbol: Symbol) -> Result<OrderBook, Error>;
+
+    // None when the venue's balances can't be had, e.g. without credentials, which is not
+    // the same as holding nothing
+    async fn balances(&self) -> Result<Option<HashMap<Currency, f64>>, Error>;
+}
+
+// both exchanges go through a `reqwest::Client`, which callers can supply pre-configured
+// with proxies, headers or TLS settings
+#[derive(Default)]
+pub struct Binance {
+    client: Client,
+    credentials: Option<ApiCredentials>,
+    // request weight used in the current minute, as last reported by Binance
+    used_weight: AtomicU32,
+    // book levels fetched per side, at least one
+    depth: u32,
+    // keep the valid side of a book whose other side fails to parse
+    lenient: bool,
+    // overrides the production host, e.g. for the Spot Testnet
+    base_url: Option<String>,
+    // sent with every request, e.g. a User-Agent
+    headers: HeaderMap,
+}
+
+#[derive(Default)]
+pub struct MercadoBitcoin {
+    client: Client,
+    credentials: Option<ApiCredentials>,
+    depth: u32,
+    lenient: bool,
+    // overrides both production hosts, e.g. for a staging environment
+    base_url: Option<String>,
+    headers: HeaderMap,
+}
+
+// one side of a book parsed on its own; a malformed side is logged and comes back empty, which
+// still leaves the direction that only needs the other side
+fn lenient_side<T: DeserializeOwned>(book: &Value, venue: &str, side: &str) -> Vec<T> {
+    match serde_json::from_value(book[side].clone()) {
+        Ok(levels) => levels,
+        Err(error) => {
+            log!("dropping malformed {} {}: {}", venue, side, error);
+            Vec::new()
+        }
+    }
+}
+
+// Binance quotes levels as strings, which must also parse as numbers
+fn lenient_binance_side(book: &Value, side: &str) -> Vec<[String; 2]> {
+    let levels: Vec<[String; 2]> = lenient_side(book, "Binance", side);
+    if levels
+        .iter()
+        .flatten()
+        .any(|number| parse_number(number, false).is_err())
+    {
+        log!("dropping malformed Binance {}: non-numeric level", side);
+        return Vec::new();
+    }
+    levels
+}
+
+// configured headers, checked up front so that a typo fails at startup rather than on the
+// first request
+pub fn header_map(headers: &HashMap<String, String>) -> Result<HeaderMap, String> {
+    let mut map = HeaderMap::new();
+    for (name, value) in headers {
+        let header_name = HeaderName::from_bytes(name.as_bytes())
+            .map_err(|_| format!("invalid header name {:?}", name))?;
+        // the value may be a key, so it stays out of the message
+        let mut header_value = HeaderValue::from_str(value)
+            .map_err(|_| format!("invalid value for header {:?}", name))?;
+        header_value.set_sensitive(true);
+        map.insert(header_name, header_value);
+    }
+    Ok(map)
+}
+
+// how long after its timestamp Binance still accepts a signed request
+const BINANCE_RECV_WINDOW_MS: u64 = 5_000;
+
+#[derive(Deserialize)]
+struct BinanceAccount {
+    balances: Vec<BinanceAssetBalance>,
+}
+
+#[derive(Deserialize)]
+struct BinanceAssetBalance {
+    asset: String,
+    free: String,
+    locked: String,
+}
+
+#[derive(Deserialize)]
+struct MBAuthorization {
+    access_token: String,
+}
+
+#[derive(Deserialize)]
+struct MBAccount {
+    id: String,
+}
+
+#[derive(Deserialize)]
+struct MBAssetBalance {
+    symbol: String,
+    // available plus on hold
+    total: String,
+}
+
+#[derive(Deserialize)]
+struct BinanceSystemStatus {
+    // 0 normal, 1 system maintenance
+    status: u8,
+}
+
+#[derive(Deserialize)]
+struct BinanceServerTime {
+    #[serde(rename = "serverTime")]
+    server_time: u64,
+}
+
+impl Binance {
+    pub fn with_client(client: Client) -> Self {
+        Self {
+            client,
+            credentials: None,
+            used_weight: AtomicU32::new(0),
+            depth: 1,
+            lenient: false,
+            base_url: None,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    pub fn with_depth(self, depth: u32) -> Self {
+        Self { depth, ..self }
+    }
+
+    pub fn with_lenient_parsing(self, lenient: bool) -> Self {
+        Self { lenient, ..self }
+    }
+
+    pub fn with_base_url(self, base_url: Option<String>) -> Self {
+        Self { base_url, ..self }
+    }
+
+    pub fn with_headers(self, headers: HeaderMap) -> Self {
+        Self { headers, ..self }
+    }
+
+    fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url).headers(self.headers.clone())
+    }
+
+    fn post(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.post(url).headers(self.headers.clone())
+    }
+
+    pub fn with_credentials(self, credentials: Option<ApiCredentials>) -> Self {
+        Self {
+            credentials,
+            ..self
+        }
+    }
+
+    fn url(&self, path: &str) -> String {
+        let base_url = self.base_url.as_deref().unwrap_or(BINANCE_BASE_URL);
+        format!("{}{}", base_url.trim_end_matches('/'), path)
+    }
+
+    pub fn used_weight(&self) -> u32 {
+        self.used_weight.load(Ordering::Relaxed)
+    }
+
+    fn record_weight(&self, response: &Response) {
+        let used_weight = response
+            .headers()
+            .get("x-mbx-used-weight-1m")
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.parse::<u32>().ok());
+        if let Some(used_weight) = used_weight {
+            self.used_weight.store(used_weight, Ordering::Relaxed);
+        }
+    }
+
+    pub async fn server_time(&self) -> Result<u64, Error> {
+        let response = self.get(self.url("/api/v3/time")).send().await?;
+        self.record_weight(&response);
+        // a rate-limited or failed request isn't a server time, whatever its body says
+        let server_time: BinanceServerTime = response.error_for_status()?.json().await?;
+        Ok(server_time.server_time)
+    }
+
+    pub async fn in_maintenance(&self) -> Result<bool, Error> {
+        let system_status: BinanceSystemStatus = self
+            .get(self.url("/sapi/v1/system/status"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(system_status.status == 1)
+    }
+
+    pub async fn fetch_depth(&self, binance_symbol: &str) -> Result<OrderBook, Error> {
+        let url = self.url(&format!(
+            "/api/v3/depth?symbol={}&limit={}",
+            binance_symbol,
+            self.depth.max(1)
+        ));
+        let response = self.get(&url).send().await?;
+        self.record_weight(&response);
+        let response = response.error_for_status()?;
+        let order_book = if self.lenient {
+            let value: Value = response.json().await?;
+            BinanceOrderBookData {
+                last_update_id: value["lastUpdateId"].as_u64().unwrap_or_default(),
+                bids: lenient_binance_side(&value, "bids"),
+                asks: lenient_binance_side(&value, "asks"),
+            }
+        } else {
+            response.json::<BinanceOrderBookData>().await?
+        };
+        let mut order_book: OrderBook = order_book.into();
+        order_book.fetched_at_ms = Some(unix_millis());
+        Ok(order_book)
+    }
+
+    // validates the order against Binance's test endpoint without placing it; false when there
+    // are no credentials to sign it with
+    pub async fn test_order(&self, order: &Order) -> Result<bool, Error> {
+        let Some(credentials) = &self.credentials else {
+            return Ok(false);
+        };
+        let side = match order.leg.side {
+            Side::Buy => "BUY",
+            Side::Sell => "SELL",
+        };
+        let query = SignedRequest::new()
+            .param("symbol", order.symbol.get_binance_symbol_param())
+            .param("side", side)
+            .param("type", "LIMIT")
+            .param("timeInForce", format!("{:?}", order.time_in_force))
+            .param("quantity", order.qty)
+            .param("price", order.price)
+            .query(
+                &credentials.api_secret,
+                unix_millis(),
+                BINANCE_RECV_WINDOW_MS,
+            );
+        let url = self.url(&format!("/api/v3/order/test?{}", query));
+        let response = self
+            .post(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send()
+            .await?;
+        self.record_weight(&response);
+        response.error_for_status()?;
+        Ok(true)
+    }
+
+    // the most recent `limit` klines of `interval`, e.g. "1h"; malformed rows are skipped
+    pub async fn klines(
+        &self,
+        binance_symbol: &str,
+        interval: &str,
+        limit: u32,
+    ) -> Result<Vec<Kline>, Error> {
+        let url = self.url(&format!(
+            "/api/v3/klines?symbol={}&interval={}&limit={}",
+            binance_symbol, interval, limit
+        ));
+        let response = self.get(&url).send().await?;
+        self.record_weight(&response);
+        let rows: Vec<Vec<Value>> = response.error_for_status()?.json().await?;
+        Ok(rows.iter().filter_map(|row| Kline::from_row(row)).collect())
+    }
+
+    pub async fn exchange_info(&self) -> Result<BinanceExchangeInfo, Error> {
+        let response = self.get(self.url("/api/v3/exchangeInfo")).send().await?;
+        self.record_weight(&response);
+        let exchange_info: BinanceExchangeInfo = response.error_for_status()?.json().await?;
+        Ok(exchange_info)
+    }
+}
+
+impl MercadoBitcoin {
+    pub fn with_client(client: Client) -> Self {
+        Self {
+            client,
+            credentials: None,
+            depth: 1,
+            lenient: false,
+            base_url: None,
+            headers: HeaderMap::new(),
+        }
+    }
+
+    pub fn with_depth(self, depth: u32) -> Self {
+        Self { depth, ..self }
+    }
+
+    pub fn with_lenient_parsing(self, lenient: bool) -> Self {
+        Self { lenient, ..self }
+    }
+
+    pub fn with_base_url(self, base_url: Option<String>) -> Self {
+        Self { base_url, ..self }
+    }
+
+    pub fn with_headers(self, headers: HeaderMap) -> Self {
+        Self { headers, ..self }
+    }
+
+    fn get(&self, url: impl IntoUrl) -> RequestBuilder {
+        self.client.get(url).headers(self.headers.clone())
+    }
+
+    pub fn with_credentials(self, credentials: Option<ApiCredentials>) -> Self {
+        Self {
+            credentials,
+            ..self
+        }
+    }
+
+    fn url(&self, default_base_url: &str, path: &str) -> String {
+        let base_url = self.base_url.as_deref().unwrap_or(default_base_url);
+        format!("{}{}", base_url.trim_end_matches('/'), path)
+    }
+
+    pub async fn listed_symbols(&self) -> Result<Vec<String>, Error> {
+        let response = self
+            .get(self.url(MB_API_BASE_URL, "/api/v4/symbols"))
+            .send()
+            .await?;
+        let symbols: MBSymbolsData = response.error_for_status()?.json().await?;
+        Ok(symbols.symbol)
+    }
+}
+
+impl Exchange for Binance {
+    const VENUE: Venue = Venue::Binance;
+
+    async fn fetch_order_book(&self, symbol: Symbol) -> Result<OrderBook, Error> {
+        self.fetch_depth(symbol.get_binance_symbol_param()).await
+    }
+
+    async fn balances(&self) -> Result<Option<HashMap<Currency, f64>>, Error> {
+        let Some(credentials) = &self.credentials else {
+            return Ok(None);
+        };
+        let query = SignedRequest::new().query(
+            &credentials.api_secret,
+            unix_millis(),
+            BINANCE_RECV_WINDOW_MS,
+        );
+        let url = self.url(&format!("/api/v3/account?{}", query));
+        let response = self
+            .get(&url)
+            .header("X-MBX-APIKEY", &credentials.api_key)
+            .send()
+            .await?;
+        self.record_weight(&response);
+        let response = response.error_for_status()?;
+        let account: BinanceAccount = response.json().await?;
+
+        let mut balances = HashMap::new();
+        for balance in account.balances {
+            let Some(currency) = Currency::from_asset(&balance.asset) else {
+                continue;
+            };
+            let free = balance.free.parse::<f64>().unwrap_or_default();
+            let locked = balance.locked.parse::<f64>().unwrap_or_default();
+            balances.insert(currency, free + locked);
+        }
+        Ok(Some(balances))
+    }
+}
+
+impl Exchange for MercadoBitcoin {
+    const VENUE: Venue = Venue::MercadoBitcoin;
+
+    async fn fetch_order_book(&self, symbol: Symbol) -> Result<OrderBook, Error> {
+        let symbol = symbol.get_mb_symbol_param();
+        let url = self.url(
+            MB_DATA_BASE_URL,
+            &format!("/api/{}/orderbook?limit={}", symbol, self.depth.max(1)),
+        );
+        let response = self.get(&url).send().await?.error_for_status()?;
+        let order_book = if self.lenient {
+            let value: Value = response.json().await?;
+            MBOrderBookData {
+                timestamp: value["timestamp"].as_u64().unwrap_or_default(),
+                bids: lenient_side(&value, "MB", "bids"),
+                asks: lenient_side(&value, "MB", "asks"),
+            }
+        } else {
+            response.json::<MBOrderBookData>().await?
+        };
+        let mut order_book: OrderBook = order_book.into();
+        order_book.fetched_at_ms = Some(unix_millis());
+        Ok(order_book)
+    }
+
+    // API v4 trades the key pair for a bearer token first; the bot trades from the key's
+    // first account
+    async fn balances(&self) -> Result<Option<HashMap<Currency, f64>>, Error> {
+        let Some(credentials) = &self.credentials else {
+            return Ok(None);
+        };
+        let authorization: MBAuthorization = self
+            .client
+            .post(self.url(MB_API_BASE_URL, "/api/v4/authorize"))
+            .headers(self.headers.clone())
+            .json(&json!({
+                "login": credentials.api_key,
+                "password": credentials.api_secret,
+            }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let bearer = format!("Bearer {}", authorization.access_token);
+        let accounts: Vec<MBAccount> = self
+            .get(self.url(MB_API_BASE_URL, "/api/v4/accounts"))
+            .header("Authorization", &bearer)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        let Some(account) = accounts.first() else {
+            log!("MB key {} has no accounts", credentials.masked_key());
+            return Ok(None);
+        };
+        let url = self.url(
+            MB_API_BASE_URL,
+            &format!("/api/v4/accounts/{}/balances", account.id),
+        );
+        let asset_balances: Vec<MBAssetBalance> = self
+            .get(&url)
+            .header("Authorization", &bearer)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+
+        let mut balances = HashMap::new();
+        for balance in asset_balances {
+            let Some(currency) = Currency::from_asset(&balance.symbol) else {
+                continue;
+            };
+            balances.insert(currency, balance.total.parse::<f64>().unwrap_or_default());
+        }
+        Ok(Some(balances))
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use std::sync::Arc;
+
+    use tokio::{
+        io::{AsyncReadExt, AsyncWriteExt},
+        net::{TcpListener, TcpStream},
+        task::JoinHandle,
+    };
+
+    use super::*;
+
+    // the request head and as much of its body as content-length announces
+    async fn read_request(stream: &mut TcpStream) -> String {
+        let mut request = Vec::new();
+        let mut chunk = [0; 1024];
+        loop {
+            let read = stream.read(&mut chunk).await.unwrap();
+            request.extend_from_slice(&chunk[..read]);
+            let text = String::from_utf8_lossy(&request).into_owned();
+            let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                if read == 0 {
+                    return text;
+                }
+                continue;
+            };
+            let content_length = head
+                .lines()
+                .find_map(|line| {
+                    line.to_lowercase()
+                        .strip_prefix("content-length:")
+                        .map(|length| length.trim().parse().unwrap_or(0))
+                })
+                .unwrap_or(0);
+            if read == 0 || body.len() >= content_length {
+                return text;
+            }
+        }
+    }
+
+    // answers a single request with `status`, the extra header lines in `headers` and `body`,
+    // returning the base url to reach it and the request as it arrived
+    pub(crate) async fn respond_with(
+        status: &'static str,
+        headers: &'static str,
+        body: &'static str,
+    ) -> (String, JoinHandle<String>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let request = tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let request = read_request(&mut stream).await;
+            let response = format!(
+                "HTTP/1.1 {}\r\ncontent-type: application/json\r\ncontent-length: {}\r\n{}connection: close\r\n\r\n{}",
+                status,
+                body.len(),
+                headers,
+                body
+            );
+            stream.write_all(response.as_bytes()).await.unwrap();
+            request
+        });
+        (format!("http://{}", address), request)
+    }
+
+    // answers one request per body in turn, each 200 OK, returning the base url and the
+    // requests as they arrived
+    async fn respond_in_turn(bodies: &'static [&'static str]) -> (String, JoinHandle<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let requests = tokio::spawn(async move {
+            let mut requests = Vec::new();
+            for body in bodies {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                let request = read_request(&mut stream).await;
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\nconnection: close\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                stream.write_all(response.as_bytes()).await.unwrap();
+                requests.push(request);
+            }
+            requests
+        });
+        (format!("http://{}", address), requests)
+    }
+
+    // answers every request with 200 and `body`, keeping connections open for reuse; returns
+    // the base url and a count of the connections accepted
+    pub(crate) async fn respond_keeping_alive(body: &'static str) -> (String, Arc<AtomicU32>) {
+        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let address = listener.local_addr().unwrap();
+        let connections = Arc::new(AtomicU32::new(0));
+        let accepted = connections.clone();
+        tokio::spawn(async move {
+            loop {
+                let (mut stream, _) = listener.accept().await.unwrap();
+                accepted.fetch_add(1, Ordering::Relaxed);
+                tokio::spawn(async move {
+                    while !read_request(&mut stream).await.is_empty() {
+                        let response = format!(
+                            "HTTP/1.1 200 OK\r\ncontent-type: application/json\r\ncontent-length: {}\r\n\r\n{}",
+                            body.len(),
+                            body
+                        );
+                        stream.write_all(response.as_bytes()).await.unwrap();
+                    }
+                });
+            }
+        });
+        (format!("http://{}", address), connections)
+    }
+
+    async fn respond_once(status: &'static str, body: &'static str) -> String {
+        respond_with(status, "", body).await.0
+    }
+
+    #[tokio::test]
+    async fn server_time_rejects_error_responses() {
+        let base_url = respond_once("429 Too Many Requests", r#"{"serverTime": 5}"#).await;
+        let binance = Binance::default().with_base_url(Some(base_url));
+        assert!(binance.server_time().await.is_err());
+
+        let base_url = respond_once("200 OK", r#"{"serverTime": 5}"#).await;
+        let binance = Binance::default().with_base_url(Some(base_url));
+        assert_eq!(binance.server_time().await.unwrap(), 5);
+    }
+
+    #[tokio::test]
+    async fn depth_and_exchange_info_surface_maintenance_responses() {
+        let body = r#"{"lastUpdateId": 1, "bids": [], "asks": [], "symbols": []}"#;
+        let base_url = respond_once("503 Service Unavailable", body).await;
+        let binance = Binance::default().with_base_url(Some(base_url));
+        let error = binance.fetch_depth("BTCBRL").await.unwrap_err();
+        assert!(crate::maintenance::is_maintenance_error(&error));
+
+        let base_url = respond_once("503 Service Unavailable", body).await;
+        let binance = Binance::default().with_base_url(Some(base_url));
+        let error = binance.exchange_info().await.unwrap_err();
+        assert!(crate::maintenance::is_maintenance_error(&error));
+    }
+
+    #[tokio::test]
+    async fn mb_balances_come_from_the_first_account() {
+        let (base_url, requests) = respond_in_turn(&[
+            r#"{"access_token": "token", "expiration": 1}"#,
+            r#"[{"id": "a1", "name": "main"}, {"id": "a2", "name": "other"}]"#,
+            r#"[{"symbol": "BRL", "available": "900", "on_hold": "100", "total": "1000"},
+                {"symbol": "BTC", "available": "0.5", "on_hold": "0", "total": "0.5"},
+                {"symbol": "XYZ", "available": "1", "on_hold": "0", "total": "1"}]"#,
+        ])
+        .await;
+        let mb = MercadoBitcoin::default()
+            .with_base_url(Some(base_url))
+            .with_credentials(Some(ApiCredentials {
+                api_key: "key".to_string(),
+                api_secret: "secret".to_string(),
+            }));
+        let balances = mb.balances().await.unwrap().unwrap();
+        assert_eq!(
+            balances,
+            HashMap::from([(Currency::BRL, 1_000.0), (Currency::BTC, 0.5)])
+        );
+
+        let requests = requests.await.unwrap();
+        assert!(requests[0].starts_with("POST /api/v4/authorize "));
+        assert!(requests[0].contains(r#""login":"key""#));
+        assert!(requests[1].starts_with("GET /api/v4/accounts "));
+        assert!(requests[2].starts_with("GET /api/v4/accounts/a1/balances "));
+        assert!(requests[2]
+            .to_lowercase()
+            .contains("authorization: bearer token"));
+    }
+
+    #[tokio::test]
+    async fn mb_balances_need_credentials() {
+        assert_eq!(MercadoBitcoin::default().balances().await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn requests_go_to_the_overridden_base_url() {
+        let (base_url, request) = respond_with(
+            "200 OK",
+            "",
+            r#"{"lastUpdateId": 1, "bids": [["9900", "1"]], "asks": [["10000", "1"]]}"#,
+        )
+        .await;
+        // a trailing slash doesn't double up with the path
+        let binance = Binance::default().with_base_url(Some(format!("{}/", base_url)));
+        let book = binance.fetch_order_book(Symbol::BTCBRL).await.unwrap();
+        assert_eq!(book.asks[0].price.0, 10_000.0);
+        assert!(request
+            .await
+            .unwrap()
+            .starts_with("GET /api/v3/depth?symbol=BTCBRL&limit=1 "));
+
+        // the one override stands in for both of MB's hosts
+        let (base_url, request) = respond_with(
+            "200 OK",
+            "",
+            r#"{"timestamp": 1, "bids": [[10500.0, 1.0]], "asks": [[10600.0, 1.0]]}"#,
+        )
+        .await;
+        let mb = MercadoBitcoin::default().with_base_url(Some(base_url));
+        let book = mb.fetch_order_book(Symbol::BTCBRL).await.unwrap();
+        assert_eq!(book.bids[0].price.0, 10_500.0);
+        assert!(request
+            .await
+            .unwrap()
+            .starts_with("GET /api/BTC/orderbook?limit=1 "));
+        let (base_url, request) = respond_with("200 OK", "", r#"{"symbol": ["BTC-BRL"]}"#).await;
+        let mb = MercadoBitcoin::default().with_base_url(Some(base_url));
+        assert_eq!(mb.listed_symbols().await.unwrap(), ["BTC-BRL"]);
+        assert!(request.await.unwrap().starts_with("GET /api/v4/symbols "));
+    }
+
+    #[tokio::test]
+    async fn configured_headers_go_out_with_every_request() {
+        let headers = header_map(&HashMap::from([(
+            "X-Api-Tag".to_string(),
+            "desk-7".to_string(),
+        )]))
+        .unwrap();
+        let (base_url, request) = respond_with("200 OK", "", r#"{"serverTime": 5}"#).await;
+        let binance = Binance::default()
+            .with_base_url(Some(base_url))
+            .with_headers(headers);
+        binance.server_time().await.unwrap();
+        assert!(request.await.unwrap().contains("x-api-tag: desk-7\r\n"));
+    }
+
+    #[test]
+    fn a_malformed_header_is_refused_without_echoing_its_value() {
+        let headers = |name: &str, value: &str| {
+            header_map(&HashMap::from([(name.to_string(), value.to_string())]))
+        };
+        assert_eq!(
+            headers("bad header", "x").unwrap_err(),
+            r#"invalid header name "bad header""#
+        );
+        let error = headers("X-Key", "secret\n").unwrap_err();
+        assert!(!error.contains("secret"), "{}", error);
+    }
+
+    #[tokio::test]
+    async fn an_injected_client_is_the_one_that_sends() {
+        let client = Client::builder()
+            .user_agent("arbitrage-test/1")
+            .build()
+            .unwrap();
+        let (base_url, request) = respond_with("200 OK", "", r#"{"serverTime": 5}"#).await;
+        let binance = Binance::with_client(client.clone()).with_base_url(Some(base_url));
+        binance.server_time().await.unwrap();
+        assert!(request
+            .await
+            .unwrap()
+            .contains("user-agent: arbitrage-test/1\r\n"));
+
+        let (base_url, request) = respond_with("200 OK", "", r#"{"symbol": []}"#).await;
+        let mb = MercadoBitcoin::with_client(client).with_base_url(Some(base_url));
+        mb.listed_symbols().await.unwrap();
+        assert!(request
+            .await
+            .unwrap()
+            .contains("user-agent: arbitrage-test/1\r\n"));
+    }
+}
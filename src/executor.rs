@@ -0,0 +1,456 @@
+use std::sync::Mutex;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::{Clock, SystemClock},
+    units::Qty,
+    Fill, Leg, OrderBook, Side, Symbol, Venue,
+};
+#[cfg(feature = "io")]
+use {
+    crate::{config::Config, output::log},
+    reqwest::Error,
+    std::time::Duration,
+    tokio::time::sleep,
+    tokio_util::sync::CancellationToken,
+};
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "UPPERCASE")]
+pub enum TimeInForce {
+    // rests on the book until filled or cancelled
+    GTC,
+    // fills what it can immediately, the rest is cancelled
+    #[default]
+    IOC,
+    // fills completely and immediately or not at all
+    FOK,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Order {
+    pub leg: Leg,
+    pub symbol: Symbol,
+    pub price: f64,
+    pub qty: f64,
+    pub time_in_force: TimeInForce,
+    // rejected instead of executed if it would cross the book on arrival
+    pub post_only: bool,
+}
+
+// lifecycle of an order on the exchange, named as in Binance's order status strings
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum OrderStatus {
+    New,
+    PartiallyFilled,
+    Filled,
+    Canceled,
+    Rejected,
+    Expired,
+}
+
+impl OrderStatus {
+    pub fn is_terminal(&self) -> bool {
+        !matches!(self, Self::New | Self::PartiallyFilled)
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct OrderState {
+    pub status: OrderStatus,
+    // cumulative filled portion so far
+    pub fill: Fill,
+}
+
+#[cfg(feature = "io")]
+pub trait OrderExecutor {
+    // submits the order, returning the id its status can be queried with
+    async fn place(&self, order: &Order) -> Result<u64, Error>;
+
+    async fn order_status(&self, symbol: Symbol, order_id: u64) -> Result<OrderState, Error>;
+
+    // cancels whatever of the order is still open, returning its final state
+    async fn cancel(&self, symbol: Symbol, order_id: u64) -> Result<OrderState, Error>;
+
+    // what order timeouts are measured against
+    fn clock(&self) -> &dyn Clock;
+}
+
+// places the order and polls its status until it reaches a terminal state or the timeout
+// passes, then returns whatever was filled
+#[cfg(feature = "io")]
+pub async fn execute(
+    executor: &impl OrderExecutor,
+    order: &Order,
+    config: &Config,
+) -> Result<Fill, Error> {
+    execute_leg(executor, order, config, &CancellationToken::new()).await
+}
+
+// like execute, for one of several legs in flight together: timing out cancels `legs` so
+// the others stop too, and the leg stops early once another, or a halt, has cancelled it
+#[cfg(feature = "io")]
+pub async fn execute_leg(
+    executor: &impl OrderExecutor,
+    order: &Order,
+    config: &Config,
+    legs: &CancellationToken,
+) -> Result<Fill, Error> {
+    let order_id = executor.place(order).await?;
+    let timeout = Duration::from_millis(
+        config
+            .leg_timeouts_ms
+            .get(&order.leg.venue)
+            .copied()
+            .unwrap_or(config.order_status_timeout_ms),
+    );
+    let clock = executor.clock();
+    let started = clock.instant();
+
+    loop {
+        let state = executor.order_status(order.symbol, order_id).await?;
+        if state.status.is_terminal() {
+            return Ok(state.fill);
+        }
+        // IOC and FOK resolve on arrival, so a single check is enough for them
+        if order.time_in_force != TimeInForce::GTC {
+            log!(
+                "order {} still {:?}, booking the {} filled so far",
+                order_id,
+                state.status,
+                state.fill.qty
+            );
+            return Ok(state.fill);
+        }
+        let elapsed = clock.instant() - started;
+        if elapsed >= timeout {
+            legs.cancel();
+            let state = executor.cancel(order.symbol, order_id).await?;
+            log!(
+                "order {} timed out after {:?}, cancelled with {} filled",
+                order_id,
+                elapsed,
+                state.fill.qty
+            );
+            return Ok(state.fill);
+        }
+        tokio::select! {
+            _ = sleep(Duration::from_millis(config.order_status_poll_ms)) => {}
+            _ = legs.cancelled() => {
+                let state = executor.cancel(order.symbol, order_id).await?;
+                log!(
+                    "order {} cancelled along with its other legs, with {} filled",
+                    order_id,
+                    state.fill.qty
+                );
+                return Ok(state.fill);
+            }
+        }
+    }
+}
+
+// how a resting maker order fills in simulation: the volume expected to trade against its
+// side within horizon_ms first clears the quantity queued ahead of it, and only the rest
+// reaches the order
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct MakerQueue {
+    // base quantity traded per second against the side the order rests on
+    pub trade_flow_per_sec: f64,
+    pub horizon_ms: u64,
+}
+
+impl MakerQueue {
+    // the share of `qty` expected to fill behind `ahead` already queued at or better than it
+    pub fn fill_probability(&self, ahead: f64, qty: f64) -> f64 {
+        if qty <= 0.0 {
+            return 0.0;
+        }
+        let traded = self.trade_flow_per_sec * self.horizon_ms as f64 / 1000.0;
+        ((traded - ahead) / qty).clamp(0.0, 1.0)
+    }
+}
+
+// fills orders against the books fetched this cycle
+pub struct SimulatedExecutor<'a> {
+    binance_order_book: &'a OrderBook,
+    mb_order_book: &'a OrderBook,
+    // without one, a post-only order that doesn't cross never fills
+    maker_queue: Option<MakerQueue>,
+    orders: Mutex<Vec<OrderState>>,
+    clock: &'a dyn Clock,
+}
+
+impl<'a> SimulatedExecutor<'a> {
+    pub fn new(binance_order_book: &'a OrderBook, mb_order_book: &'a OrderBook) -> Self {
+        Self {
+            binance_order_book,
+            mb_order_book,
+            maker_queue: None,
+            orders: Mutex::new(Vec::new()),
+            clock: &SystemClock,
+        }
+    }
+
+    pub fn with_maker_queue(mut self, maker_queue: Option<MakerQueue>) -> Self {
+        self.maker_queue = maker_queue;
+        self
+    }
+
+    pub fn with_clock(mut self, clock: &'a dyn Clock) -> Self {
+        self.clock = clock;
+        self
+    }
+}
+
+impl SimulatedExecutor<'_> {
+    fn order_book(&self, venue: Venue) -> &OrderBook {
+        match venue {
+            Venue::Binance => self.binance_order_book,
+            Venue::MercadoBitcoin => self.mb_order_book,
+        }
+    }
+
+    // resting quantity on the order's own side at its price or better, filled before it
+    fn queue_ahead(&self, order: &Order) -> f64 {
+        let order_book = self.order_book(order.leg.venue);
+        match order.leg.side {
+            Side::Buy => {
+                order_book
+                    .bids
+                    .iter()
+                    .filter(|bid| bid.price.0 >= order.price)
+                    .map(|bid| bid.qty)
+                    .sum::<Qty>()
+                    .0
+            }
+            Side::Sell => {
+                order_book
+                    .asks
+                    .iter()
+                    .filter(|ask| ask.price.0 <= order.price)
+                    .map(|ask| ask.qty)
+                    .sum::<Qty>()
+                    .0
+            }
+        }
+    }
+
+    fn liquidity_at(&self, order: &Order) -> f64 {
+        let order_book = self.order_book(order.leg.venue);
+        match order.leg.side {
+            Side::Buy => {
+                order_book
+                    .asks
+                    .iter()
+                    .filter(|ask| ask.price.0 <= order.price)
+                    .map(|ask| ask.qty)
+                    .sum::<Qty>()
+                    .0
+            }
+            Side::Sell => {
+                order_book
+                    .bids
+                    .iter()
+                    .filter(|bid| bid.price.0 >= order.price)
+                    .map(|bid| bid.qty)
+                    .sum::<Qty>()
+                    .0
+            }
+        }
+    }
+}
+
+#[cfg(feature = "io")]
+impl OrderExecutor for SimulatedExecutor<'_> {
+    async fn place(&self, order: &Order) -> Result<u64, Error> {
+        sleep(Duration::from_secs(1)).await;
+
+        let liquidity = self.liquidity_at(order);
+        let maker_fill = match self.maker_queue {
+            Some(maker_queue) if order.post_only && liquidity <= 0.0 => {
+                order.qty * maker_queue.fill_probability(self.queue_ahead(order), order.qty)
+            }
+            _ => 0.0,
+        };
+        let filled_qty = match order.time_in_force {
+            // a post-only order never takes liquidity, it only fills from the queue
+            _ if order.post_only => maker_fill,
+            TimeInForce::FOK if liquidity < order.qty => 0.0,
+            // the unfilled GTC remainder would rest on the book
+            TimeInForce::GTC | TimeInForce::IOC | TimeInForce::FOK => {
+                f64::min(order.qty, liquidity)
+            }
+        };
+        let status = match order.time_in_force {
+            _ if order.post_only && liquidity > 0.0 => OrderStatus::Rejected,
+            _ if order.post_only && filled_qty >= order.qty => OrderStatus::Filled,
+            _ if order.post_only && filled_qty > 0.0 => OrderStatus::PartiallyFilled,
+            _ if order.post_only => OrderStatus::New,
+            _ if filled_qty >= order.qty => OrderStatus::Filled,
+            TimeInForce::GTC if filled_qty > 0.0 => OrderStatus::PartiallyFilled,
+            TimeInForce::GTC => OrderStatus::New,
+            TimeInForce::IOC | TimeInForce::FOK => OrderStatus::Expired,
+        };
+
+        let mut orders = self.orders.lock().unwrap();
+        orders.push(OrderState {
+            status,
+            fill: Fill {
+                leg: order.leg,
+                symbol: order.symbol,
+                price: order.price,
+                qty: filled_qty,
+            },
+        });
+        Ok(orders.len() as u64 - 1)
+    }
+
+    async fn order_status(&self, _symbol: Symbol, order_id: u64) -> Result<OrderState, Error> {
+        let orders = self.orders.lock().unwrap();
+        Ok(orders[order_id as usize])
+    }
+
+    async fn cancel(&self, _symbol: Symbol, order_id: u64) -> Result<OrderState, Error> {
+        let mut orders = self.orders.lock().unwrap();
+        let state = &mut orders[order_id as usize];
+        if !state.status.is_terminal() {
+            state.status = OrderStatus::Canceled;
+        }
+        Ok(*state)
+    }
+
+    fn clock(&self) -> &dyn Clock {
+        self.clock
+    }
+}
+
+#[cfg(all(test, feature = "io"))]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+    use crate::clock::MockClock;
+
+    fn resting_buy() -> Order {
+        Order {
+            leg: Leg {
+                venue: Venue::Binance,
+                side: Side::Buy,
+            },
+            symbol: Symbol::BTCBRL,
+            price: 10_000.0,
+            qty: 1.0,
+            time_in_force: TimeInForce::GTC,
+            post_only: false,
+        }
+    }
+
+    #[tokio::test]
+    async fn a_resting_gtc_order_times_out_as_the_clock_advances() {
+        let binance = OrderBook::builder().ask(10_000.0, 0.4).build();
+        let mb = OrderBook::builder().bid(10_000.0, 0.3).build();
+        let clock = MockClock::new(0);
+        let executor = SimulatedExecutor::new(&binance, &mb).with_clock(&clock);
+        let config = Config {
+            order_status_timeout_ms: 5_000,
+            order_status_poll_ms: 10,
+            // the sibling leg would rest well past the first one's timeout on its own
+            leg_timeouts_ms: HashMap::from([(Venue::MercadoBitcoin, 60_000)]),
+            ..Config::default()
+        };
+        let legs = CancellationToken::new();
+        let order = resting_buy();
+        let sibling = Order {
+            leg: Leg {
+                venue: Venue::MercadoBitcoin,
+                side: Side::Sell,
+            },
+            ..order
+        };
+
+        let (fill, sibling_fill, _) = tokio::join!(
+            execute_leg(&executor, &order, &config, &legs),
+            execute_leg(&executor, &sibling, &config, &legs),
+            async {
+                // placing takes a second in simulation, then both orders rest
+                sleep(Duration::from_millis(1_200)).await;
+                assert!(!legs.is_cancelled());
+                clock.advance(5_000);
+            }
+        );
+        assert_eq!(fill.unwrap().qty, 0.4);
+        assert_eq!(sibling_fill.unwrap().qty, 0.3);
+        assert!(legs.is_cancelled(), "timing out cancels the other legs");
+        for order_id in 0..2 {
+            let state = executor
+                .order_status(Symbol::BTCBRL, order_id)
+                .await
+                .unwrap();
+            assert_eq!(state.status, OrderStatus::Canceled, "order {}", order_id);
+        }
+    }
+
+    #[tokio::test]
+    async fn a_halt_cancels_a_resting_gtc_order() {
+        let binance = OrderBook::builder().ask(10_000.0, 0.4).build();
+        let mb = OrderBook::builder().build();
+        let executor = SimulatedExecutor::new(&binance, &mb);
+        let order = resting_buy();
+        let halt = CancellationToken::new();
+        halt.cancel();
+
+        let fill = execute_leg(&executor, &order, &Config::default(), &halt.child_token())
+            .await
+            .unwrap();
+        assert_eq!(fill.qty, 0.4);
+        let state = executor.order_status(Symbol::BTCBRL, 0).await.unwrap();
+        assert_eq!(state.status, OrderStatus::Canceled);
+    }
+
+    #[tokio::test]
+    async fn a_post_only_order_that_would_cross_is_rejected() {
+        let binance = OrderBook::builder()
+            .bid(9_900.0, 1.0)
+            .ask(10_000.0, 1.0)
+            .build();
+        let mb = OrderBook::builder().build();
+        let executor = SimulatedExecutor::new(&binance, &mb);
+        let post_only = |price| Order {
+            price,
+            post_only: true,
+            ..resting_buy()
+        };
+
+        let crossing = executor.place(&post_only(10_000.0)).await.unwrap();
+        let state = executor
+            .order_status(Symbol::BTCBRL, crossing)
+            .await
+            .unwrap();
+        assert_eq!(state.status, OrderStatus::Rejected);
+        assert_eq!(state.fill.qty, 0.0);
+
+        let resting = executor.place(&post_only(9_950.0)).await.unwrap();
+        let state = executor
+            .order_status(Symbol::BTCBRL, resting)
+            .await
+            .unwrap();
+        assert_eq!(state.status, OrderStatus::New);
+    }
+
+    #[test]
+    fn the_queue_ahead_is_cleared_before_the_order_fills() {
+        // 2 traded over the horizon
+        let maker_queue = MakerQueue {
+            trade_flow_per_sec: 0.5,
+            horizon_ms: 4_000,
+        };
+        assert_eq!(maker_queue.fill_probability(0.0, 1.0), 1.0);
+        assert_eq!(maker_queue.fill_probability(1.5, 1.0), 0.5);
+        assert_eq!(maker_queue.fill_probability(1.0, 4.0), 0.25);
+        // a queue the flow never gets through, and nothing left to fill
+        assert_eq!(maker_queue.fill_probability(3.0, 1.0), 0.0);
+        assert_eq!(maker_queue.fill_probability(0.0, 0.0), 0.0);
+    }
+}
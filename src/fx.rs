@@ -0,0 +1,32 @@
+#[derive(Clone, Copy, Debug)]
+pub struct FxRate {
+    pub rate: f64,
+    pub fetched_at_ms: u64,
+}
+
+// last known rate, only handed out while younger than its TTL
+pub struct FxCache {
+    ttl_ms: u64,
+    rate: Option<FxRate>,
+}
+
+impl FxCache {
+    pub fn new(ttl_ms: u64) -> Self {
+        Self { ttl_ms, rate: None }
+    }
+
+    pub fn fresh_rate(&self, now_ms: u64) -> Option<f64> {
+        let rate = self.rate?;
+        if now_ms.saturating_sub(rate.fetched_at_ms) > self.ttl_ms {
+            return None;
+        }
+        Some(rate.rate)
+    }
+
+    pub fn update(&mut self, rate: f64, now_ms: u64) {
+        self.rate = Some(FxRate {
+            rate,
+            fetched_at_ms: now_ms,
+        });
+    }
+}
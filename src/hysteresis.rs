@@ -0,0 +1,94 @@
+use serde::{Deserialize, Serialize};
+
+use crate::output::log;
+
+// separate profit thresholds to start and to keep trading, in base_currency like min_profit,
+// so a profit hovering around a single threshold doesn't flip the bot every cycle
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct ProfitHysteresis {
+    pub enter_profit: f64,
+    pub exit_profit: f64,
+}
+
+impl ProfitHysteresis {
+    pub fn check(&self) -> Result<(), String> {
+        if self.enter_profit < self.exit_profit {
+            return Err(format!(
+                "enter_profit {} is below exit_profit {}",
+                self.enter_profit, self.exit_profit
+            ));
+        }
+        Ok(())
+    }
+}
+
+// which of the two thresholds applies, given whether the last evaluation found a trade
+#[derive(Debug, Default)]
+pub struct ProfitThreshold {
+    trading: bool,
+}
+
+impl ProfitThreshold {
+    pub fn current(&self, min_profit: f64, hysteresis: Option<ProfitHysteresis>) -> f64 {
+        match hysteresis {
+            Some(hysteresis) if self.trading => hysteresis.exit_profit,
+            Some(hysteresis) => hysteresis.enter_profit,
+            None => min_profit,
+        }
+    }
+
+    pub fn observe(&mut self, found_opportunity: bool, hysteresis: Option<ProfitHysteresis>) {
+        let Some(hysteresis) = hysteresis else {
+            return;
+        };
+        if found_opportunity && !self.trading {
+            log!(
+                "profit above {}, trading until it drops below {}",
+                hysteresis.enter_profit,
+                hysteresis.exit_profit
+            );
+        } else if !found_opportunity && self.trading {
+            log!(
+                "profit below {}, waiting for it to exceed {}",
+                hysteresis.exit_profit,
+                hysteresis.enter_profit
+            );
+        }
+        self.trading = found_opportunity;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_profit_hovering_between_the_thresholds_keeps_the_state() {
+        let hysteresis = Some(ProfitHysteresis {
+            enter_profit: 10.0,
+            exit_profit: 5.0,
+        });
+        let mut threshold = ProfitThreshold::default();
+        let mut states = Vec::new();
+        for profit in [8.0, 11.0, 8.0, 6.0, 8.0, 4.0, 8.0, 6.0] {
+            let found = profit >= threshold.current(0.0, hysteresis);
+            threshold.observe(found, hysteresis);
+            states.push(found);
+        }
+        assert_eq!(states, [false, true, true, true, true, false, false, false]);
+    }
+
+    #[test]
+    fn an_entry_below_the_exit_is_rejected() {
+        let hysteresis = ProfitHysteresis {
+            enter_profit: 5.0,
+            exit_profit: 10.0,
+        };
+        assert!(hysteresis.check().is_err());
+        let hysteresis = ProfitHysteresis {
+            enter_profit: 10.0,
+            exit_profit: 10.0,
+        };
+        assert!(hysteresis.check().is_ok());
+    }
+}
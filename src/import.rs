@@ -0,0 +1,301 @@
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fs,
+};
+
+use crate::{
+    get_default_balance, incremental::IncrementalOrderBook, number::parse_number, output::log,
+    Config, DecisionInput, OrderBook, Symbol, Venue,
+};
+
+// one level of a book recorded by another tool
+#[derive(Clone, Copy, Debug)]
+struct Row {
+    venue: Venue,
+    symbol: Symbol,
+    timestamp_ms: u64,
+    bid: bool,
+    price: f64,
+    qty: f64,
+    // of the venue's book once this timestamp's rows are applied
+    checksum: Option<i32>,
+}
+
+fn parse_venue(name: &str) -> Option<Venue> {
+    match name.to_lowercase().as_str() {
+        "binance" => Some(Venue::Binance),
+        "mb" | "mercadobitcoin" | "mercado_bitcoin" => Some(Venue::MercadoBitcoin),
+        _ => None,
+    }
+}
+
+fn parse_symbol(name: &str) -> Option<Symbol> {
+    match name.to_uppercase().replace(['-', '_', '/'], "").as_str() {
+        "BTCBRL" => Some(Symbol::BTCBRL),
+        "USDTBRL" => Some(Symbol::USDTBRL),
+        "ETHBRL" => Some(Symbol::ETHBRL),
+        _ => None,
+    }
+}
+
+// exchange,symbol,timestamp,side,price,qty with an optional trailing checksum, or separated
+// by semicolons when the numbers use a decimal comma
+fn parse_row(line: &str, decimal_comma: bool) -> Option<Row> {
+    let delimiter = if decimal_comma { ';' } else { ',' };
+    let fields: Vec<&str> = line.split(delimiter).map(str::trim).collect();
+    let (venue, symbol, timestamp_ms, side, price, qty, checksum) = match fields[..] {
+        [venue, symbol, timestamp_ms, side, price, qty] => {
+            (venue, symbol, timestamp_ms, side, price, qty, None)
+        }
+        [venue, symbol, timestamp_ms, side, price, qty, checksum] => (
+            venue,
+            symbol,
+            timestamp_ms,
+            side,
+            price,
+            qty,
+            Some(checksum.parse().ok()?),
+        ),
+        _ => return None,
+    };
+    let bid = match side.to_lowercase().as_str() {
+        "bid" | "buy" => true,
+        "ask" | "sell" => false,
+        _ => return None,
+    };
+    let price = parse_number(price, decimal_comma).ok()?;
+    let qty = parse_number(qty, decimal_comma).ok()?;
+    if !price.is_finite() || price <= 0.0 || !qty.is_finite() || qty < 0.0 {
+        return None;
+    }
+    Some(Row {
+        venue: parse_venue(venue)?,
+        symbol: parse_symbol(symbol)?,
+        timestamp_ms: timestamp_ms.parse().ok()?,
+        bid,
+        price,
+        qty,
+        checksum,
+    })
+}
+
+// rebuilds the recorded books as decision inputs, one per timestamp once both venues have a
+// book for the symbol; a venue without rows at a timestamp keeps its last book, and with
+// import_diffs the rows update that book instead of replacing it; with
+// validate_book_checksums a book failing its checksum is dropped until a later update
+// matches one on its own
+pub fn import_csv(path: &str, config: &Config) -> Result<Vec<DecisionInput>, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)?;
+    let mut snapshots: BTreeMap<u64, HashMap<(Symbol, Venue), Vec<Row>>> = BTreeMap::new();
+    for (index, line) in contents.lines().enumerate() {
+        let line = line.trim();
+        if line.is_empty() || (index == 0 && line.to_lowercase().starts_with("exchange")) {
+            continue;
+        }
+        match parse_row(line, config.decimal_comma) {
+            Some(row) => snapshots
+                .entry(row.timestamp_ms)
+                .or_default()
+                .entry((row.symbol, row.venue))
+                .or_default()
+                .push(row),
+            None => log!("skipping malformed row {} of {}: {}", index + 1, path, line),
+        }
+    }
+
+    let balance = get_default_balance(config.base_currency, config.starting_balance);
+    let mut books_by_venue: HashMap<(Symbol, Venue), IncrementalOrderBook> = HashMap::new();
+    let mut latest: HashMap<(Symbol, Venue), OrderBook> = HashMap::new();
+    // books that failed their checksum, left out until an update matches again
+    let mut resyncing: HashSet<(Symbol, Venue)> = HashSet::new();
+    let mut inputs = Vec::new();
+    for (timestamp_ms, books) in snapshots {
+        let mut symbols: Vec<Symbol> = Vec::new();
+        for (symbol, _) in books.keys() {
+            if !symbols.contains(symbol) {
+                symbols.push(*symbol);
+            }
+        }
+        for ((symbol, venue), rows) in books {
+            let incremental = books_by_venue.entry((symbol, venue)).or_default();
+            // while resyncing, each update is taken as a fresh snapshot until one matches
+            if !config.import_diffs || resyncing.contains(&(symbol, venue)) {
+                incremental.clear();
+            }
+            let checksum = rows.iter().rev().find_map(|row| row.checksum);
+            for row in rows {
+                if row.bid {
+                    incremental.apply_bid(row.price, row.qty);
+                } else {
+                    incremental.apply_ask(row.price, row.qty);
+                }
+            }
+            if config.validate_book_checksums && config.import_diffs {
+                let matches = checksum.map(|expected| expected == incremental.checksum());
+                if matches == Some(false) {
+                    log!(
+                        "{:?} {:?} book at {} failed its checksum, resyncing",
+                        venue,
+                        symbol,
+                        timestamp_ms
+                    );
+                    incremental.clear();
+                    resyncing.insert((symbol, venue));
+                    latest.remove(&(symbol, venue));
+                    continue;
+                }
+                if resyncing.contains(&(symbol, venue)) {
+                    if matches.is_none() {
+                        continue;
+                    }
+                    resyncing.remove(&(symbol, venue));
+                }
+            }
+            let mut book = incremental.snapshot();
+            book.updated_at_ms = Some(timestamp_ms);
+            latest.insert((symbol, venue), book);
+        }
+        for symbol in symbols {
+            let (Some(binance), Some(mb)) = (
+                latest.get(&(symbol, Venue::Binance)),
+                latest.get(&(symbol, Venue::MercadoBitcoin)),
+            ) else {
+                continue;
+            };
+            let mut config = config.clone();
+            config.symbol = symbol;
+            inputs.push(DecisionInput {
+                binance_order_book: binance.clone(),
+                mb_order_book: mb.clone(),
+                balance: balance.clone(),
+                // every importable symbol is quoted in BRL, like the default base currency
+                min_profit: config.min_profit,
+                config,
+            });
+        }
+    }
+    Ok(inputs)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn import(name: &str, rows: &str, config: &Config) -> Vec<DecisionInput> {
+        let path = std::env::temp_dir().join(name);
+        fs::write(&path, rows).unwrap();
+        let inputs = import_csv(path.to_str().unwrap(), config).unwrap();
+        fs::remove_file(path).unwrap();
+        inputs
+    }
+
+    fn checksum_of(levels: &[(bool, f64, f64)]) -> i32 {
+        let mut book = IncrementalOrderBook::default();
+        for (bid, price, qty) in levels {
+            if *bid {
+                book.apply_bid(*price, *qty);
+            } else {
+                book.apply_ask(*price, *qty);
+            }
+        }
+        book.checksum()
+    }
+
+    #[test]
+    fn a_row_is_parsed_with_its_aliases() {
+        let row = parse_row("Mercado_Bitcoin, btc-brl, 5, buy, 10500.5, 0.25, -7", false).unwrap();
+        assert_eq!(row.venue, Venue::MercadoBitcoin);
+        assert_eq!(row.symbol, Symbol::BTCBRL);
+        assert_eq!(row.timestamp_ms, 5);
+        assert!(row.bid);
+        assert_eq!(
+            (row.price, row.qty, row.checksum),
+            (10_500.5, 0.25, Some(-7))
+        );
+    }
+
+    #[test]
+    fn malformed_rows_are_rejected() {
+        for line in [
+            "binance,BTCBRL,5,bid,10000",
+            "binance,BTCBRL,5,bid,10000,1,7,extra",
+            "binance,BTCBRL,five,bid,10000,1",
+            "binance,BTCBRL,5,middle,10000,1",
+            "binance,BTCBRL,5,bid,abc,1",
+            "binance,BTCBRL,5,bid,0,1",
+            "binance,BTCBRL,5,bid,10000,-1",
+            "binance,BTCBRL,5,bid,10000,1,not-a-checksum",
+            "kraken,BTCBRL,5,bid,10000,1",
+            "binance,DOGEBRL,5,bid,10000,1",
+        ] {
+            assert!(parse_row(line, false).is_none(), "{}", line);
+        }
+    }
+
+    #[test]
+    fn decimal_comma_rows_are_split_on_semicolons() {
+        let row = parse_row("binance;BTCBRL;5;ask;10.500,5;0,25", true).unwrap();
+        assert_eq!((row.price, row.qty), (10_500.5, 0.25));
+        assert!(!row.bid);
+        // a dot-decimal file read as decimal comma doesn't split into fields
+        assert!(parse_row("binance,BTCBRL,5,ask,10500.5,0.25", true).is_none());
+
+        let config = Config {
+            decimal_comma: true,
+            ..Config::default()
+        };
+        let rows = "exchange;symbol;timestamp;side;price;qty\n\
+                    mb;BTCBRL;1;bid;10.500,0;1,0\n\
+                    mb;BTCBRL;1;ask;10.600,0;1,0\n\
+                    binance;BTCBRL;1;bid;9.900,0;1,0\n\
+                    binance;BTCBRL;1;ask;10.000,0;1,0\n\
+                    binance;BTCBRL;1;ask;oops;1,0\n";
+        let inputs = import("decimal_comma.csv", rows, &config);
+        assert_eq!(inputs.len(), 1);
+        assert_eq!(inputs[0].binance_order_book.asks.len(), 1);
+        assert_eq!(inputs[0].mb_order_book.bids[0].price.0, 10_500.0);
+    }
+
+    #[test]
+    fn a_book_failing_its_checksum_is_resynced() {
+        let config = Config {
+            import_diffs: true,
+            validate_book_checksums: true,
+            ..Config::default()
+        };
+        let good = checksum_of(&[(true, 100.0, 1.0), (false, 101.0, 1.0)]);
+        let resynced = checksum_of(&[(true, 100.5, 1.0), (false, 101.0, 1.0)]);
+        let rows = format!(
+            "mb,BTCBRL,1,bid,105,1\n\
+             mb,BTCBRL,1,ask,106,1\n\
+             binance,BTCBRL,1,bid,100,1\n\
+             binance,BTCBRL,1,ask,101,1,{good}\n\
+             binance,BTCBRL,2,bid,99,1,{good}\n\
+             binance,BTCBRL,3,bid,98,1\n\
+             binance,BTCBRL,4,bid,100.5,1\n\
+             binance,BTCBRL,4,ask,101,1,{resynced}\n"
+        );
+        let inputs = import("checksum_resync.csv", &rows, &config);
+
+        // the book at 2 doesn't match, so nothing is decided on Binance's book at 2 or 3
+        assert_eq!(inputs.len(), 2);
+        let resynced_book = &inputs[1].binance_order_book;
+        assert_eq!(resynced_book.updated_at_ms, Some(4));
+        assert_eq!(resynced_book.bids.len(), 1);
+        assert_eq!(resynced_book.bids[0].price.0, 100.5);
+    }
+
+    #[test]
+    fn checksums_are_ignored_unless_validated() {
+        let config = Config {
+            import_diffs: true,
+            ..Config::default()
+        };
+        let rows = "mb,BTCBRL,1,bid,105,1\n\
+                    mb,BTCBRL,1,ask,106,1\n\
+                    binance,BTCBRL,1,bid,100,1,7\n\
+                    binance,BTCBRL,1,ask,101,1\n";
+        assert_eq!(import("checksum_ignored.csv", rows, &config).len(), 1);
+    }
+}
@@ -0,0 +1,147 @@
+use std::{cmp::Ordering, collections::BTreeMap};
+
+use crate::OrderBook;
+
+// a price as a map key; book prices are finite, where total_cmp is the numeric order
+#[derive(Clone, Copy, Debug)]
+struct PriceKey(f64);
+
+impl PartialEq for PriceKey {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for PriceKey {}
+
+impl PartialOrd for PriceKey {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for PriceKey {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.0.total_cmp(&other.0)
+    }
+}
+
+// a book kept up to date from (price, qty) updates, as diff feeds send them: a quantity
+// replaces whatever the level held and zero removes the level
+#[derive(Clone, Debug, Default)]
+pub struct IncrementalOrderBook {
+    bids: BTreeMap<PriceKey, f64>,
+    asks: BTreeMap<PriceKey, f64>,
+}
+
+fn apply(levels: &mut BTreeMap<PriceKey, f64>, price: f64, qty: f64) {
+    if qty > 0.0 {
+        levels.insert(PriceKey(price), qty);
+    } else {
+        levels.remove(&PriceKey(price));
+    }
+}
+
+impl IncrementalOrderBook {
+    pub fn apply_bid(&mut self, price: f64, qty: f64) {
+        apply(&mut self.bids, price, qty);
+    }
+
+    pub fn apply_ask(&mut self, price: f64, qty: f64) {
+        apply(&mut self.asks, price, qty);
+    }
+
+    // drops every level, e.g. before applying a full snapshot
+    pub fn clear(&mut self) {
+        self.bids.clear();
+        self.asks.clear();
+    }
+
+    pub fn checksum(&self) -> i32 {
+        checksum(&self.snapshot())
+    }
+
+    pub fn snapshot(&self) -> OrderBook {
+        let builder = self
+            .bids
+            .iter()
+            .fold(OrderBook::builder(), |builder, (price, qty)| {
+                builder.bid(price.0, *qty)
+            });
+        self.asks
+            .iter()
+            .fold(builder, |builder, (price, qty)| builder.ask(price.0, *qty))
+            .build()
+    }
+}
+
+// levels per side a checksum covers
+const CHECKSUM_DEPTH: usize = 25;
+
+// the checksum depth feeds publish with their updates: the CRC32 of the best 25 levels of
+// each side interleaved as bid price:bid qty:ask price:ask qty:..., a side that runs out
+// contributing nothing further, read as a signed 32-bit integer
+pub fn checksum(order_book: &OrderBook) -> i32 {
+    let mut fields = Vec::new();
+    for depth in 0..CHECKSUM_DEPTH {
+        for side in [&order_book.bids, &order_book.asks] {
+            if let Some(level) = side.get(depth) {
+                fields.push(format!("{}:{}", level.price.0, level.qty.0));
+            }
+        }
+    }
+    crc32(fields.join(":").as_bytes()) as i32
+}
+
+// CRC-32/ISO-HDLC, as zlib computes it
+fn crc32(bytes: &[u8]) -> u32 {
+    let mut crc = !0u32;
+    for byte in bytes {
+        crc ^= u32::from(*byte);
+        for _ in 0..8 {
+            crc = if crc & 1 == 1 {
+                (crc >> 1) ^ 0xEDB8_8320
+            } else {
+                crc >> 1
+            };
+        }
+    }
+    !crc
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crc32_matches_the_reference_check_value() {
+        assert_eq!(crc32(b"123456789"), 0xCBF4_3926);
+    }
+
+    #[test]
+    fn a_corrupted_book_fails_the_checksum() {
+        let mut book = IncrementalOrderBook::default();
+        book.apply_bid(100.0, 1.0);
+        book.apply_bid(99.0, 2.0);
+        book.apply_ask(101.0, 1.5);
+        let expected = book.checksum();
+
+        // a missed update leaves a level the venue has already removed
+        let mut corrupted = book.clone();
+        corrupted.apply_bid(98.0, 1.0);
+        assert_ne!(corrupted.checksum(), expected);
+
+        corrupted.apply_bid(98.0, 0.0);
+        assert_eq!(corrupted.checksum(), expected);
+    }
+
+    #[test]
+    fn interleaves_the_sides_best_first() {
+        let order_book = OrderBook::builder()
+            .bid(99.0, 2.0)
+            .bid(100.0, 1.0)
+            .ask(101.0, 1.5)
+            .build();
+        assert_eq!(checksum(&order_book), crc32(b"100:1:101:1.5:99:2") as i32);
+    }
+}
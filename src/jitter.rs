@@ -0,0 +1,74 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+// spreads poll intervals uniformly over base ± pct% so that separate instances drift apart
+// instead of hitting the APIs in lockstep
+pub struct Jitter {
+    pct: f64,
+    state: u64,
+}
+
+impl Jitter {
+    pub fn new(pct: f64) -> Self {
+        let seed = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|elapsed| elapsed.as_nanos() as u64)
+            .unwrap_or_default();
+        Self::with_seed(pct, seed)
+    }
+
+    pub fn with_seed(pct: f64, seed: u64) -> Self {
+        Self {
+            pct: pct.clamp(0.0, 100.0),
+            // xorshift never leaves zero
+            state: seed | 1,
+        }
+    }
+
+    // xorshift64, uniform in [0, 1)
+    fn next_unit(&mut self) -> f64 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    pub fn next_interval(&mut self, base: Duration) -> Duration {
+        if self.pct == 0.0 {
+            return base;
+        }
+        let factor = 1.0 + (self.next_unit() * 2.0 - 1.0) * self.pct / 100.0;
+        base.mul_f64(factor)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn a_seeded_jitter_stays_within_the_band() {
+        let base = Duration::from_millis(5_000);
+        let mut jitter = Jitter::with_seed(10.0, 42);
+        let intervals: Vec<Duration> = (0..1_000).map(|_| jitter.next_interval(base)).collect();
+        assert!(intervals
+            .iter()
+            .all(|interval| (4_500..=5_500).contains(&interval.as_millis())));
+        // spread over the band rather than stuck near the base
+        assert!(intervals
+            .iter()
+            .any(|interval| interval.as_millis() < 4_600));
+        assert!(intervals
+            .iter()
+            .any(|interval| interval.as_millis() > 5_400));
+
+        let mut same_seed = Jitter::with_seed(10.0, 42);
+        assert_eq!(same_seed.next_interval(base), intervals[0]);
+    }
+
+    #[test]
+    fn no_jitter_keeps_the_base_interval() {
+        let base = Duration::from_millis(5_000);
+        let mut jitter = Jitter::with_seed(0.0, 42);
+        assert!((0..10).all(|_| jitter.next_interval(base) == base));
+    }
+}
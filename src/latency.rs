@@ -0,0 +1,27 @@
+use std::time::Duration;
+
+// where one cycle's time went: fetching each venue's book, deciding, and executing
+#[derive(Clone, Copy, Debug, Default)]
+pub struct CycleLatency {
+    pub fetch_a: Duration,
+    pub fetch_b: Duration,
+    pub decide: Duration,
+    pub execute: Duration,
+}
+
+impl CycleLatency {
+    pub fn total(&self) -> Duration {
+        self.fetch_a + self.fetch_b + self.decide + self.execute
+    }
+}
+
+// nearest-rank percentile, `pct` in 0..=100
+pub fn percentile(samples: &[Duration], pct: f64) -> Option<Duration> {
+    if samples.is_empty() {
+        return None;
+    }
+    let mut sorted = samples.to_vec();
+    sorted.sort();
+    let rank = ((pct / 100.0) * sorted.len() as f64).ceil() as usize;
+    Some(sorted[rank.clamp(1, sorted.len()) - 1])
+}
@@ -0,0 +1,4628 @@
+#![allow(clippy::upper_case_acronyms)]
+// without io only the decision core runs, and the bookkeeping the loop does goes unused
+#![cfg_attr(not(feature = "io"), allow(dead_code))]
+
+mod accounts;
+mod adaptive;
+#[cfg(feature = "io")]
+mod admin;
+mod balance;
+mod book_cache;
+mod cli;
+mod clock;
+mod config;
+mod conversion;
+mod credentials;
+mod ema;
+#[cfg(feature = "io")]
+mod exchange;
+mod executor;
+mod fx;
+mod hysteresis;
+mod import;
+mod incremental;
+mod jitter;
+mod latency;
+#[cfg(feature = "io")]
+mod maintenance;
+mod number;
+mod output;
+mod pnl;
+#[cfg(feature = "io")]
+mod positions;
+#[cfg(feature = "io")]
+mod reconcile;
+#[cfg(feature = "io")]
+mod reference;
+mod routing;
+#[cfg(feature = "io")]
+mod signing;
+mod sizing;
+#[cfg(feature = "io")]
+mod skew;
+mod slippage;
+#[cfg(feature = "io")]
+mod state;
+mod stats;
+mod strategy;
+#[cfg(feature = "io")]
+mod symbols;
+mod transfer;
+mod units;
+mod validate;
+mod valuation;
+mod volume;
+#[cfg(feature = "io")]
+mod watchdog;
+mod whatif;
+
+use std::{cmp::Ordering, collections::HashMap};
+
+use balance::{check_sufficiency, tradeable_balance, Shortfall};
+use cli::Args;
+use config::{FairValue, FeeCurrency};
+use executor::{Order, TimeInForce};
+use number::parse_number;
+use output::log;
+use routing::route;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use strategy::{Arbitrage, HedgedPair, Strategy};
+use units::{Notional, Price, Qty};
+
+// what the polling loop, the exchange clients and the executors need on top of the core
+#[cfg(feature = "io")]
+use {
+    accounts::Accounts,
+    adaptive::AdaptiveInterval,
+    admin::RuntimeSettings,
+    balance::{holding_cost, required_funds},
+    book_cache::BookCache,
+    clock::{Clock, MockClock, SystemClock},
+    config::{ExecutionMode, RunMode},
+    credentials::Credentials,
+    ema::{exceeds_ema, SpreadEmas},
+    exchange::{header_map, Binance, Exchange, MercadoBitcoin},
+    executor::{execute, execute_leg, OrderExecutor, SimulatedExecutor},
+    futures_util::future::join_all,
+    fx::FxCache,
+    hysteresis::ProfitThreshold,
+    jitter::Jitter,
+    latency::CycleLatency,
+    maintenance::{is_maintenance_error, Maintenance},
+    pnl::PnlExport,
+    positions::OpenPositions,
+    reconcile::reconcile_balances,
+    reference::{deviation_pct, CoinGecko, ReferencePrice},
+    reqwest::Error,
+    sizing::TypicalTrade,
+    skew::{is_stale, ClockSkew},
+    state::{Reservation, SharedState, State},
+    stats::Stats,
+    std::{borrow::Cow, sync::Arc, time::Duration},
+    strategy::{apply_quote_update, MarketMaker},
+    symbols::SymbolRegistry,
+    tokio::time::{sleep, timeout},
+    tokio_util::sync::CancellationToken,
+    transfer::Netting,
+    valuation::{realized_net_profit, Marks},
+    volume::{tier_fee_rate, RollingVolume},
+    watchdog::Heartbeat,
+};
+
+pub use config::Config;
+
+const BINANCE_FEE_RATE: f64 = 0.001;
+
+fn parse_args() -> Args {
+    match Args::parse() {
+        Ok(args) => args,
+        Err(message) => {
+            eprintln!("{}", message);
+            std::process::exit(2);
+        }
+    }
+}
+
+// the commands that need nothing but their inputs, true if one of them ran
+fn run_offline(args: &Args) -> Result<bool, Box<dyn std::error::Error>> {
+    if args.help {
+        println!("{}", cli::USAGE);
+        return Ok(true);
+    }
+    output::set_json(args.json);
+    if let Some(path) = &args.replay_path {
+        let input = DecisionInput::load(path)?;
+        let decision = replay(&input);
+        output::emit("decision", &decision, format_args!("{:?}", decision));
+        return Ok(true);
+    }
+    if let Some(path) = &args.what_if_fees_path {
+        let config = match &args.config_path {
+            Some(path) => Config::from_file(path)?,
+            None => Config::default(),
+        };
+        let session = whatif::load_session(path, &config)?;
+        let fee_rates = args
+            .fee_rates
+            .clone()
+            .unwrap_or(whatif::DEFAULT_FEE_RATES.to_vec());
+        // evaluated up front so the per-spread output doesn't break up the table
+        let scenarios = whatif::fee_grid(&session, &fee_rates);
+        log!(
+            "{} captured decisions\n{:>10} {:>14} {:>14}",
+            session.len(),
+            "fee rate",
+            "opportunities",
+            "net profit"
+        );
+        for scenario in scenarios {
+            output::emit(
+                "fee_scenario",
+                scenario,
+                format_args!(
+                    "{:>10} {:>14} {:>14.2}",
+                    scenario.fee_rate, scenario.opportunities, scenario.net_profit
+                ),
+            );
+        }
+        return Ok(true);
+    }
+    Ok(false)
+}
+
+// the binary's entry point, see main.rs
+#[cfg(not(feature = "io"))]
+pub fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_args();
+    if !run_offline(&args)? {
+        output::error(
+            "io_disabled",
+            "io",
+            format_args!("built without the io feature, only --replay and --what-if-fees run"),
+        );
+        std::process::exit(2);
+    }
+    Ok(())
+}
+
+// the binary's entry point, see main.rs
+#[cfg(feature = "io")]
+pub async fn run() -> Result<(), Box<dyn std::error::Error>> {
+    let args = parse_args();
+    if run_offline(&args)? {
+        return Ok(());
+    }
+
+    let mut config = match &args.config_path {
+        Some(path) => Config::from_file(path)?,
+        None => Config::default(),
+    };
+    if let Some(mode) = args.mode {
+        config.mode = mode;
+    }
+    if let Some(path) = &args.pnl_export_path {
+        config.pnl_export_path = Some(path.clone());
+    }
+    if args.validate_config {
+        let problems = validate::validate(&config);
+        for problem in &problems {
+            output::error("invalid_config", problem, format_args!("{}", problem));
+        }
+        if !problems.is_empty() {
+            std::process::exit(1);
+        }
+        log!("config is valid");
+        return Ok(());
+    }
+    if let Some(path) = &config.slippage_model_path {
+        log!(
+            "loaded slippage calibrations for {} symbols from {}",
+            config.slippage_model.len(),
+            path
+        );
+    }
+    let run_mode = args.run_mode.unwrap_or(config.run_mode);
+    let symbol = config.symbol;
+
+    let (binance, mb, reference) = match http_client(&config)? {
+        Some(client) => (
+            Binance::with_client(client.clone()),
+            MercadoBitcoin::with_client(client.clone()),
+            CoinGecko::with_client(client),
+        ),
+        None => (
+            Binance::default(),
+            MercadoBitcoin::default(),
+            CoinGecko::default(),
+        ),
+    };
+    let headers = |venue| {
+        let headers = config.http_headers.get(&venue).cloned().unwrap_or_default();
+        match header_map(&headers) {
+            Ok(map) => {
+                if !map.is_empty() {
+                    let names: Vec<&str> = map.keys().map(|name| name.as_str()).collect();
+                    log!("sending {} to {:?}", names.join(", "), venue);
+                }
+                map
+            }
+            Err(message) => {
+                output::error(
+                    "invalid_http_header",
+                    &message,
+                    format_args!("{:?} http_headers: {}", venue, message),
+                );
+                std::process::exit(2);
+            }
+        }
+    };
+    let (binance, mb) = (
+        binance
+            .with_base_url(config.base_urls.get(&Venue::Binance).cloned())
+            .with_headers(headers(Venue::Binance)),
+        mb.with_base_url(config.base_urls.get(&Venue::MercadoBitcoin).cloned())
+            .with_headers(headers(Venue::MercadoBitcoin)),
+    );
+    if http_tuned(&config) {
+        measure_connection_reuse(&binance, &SystemClock).await;
+    }
+    // the imbalance gate needs the levels behind the top of the book, and the shallow-book
+    // retry needs at least the levels it checks for
+    let depth = match config.min_direction_imbalance {
+        Some(_) => config.imbalance_levels.max(1) as u32,
+        None => 1,
+    }
+    .max(config.min_book_levels.unwrap_or(1) as u32);
+    let (binance, mb) = (
+        binance
+            .with_depth(depth)
+            .with_lenient_parsing(config.lenient_book_parsing),
+        mb.with_depth(depth)
+            .with_lenient_parsing(config.lenient_book_parsing),
+    );
+
+    if let Err(message) = config.check_market_making() {
+        output::error("experimental_mode", &message, format_args!("{}", message));
+        std::process::exit(2);
+    }
+
+    // live trading refuses to start without a key pair for both exchanges, shadow mode makes
+    // do with whatever is configured
+    let (binance, mb) = if config.mode == ExecutionMode::Shadow {
+        let credentials = Credentials::load(config.secrets_path.as_deref())?;
+        (
+            binance.with_credentials(credentials.binance),
+            mb.with_credentials(credentials.mercado_bitcoin),
+        )
+    } else if config.mode == ExecutionMode::Live {
+        let credentials = Credentials::load(config.secrets_path.as_deref())?;
+        let missing = credentials.missing(&config.accounts);
+        if !missing.is_empty() {
+            output::error(
+                "missing_credentials",
+                &missing,
+                format_args!(
+                    "live mode requires API credentials, missing {}",
+                    missing.join(", ")
+                ),
+            );
+            std::process::exit(2);
+        }
+        log!("loaded credentials {:?}", credentials);
+        (
+            binance.with_credentials(credentials.binance),
+            mb.with_credentials(credentials.mercado_bitcoin),
+        )
+    } else {
+        (binance, mb)
+    };
+
+    if config.discover_symbols {
+        let registry = match &config.symbol_cache_path {
+            Some(path) => {
+                SymbolRegistry::fetch_cached(&binance, &mb, path, config.symbol_cache_ttl_ms)
+                    .await?
+            }
+            None => SymbolRegistry::fetch(&binance, &mb).await?,
+        };
+        log!(
+            "discovered {} symbols listed on both exchanges, known pairs = {:?}",
+            registry.len(),
+            registry.known_symbols()
+        );
+        match registry.get(symbol) {
+            Some(info) => {
+                log!(
+                    "{} ({}/{}) tick = {}, step = {}, min qty = {}, min notional = {}",
+                    info.binance_symbol,
+                    info.base_asset,
+                    info.quote_asset,
+                    info.tick_size,
+                    info.step_size,
+                    info.min_qty,
+                    info.min_notional
+                );
+                config
+                    .min_notional
+                    .entry(Venue::Binance)
+                    .or_insert(info.min_notional);
+            }
+            None => {
+                log!("{:?} is not tradeable on both exchanges", symbol);
+                return Ok(());
+            }
+        }
+    }
+
+    // advisory only, so a failed fetch leaves trades sized by the book alone
+    if let (Some(sizing), None) = (&config.kline_sizing, config.max_trade_qty) {
+        match binance
+            .klines(
+                symbol.get_binance_symbol_param(),
+                &sizing.interval,
+                sizing.limit,
+            )
+            .await
+        {
+            Ok(klines) => match TypicalTrade::from_klines(&klines) {
+                Some(typical) => {
+                    let max_qty = typical.qty * sizing.max_size_multiple;
+                    log!(
+                        "typical {:?} trade is {} at {}, MB fee rate {} at that size; capping trades at {}",
+                        symbol,
+                        typical.qty,
+                        typical.price,
+                        get_mb_fee_rate(typical.price, typical.qty),
+                        max_qty
+                    );
+                    config.max_trade_qty = Some(max_qty);
+                }
+                None => log!("no trades in the last {} klines", klines.len()),
+            },
+            Err(error) => log!("kline fetch failed: {}", error),
+        }
+    }
+
+    let mut bot = Bot::new(config, binance, mb, reference);
+    if bot.config.admin_api {
+        let token = std::env::var("ADMIN_API_TOKEN").unwrap_or_default();
+        if token.is_empty() {
+            output::error(
+                "missing_admin_token",
+                "ADMIN_API_TOKEN",
+                format_args!("the admin API requires ADMIN_API_TOKEN"),
+            );
+            std::process::exit(2);
+        }
+        tokio::spawn(admin::serve(
+            bot.config.admin_api_bind.clone(),
+            token,
+            bot.shared_state(),
+        ));
+    }
+
+    let halt = bot.halt.clone();
+    match run_mode {
+        RunMode::Once => bot.run_cycle().await?,
+        RunMode::Loop => {
+            let mut jitter = Jitter::new(bot.config.poll_jitter_pct);
+            let drain = bot.drain.clone();
+            tokio::spawn({
+                let drain = drain.clone();
+                async move {
+                    shutdown_signal().await;
+                    log!("draining: no new trades, finishing whatever is in flight");
+                    drain.cancel();
+                }
+            });
+            let heartbeat = Heartbeat::default();
+            let unwind = Duration::from_millis(bot.config.watchdog_unwind_ms);
+            if let Some(stall_ms) = bot.config.watchdog_stall_ms {
+                tokio::spawn(watchdog::watch(
+                    heartbeat.clone(),
+                    Duration::from_millis(stall_ms),
+                    halt.clone(),
+                    bot.clock.clone(),
+                ));
+            }
+            loop {
+                heartbeat.start_cycle(bot.clock.now_ms());
+                let mut cycle = Box::pin(async {
+                    if bot.config.maintenance_backoff_ms.is_some() {
+                        bot.run_cycle_unless_maintenance().await;
+                    } else if let Err(error) = bot.run_cycle().await {
+                        report_cycle_error(&error);
+                    }
+                });
+                tokio::select! {
+                    _ = &mut cycle => {}
+                    _ = halt.cancelled() => {
+                        // the halt cancels the cycle's open orders, so one stalled on an order
+                        // finishes by correcting what filled; one stalled on a fetch is dropped
+                        if timeout(unwind, cycle).await.is_err() {
+                            log!("cycle did not unwind within {:?}, abandoning it", unwind);
+                        }
+                        break;
+                    }
+                }
+                drop(cycle);
+                heartbeat.end_cycle();
+                let interval = match bot.config.maintenance_backoff_ms {
+                    Some(backoff_ms) if bot.maintenance.is_active() => {
+                        Duration::from_millis(backoff_ms)
+                    }
+                    _ => bot.throttled(jitter.next_interval(bot.poll_interval.interval())),
+                };
+                if drain.is_cancelled() {
+                    break;
+                }
+                tokio::select! {
+                    _ = sleep(interval) => {}
+                    _ = drain.cancelled() => break,
+                }
+            }
+        }
+    }
+
+    let shared_state = bot.shared_state();
+    let state = shared_state.read();
+    state.stats.report(&state.balance, &bot.config, &bot.marks);
+    state.accounts.report();
+
+    // exits non-zero so that a supervisor notices and restarts the bot
+    if halt.is_cancelled() {
+        std::process::exit(1);
+    }
+    Ok(())
+}
+
+// ctrl-c, or SIGTERM where there is one
+#[cfg(feature = "io")]
+async fn shutdown_signal() {
+    #[cfg(unix)]
+    if let Ok(mut terminate) =
+        tokio::signal::unix::signal(tokio::signal::unix::SignalKind::terminate())
+    {
+        tokio::select! {
+            _ = tokio::signal::ctrl_c() => {}
+            _ = terminate.recv() => {}
+        }
+        return;
+    }
+    let _ = tokio::signal::ctrl_c().await;
+}
+
+#[cfg(feature = "io")]
+fn report_cycle_error(error: &Error) {
+    output::error(
+        "cycle_failed",
+        error.to_string(),
+        format_args!("cycle failed: {}", error),
+    );
+}
+
+// why a decision other than a trade or no opportunity at all was skipped
+fn report_skipped(decision: &Decision) {
+    match decision {
+        Decision::NoOpportunity | Decision::Trade(_) => {}
+        Decision::ImbalanceTooLow {
+            imbalance,
+            min_imbalance,
+        } => log!(
+            "skipping trade, book imbalance {:?} is below {}",
+            imbalance,
+            min_imbalance
+        ),
+        Decision::Unconfirmed => {
+            log!("skipping trade, opportunity vanished on the confirmation fetch")
+        }
+        Decision::PositionLimit { open } => {
+            log!("skipping trade, {} positions are still open", open)
+        }
+        Decision::Draining => log!("skipping trade, draining for shutdown"),
+        Decision::InventoryExhausted => {
+            log!("skipping trade, no profitable direction is covered by the hedged inventory")
+        }
+        Decision::Insufficient {
+            shortfall,
+            snapshot,
+        } => {
+            output::emit(
+                "insufficient_balance",
+                shortfall,
+                format_args!(
+                    "skipping trade, insufficient {:?}: required {}, available {}",
+                    shortfall.currency, shortfall.required, shortfall.available
+                ),
+            );
+            if let Some(spread) = snapshot.spread {
+                output::emit(
+                    "clamped_opportunity",
+                    snapshot,
+                    format_args!(
+                        "a balance-clamped trade of {} of the {} on the book would net {} at imbalance {:?}, leaving {:?}",
+                        spread.qty, snapshot.book_qty, spread.net, snapshot.imbalance, snapshot.expected_balance
+                    ),
+                );
+            }
+        }
+    }
+}
+
+fn http_tuned(config: &Config) -> bool {
+    config.http2_prior_knowledge
+        || config.http_pool_max_idle_per_host.is_some()
+        || config.http_pool_idle_timeout_ms.is_some()
+}
+
+// one client shared by every venue when the proxy or pool settings call for it, otherwise
+// None and each venue keeps its own default client
+#[cfg(feature = "io")]
+fn http_client(config: &Config) -> Result<Option<reqwest::Client>, Error> {
+    if config.http_proxy.is_none() && !http_tuned(config) {
+        return Ok(None);
+    }
+    let mut builder = reqwest::Client::builder();
+    if let Some(proxy) = &config.http_proxy {
+        builder = builder.proxy(reqwest::Proxy::all(proxy)?);
+    }
+    if let Some(max_idle) = config.http_pool_max_idle_per_host {
+        builder = builder.pool_max_idle_per_host(max_idle);
+    }
+    if let Some(idle_timeout_ms) = config.http_pool_idle_timeout_ms {
+        builder = builder.pool_idle_timeout(Duration::from_millis(idle_timeout_ms));
+    }
+    if config.http2_prior_knowledge {
+        builder = builder.http2_prior_knowledge();
+    }
+    builder.build().map(Some)
+}
+
+// times a request on a fresh connection against one on the pooled connection it left behind
+#[cfg(feature = "io")]
+async fn measure_connection_reuse(binance: &Binance, clock: &dyn Clock) {
+    let start = clock.instant();
+    if let Err(error) = binance.server_time().await {
+        log!("connection reuse check failed: {}", error);
+        return;
+    }
+    let cold = clock.instant() - start;
+    let start = clock.instant();
+    if let Err(error) = binance.server_time().await {
+        log!("connection reuse check failed: {}", error);
+        return;
+    }
+    let pooled = clock.instant() - start;
+    output::emit(
+        "connection_reuse",
+        json!({
+            "cold_ms": cold.as_secs_f64() * 1000.0,
+            "pooled_ms": pooled.as_secs_f64() * 1000.0,
+        }),
+        format_args!("binance request: cold = {:?}, pooled = {:?}", cold, pooled),
+    );
+}
+
+// everything that persists from one cycle to the next
+#[cfg(feature = "io")]
+struct Bot {
+    config: Config,
+    binance: Binance,
+    mb: MercadoBitcoin,
+    reference: CoinGecko,
+    // per symbol, refetched once each expires
+    reference_prices: HashMap<Symbol, FxCache>,
+    clock: Arc<dyn Clock>,
+    clock_skew: ClockSkew,
+    state: SharedState,
+    spread_emas: SpreadEmas,
+    market_maker: MarketMaker,
+    hedged_pair: Option<HedgedPair>,
+    usdt_brl: FxCache,
+    marks: Marks,
+    poll_interval: AdaptiveInterval,
+    binance_volume: RollingVolume,
+    // per symbol, since each settles in its own currencies
+    netting: HashMap<Symbol, Netting>,
+    positions: OpenPositions,
+    pnl_export: Option<PnlExport>,
+    maintenance: Maintenance,
+    profit_threshold: ProfitThreshold,
+    book_cache: BookCache,
+    // cancelled on shutdown: no new trades are taken, those already executing complete
+    drain: CancellationToken,
+    // the pairs scan_listed_symbols found listed, and when
+    listed: Option<(u64, Vec<Symbol>)>,
+    // when the holding cost was last charged, None before the first cycle
+    holding_cost_accrued_ms: Option<u64>,
+    // cancelled by the watchdog: open orders are cancelled, no further slices go out and
+    // only the corrective orders for what already filled are sent
+    halt: CancellationToken,
+}
+
+#[cfg(feature = "io")]
+impl Bot {
+    fn new(config: Config, binance: Binance, mb: MercadoBitcoin, reference: CoinGecko) -> Self {
+        let clock: Arc<dyn Clock> = match config.fixed_clock_ms {
+            Some(now_ms) => Arc::new(MockClock::new(now_ms)),
+            None => Arc::new(SystemClock),
+        };
+        Self {
+            clock,
+            clock_skew: ClockSkew::new(Duration::from_secs(config.clock_skew_refresh_secs)),
+            state: {
+                let balance = get_default_balance(config.base_currency, config.starting_balance);
+                let accounts = Accounts::new(&config.accounts, &balance, config.account_policy);
+                SharedState::new(State {
+                    balance,
+                    reserve: HashMap::new(),
+                    reserved: HashMap::new(),
+                    stats: Stats::default(),
+                    accounts,
+                    venue_balances: HashMap::new(),
+                    settings: RuntimeSettings::from_config(&config),
+                })
+            },
+            spread_emas: SpreadEmas::new(config.spread_ema_period),
+            market_maker: MarketMaker::new(&config),
+            hedged_pair: config.hedged_inventory.map(HedgedPair::new),
+            usdt_brl: FxCache::new(config.fx_rate_ttl_ms),
+            reference_prices: HashMap::new(),
+            marks: Marks::default(),
+            poll_interval: AdaptiveInterval::new(
+                Duration::from_millis(config.poll_interval_ms),
+                config.adaptive_poll,
+            ),
+            binance_volume: match &config.binance_volume_path {
+                Some(path) => RollingVolume::load(path).unwrap_or_else(|error| {
+                    log!("could not load Binance volume from {}: {}", path, error);
+                    RollingVolume::default()
+                }),
+                None => RollingVolume::default(),
+            },
+            netting: HashMap::new(),
+            positions: OpenPositions::new(config.max_open_positions, config.position_settle_ms),
+            drain: CancellationToken::new(),
+            halt: CancellationToken::new(),
+            holding_cost_accrued_ms: None,
+            listed: None,
+            pnl_export: config.pnl_export_path.clone().map(PnlExport::new),
+            maintenance: Maintenance::default(),
+            profit_threshold: ProfitThreshold::default(),
+            book_cache: BookCache::default(),
+            config,
+            binance,
+            mb,
+            reference,
+        }
+    }
+
+    // skips the cycle while Binance reports maintenance; a cycle that goes through ends
+    // maintenance and one failing with a 503 starts it
+    async fn run_cycle_unless_maintenance(&mut self) {
+        match self.binance.in_maintenance().await {
+            Ok(true) => {
+                self.maintenance
+                    .observe(true, self.clock.now_ms(), "Binance system status");
+                return;
+            }
+            Ok(false) => {}
+            Err(error) => log!("could not check Binance system status: {}", error),
+        }
+        match self.run_cycle().await {
+            Ok(()) => self
+                .maintenance
+                .observe(false, self.clock.now_ms(), "cycle completed"),
+            Err(error) if is_maintenance_error(&error) => {
+                self.maintenance
+                    .observe(true, self.clock.now_ms(), &error.to_string());
+            }
+            Err(error) => report_cycle_error(&error),
+        }
+    }
+
+    // picks the VIP tier rate from the executed volume still inside the window
+    fn refresh_binance_fee_rate(&mut self) {
+        let volume = self
+            .binance_volume
+            .total(self.clock.now_ms(), self.config.binance_volume_window_ms);
+        if let Some(fee_rate) = tier_fee_rate(&self.config.binance_vip_tiers, volume) {
+            if fee_rate != self.config.binance_fee_rate {
+                log!("Binance volume {} reaches fee rate {}", volume, fee_rate);
+            }
+            self.config.binance_fee_rate = fee_rate;
+            self.market_maker.set_binance_fee_rate(fee_rate);
+        }
+    }
+
+    // stretches the interval by the ratio of Binance's used weight to the throttle threshold
+    // once that threshold is reached
+    fn throttled(&self, interval: Duration) -> Duration {
+        let used_weight = self.binance.used_weight();
+        let threshold = self.config.binance_weight_limit as f64
+            * self.config.binance_weight_throttle_pct
+            / 100.0;
+        if threshold <= 0.0 || (used_weight as f64) < threshold {
+            return interval;
+        }
+        let throttled = interval.mul_f64(used_weight as f64 / threshold);
+        log!(
+            "Binance weight {}/{}, throttling poll interval to {:?}",
+            used_weight,
+            self.config.binance_weight_limit,
+            throttled
+        );
+        throttled
+    }
+
+    // a handle for other tasks to read balances and stats through
+    fn shared_state(&self) -> SharedState {
+        self.state.clone()
+    }
+
+    // takes in whatever the admin API changed since the last cycle
+    fn apply_runtime_settings(&mut self) -> RuntimeSettings {
+        let settings = self.state.read().settings.clone();
+        self.config.min_profit = settings.min_profit;
+        self.config.enabled_symbols = settings.enabled_symbols.clone();
+        if settings.poll_interval_ms != self.config.poll_interval_ms {
+            self.config.poll_interval_ms = settings.poll_interval_ms;
+            self.poll_interval
+                .reset(Duration::from_millis(settings.poll_interval_ms));
+        }
+        settings
+    }
+
+    async fn run_cycle(&mut self) -> Result<(), Error> {
+        if self.apply_runtime_settings().paused {
+            log!("skipping cycle, trading is paused");
+            return Ok(());
+        }
+        let symbol = self.config.symbol;
+        if !self.config.symbol_enabled(symbol) {
+            log!("skipping cycle, {:?} is disabled", symbol);
+            return Ok(());
+        }
+
+        // a USDT base needs the USDTBRL rate to value BRL-quoted profits
+        if self.config.base_currency == Currency::USDT && symbol != Symbol::USDTBRL {
+            self.refresh_usdt_brl().await;
+        }
+        self.refresh_binance_fee_rate();
+        let reference_price = match self.config.max_reference_deviation_pct {
+            Some(_) => self.refresh_reference_price(symbol).await,
+            None => None,
+        };
+
+        let config = &self.config;
+
+        if config.max_book_age_ms.is_some() || config.max_book_age_gap_ms.is_some() {
+            self.clock_skew
+                .refresh_if_due(&self.binance, self.clock.as_ref())
+                .await?;
+        }
+
+        let fetch_started = self.clock.instant();
+        let Some(binance_order_book) = self.fetch_full_book(&self.binance, symbol).await? else {
+            log!("skipping cycle, Binance book is still unusable after a retry");
+            return Ok(());
+        };
+        let binance_fetched = self.clock.instant();
+        let Some(mb_order_book) = self.fetch_full_book(&self.mb, symbol).await? else {
+            log!("skipping cycle, MB book is still unusable after a retry");
+            return Ok(());
+        };
+        let mb_fetched = self.clock.instant();
+        let mut execute = Duration::ZERO;
+        self.poll_interval
+            .observe(&binance_order_book, &mb_order_book);
+        if let Some(mid) = mb_order_book.mid_price() {
+            self.marks
+                .observe(symbol.base_currency(), symbol.quote_currency(), mid);
+        }
+        let min_profit = self.marks.convert(
+            self.profit_threshold
+                .current(config.min_profit, config.profit_hysteresis),
+            config.base_currency,
+            symbol.quote_currency(),
+        );
+
+        let decision = self
+            .evaluate(
+                symbol,
+                &binance_order_book,
+                &mb_order_book,
+                reference_price,
+                min_profit,
+                0,
+            )
+            .await?;
+        let config = &self.config;
+        match decision {
+            // a gate ahead of the decision has already said why
+            None => {}
+            Some(Decision::NoOpportunity) if config.market_making => {
+                if let Some(update) =
+                    self.market_maker
+                        .evaluate(&binance_order_book, &mb_order_book, symbol)
+                {
+                    let execute_started = self.clock.instant();
+                    apply_quote_update(update).await?;
+                    execute += self.clock.instant() - execute_started;
+                }
+            }
+            Some(Decision::Trade(action)) if config.mode == ExecutionMode::Shadow => {
+                let balance = self.state.balance();
+                let execute_started = self.clock.instant();
+                self.shadow_trade(action, balance).await?;
+                execute += self.clock.instant() - execute_started;
+            }
+            Some(Decision::Trade(action)) => {
+                let balance = self.state.balance();
+
+                // execute trade
+                let execute_started = self.clock.instant();
+                let executor = SimulatedExecutor::new(&binance_order_book, &mb_order_book)
+                    .with_maker_queue(self.config.maker_queue)
+                    .with_clock(self.clock.as_ref());
+                let trade_result = take_trade_action(
+                    action,
+                    balance.clone(),
+                    &self.config,
+                    &executor,
+                    &binance_order_book,
+                    &mb_order_book,
+                    &self.halt,
+                )
+                .await?;
+                execute += self.clock.instant() - execute_started;
+                self.book_trade(
+                    action,
+                    trade_result,
+                    &balance,
+                    &binance_order_book,
+                    &mb_order_book,
+                );
+            }
+            Some(decision) => report_skipped(&decision),
+        }
+        let config = &self.config;
+
+        let latency = CycleLatency {
+            fetch_a: binance_fetched - fetch_started,
+            fetch_b: mb_fetched - binance_fetched,
+            decide: (self.clock.instant() - mb_fetched).saturating_sub(execute),
+            execute,
+        };
+        let ms = |duration: Duration| duration.as_secs_f64() * 1000.0;
+        output::emit(
+            "cycle_latency",
+            json!({
+                "fetch_binance_ms": ms(latency.fetch_a),
+                "fetch_mb_ms": ms(latency.fetch_b),
+                "decide_ms": ms(latency.decide),
+                "execute_ms": ms(latency.execute),
+                "binance_weight": self.binance.used_weight(),
+            }),
+            format_args!(
+                "cycle latency: fetch binance = {:?}, fetch mb = {:?}, decide = {:?}, execute = {:?}, binance weight = {}",
+                latency.fetch_a, latency.fetch_b, latency.decide, latency.execute, self.binance.used_weight()
+            ),
+        );
+        {
+            let mut state = self.state.write();
+            state.stats.record_latency(latency);
+            state.stats.peak_binance_weight = state
+                .stats
+                .peak_binance_weight
+                .max(self.binance.used_weight());
+        }
+        if config.holding_cost_rate > 0.0 {
+            self.charge_holding_cost();
+        }
+        let config = &self.config;
+
+        if config.mode != ExecutionMode::Shadow {
+            self.convert_stranded(&binance_order_book, &mb_order_book)
+                .await?;
+        }
+
+        if config.mode == ExecutionMode::Live {
+            let held = self.state.read().held_balance();
+            let held = reconcile_balances(&self.binance, &self.mb, held, config).await?;
+            self.state.write().set_held_balance(held);
+        }
+
+        if self.config.cross_quote {
+            self.report_cross_quote(&mb_order_book).await?;
+        }
+
+        if !self.config.scan_symbols.is_empty() || self.config.scan_listed_symbols {
+            self.scan().await;
+        }
+
+        if let Some(pnl_export) = &mut self.pnl_export {
+            if let Err(error) = pnl_export.append(
+                self.clock.now_ms(),
+                &self.state.balance(),
+                &self.marks,
+                self.config.base_currency,
+            ) {
+                log!("could not append to the P&L export: {}", error);
+            }
+        }
+
+        Ok(())
+    }
+
+    // every gate a trade goes through, the same for the configured symbol and the scanned
+    // ones: the book checks ahead of `decide`, then the hedged inventory, draining, the
+    // position limit and the confirmation fetch. None when a check ahead of the decision
+    // skipped it, having logged why; `pending` trades were accepted earlier in the cycle and
+    // will each open a position
+    async fn evaluate(
+        &mut self,
+        symbol: Symbol,
+        binance_order_book: &OrderBook,
+        mb_order_book: &OrderBook,
+        reference_price: Option<f64>,
+        min_profit: Option<f64>,
+        pending: usize,
+    ) -> Result<Option<Decision>, Error> {
+        let config = if symbol == self.config.symbol {
+            Cow::Borrowed(&self.config)
+        } else {
+            let mut config = self.config.clone();
+            config.symbol = symbol;
+            Cow::Owned(config)
+        };
+
+        let mb_book_stale = match (config.max_book_age_ms, mb_order_book.updated_at_ms) {
+            (Some(max_age_ms), Some(updated_at_ms)) => is_stale(
+                updated_at_ms,
+                self.clock_skew.adjusted_now(self.clock.as_ref()),
+                max_age_ms,
+            ),
+            _ => false,
+        };
+
+        // spreads are relative to prices, which a bad feed can quote at 0
+        let zero_price_venue = [
+            (Venue::Binance, binance_order_book),
+            (Venue::MercadoBitcoin, mb_order_book),
+        ]
+        .into_iter()
+        .find(|(_, order_book)| order_book.quotes_zero_price())
+        .map(|(venue, _)| venue);
+
+        // gate against the average of previous cycles, then fold this cycle in
+        let mut spread_below_ema = false;
+        if let Some(net_spread) = best_net_spread(binance_order_book, mb_order_book, &config) {
+            if let (Some(factor), Some(ema)) =
+                (config.spread_ema_factor, self.spread_emas.get(symbol))
+            {
+                spread_below_ema = !exceeds_ema(net_spread, ema, factor);
+            }
+            let ema = self.spread_emas.observe(symbol, net_spread);
+            log!("net spread = {}, ema = {}", net_spread, ema);
+        }
+
+        let book_age_gap_ms = book_age_gap_ms(
+            binance_order_book,
+            mb_order_book,
+            self.clock_skew.offset_ms(),
+        );
+        let book_age_gap_exceeded = match (config.max_book_age_gap_ms, book_age_gap_ms) {
+            (Some(max_gap_ms), Some(gap_ms)) => gap_ms > max_gap_ms,
+            _ => false,
+        };
+
+        // a depegged USDT makes its BRL price meaningless for arbitrage
+        let usdt_depeg_price = match config.usdt_peg_band {
+            Some(band) if symbol.base_currency() == Currency::USDT => {
+                [binance_order_book, mb_order_book]
+                    .into_iter()
+                    .filter_map(OrderBook::mid_price)
+                    .find(|mid| !band.contains(*mid))
+            }
+            _ => None,
+        };
+
+        // a venue far from the reference is more likely a bad feed or a fat finger than an
+        // opportunity; without a reference price the gate is skipped
+        let reference_deviation = match (config.max_reference_deviation_pct, reference_price) {
+            (Some(max_deviation_pct), Some(reference)) => [
+                (Venue::Binance, binance_order_book),
+                (Venue::MercadoBitcoin, mb_order_book),
+            ]
+            .into_iter()
+            .filter_map(|(venue, order_book)| {
+                Some((venue, order_book.fair_value(config.fair_value)?))
+            })
+            .find(|(_, mid)| deviation_pct(*mid, reference) > max_deviation_pct)
+            .map(|(venue, mid)| (venue, mid, reference, max_deviation_pct)),
+            _ => None,
+        };
+
+        if mb_book_stale {
+            log!("skipping evaluation, MB order book is stale");
+            return Ok(None);
+        }
+        if let Some(venue) = zero_price_venue {
+            log!("skipping evaluation, {:?} quotes a price of 0", venue);
+            return Ok(None);
+        }
+        if let Some(price) = usdt_depeg_price {
+            log!("skipping evaluation, USDT depeg detected at {} BRL", price);
+            return Ok(None);
+        }
+        if let Some((venue, mid, reference, max_deviation_pct)) = reference_deviation {
+            log!(
+                "skipping evaluation, {:?} {:?} {} is more than {}% from the reference price {}",
+                venue,
+                config.fair_value,
+                mid,
+                max_deviation_pct,
+                reference
+            );
+            return Ok(None);
+        }
+        if book_age_gap_exceeded {
+            log!(
+                "skipping evaluation, order books are {} ms apart",
+                book_age_gap_ms.unwrap_or_default()
+            );
+            return Ok(None);
+        }
+        if spread_below_ema {
+            log!("skipping evaluation, net spread does not exceed its EMA");
+            return Ok(None);
+        }
+        let Some(min_profit) = min_profit else {
+            log!(
+                "skipping evaluation, no {:?}/{:?} rate to convert min profit",
+                config.base_currency,
+                symbol.quote_currency()
+            );
+            return Ok(None);
+        };
+
+        let balance = self.state.balance();
+        // the hysteresis and the hedged inventory belong to the configured symbol
+        let own_symbol = symbol == self.config.symbol;
+        let mut decision = decide(
+            binance_order_book,
+            mb_order_book,
+            &balance,
+            &config,
+            min_profit,
+            self.hedged_pair.as_ref().filter(|_| own_symbol),
+        );
+        if own_symbol {
+            self.profit_threshold.observe(
+                !matches!(decision, Decision::NoOpportunity),
+                config.profit_hysteresis,
+            );
+        }
+        self.positions.settle_elapsed(self.clock.now_ms());
+        if let Decision::Trade(action) = &decision {
+            if self.drain.is_cancelled() {
+                decision = Decision::Draining;
+            } else if self.positions.available() <= pending {
+                decision = Decision::PositionLimit {
+                    open: self.positions.len() + pending,
+                };
+            } else if config.confirm_before_execution && !self.confirm(action, min_profit).await? {
+                decision = Decision::Unconfirmed;
+            }
+        }
+        if let Decision::Trade(_) = decision {
+            self.capture_decision(
+                binance_order_book,
+                mb_order_book,
+                &balance,
+                &config,
+                min_profit,
+            );
+        }
+        Ok(Some(decision))
+    }
+
+    // everything an executed trade changes besides the balance it was executed against:
+    // positions, netting, stats, the balance and accounts it is booked to, the reserve and
+    // the rolling volume; `balance_before` is the snapshot the trade executed from, and only
+    // the trade's own change to it is booked so that trades executed alongside it keep theirs
+    fn book_trade(
+        &mut self,
+        action: TradeAction,
+        trade_result: TradeResult,
+        balance_before: &HashMap<Currency, f64>,
+        binance_order_book: &OrderBook,
+        mb_order_book: &OrderBook,
+    ) {
+        let config = &self.config;
+        let symbol = trade_result.symbol;
+        for fill in &trade_result.fills {
+            output::emit(
+                "fill",
+                fill,
+                format_args!(
+                    "filled {:?} {} {:?} @ {} on {:?}",
+                    fill.leg.side, fill.qty, fill.symbol, fill.price, fill.leg.venue
+                ),
+            );
+        }
+        if config.log_trade_depth {
+            output::emit(
+                "trade_depth",
+                json!({
+                    "fills": trade_result.fills,
+                    "binance_order_book": binance_order_book,
+                    "mb_order_book": mb_order_book,
+                }),
+                format_args!(
+                    "depth at execution:\nbinance {:?}\nmb {:?}",
+                    binance_order_book, mb_order_book
+                ),
+            );
+        }
+        self.check_realized_profit(
+            action.net_profit(),
+            balance_before,
+            &trade_result.new_balance,
+        );
+        if let (Some(hedged_pair), true) = (&mut self.hedged_pair, symbol == config.symbol) {
+            hedged_pair.record(&trade_result.fills);
+            output::emit(
+                "hedged_inventory",
+                json!({ "imbalance": hedged_pair.imbalance() }),
+                format_args!("hedged inventory imbalance = {}", hedged_pair.imbalance()),
+            );
+        }
+        self.positions.open(self.clock.now_ms());
+        let netting = self.netting.entry(symbol).or_default();
+        netting.record(&trade_result.fills);
+        let settlement = match config.transfer_settlement_trades {
+            Some(every) if config.charge_transfer_costs => {
+                netting.settle(every, &config.withdrawal_fees, symbol)
+            }
+            _ => None,
+        };
+        if settlement.is_some() {
+            self.positions.settle_all();
+        }
+        {
+            let mut state = self.state.write();
+            state.stats.record(&trade_result);
+            if let Some(transfer_cost) = settlement {
+                log!("settled transfers, net rebalance costs {}", transfer_cost);
+                state.stats.record_settlement(transfer_cost);
+            }
+            for (currency, amount) in &trade_result.new_balance {
+                let delta = amount - balance_before.get(currency).copied().unwrap_or(0.0);
+                *state.balance.entry(*currency).or_insert(0.0) += delta;
+            }
+            for fill in &trade_result.fills {
+                book_to_account(&mut state.accounts, *fill, config);
+                book_to_venue(&mut state.venue_balances, *fill, config);
+            }
+            if config.profit_reserve_fraction > 0.0 && trade_result.realized_profit > 0.0 {
+                state.skim(
+                    symbol.quote_currency(),
+                    trade_result.realized_profit * config.profit_reserve_fraction,
+                );
+            }
+        }
+        self.binance_volume
+            .record(self.clock.now_ms(), binance_notional(&trade_result.fills));
+        if let Some(path) = &config.binance_volume_path {
+            if let Err(error) = self.binance_volume.save(path) {
+                log!("could not save Binance volume to {}: {}", path, error);
+            }
+        }
+    }
+
+    // scan_symbols, followed with scan_listed_symbols by the listed pairs it doesn't name, less
+    // the configured symbol and those disabled
+    async fn scan_set(&mut self) -> Vec<Symbol> {
+        let mut symbols = self.config.scan_symbols.clone();
+        if self.config.scan_listed_symbols {
+            for symbol in self.listed_symbols().await {
+                if symbol != self.config.symbol && !symbols.contains(&symbol) {
+                    symbols.push(symbol);
+                }
+            }
+        }
+        symbols.retain(|symbol| self.config.symbol_enabled(*symbol));
+        symbols
+    }
+
+    // the well-known pairs last found listed on both venues, refetched once they are
+    // symbol_cache_ttl_ms old; a failed refresh keeps the previous listing and is retried
+    // next cycle
+    async fn listed_symbols(&mut self) -> Vec<Symbol> {
+        let now_ms = self.clock.now_ms();
+        let due = self.listed.as_ref().is_none_or(|(fetched_at_ms, _)| {
+            now_ms.saturating_sub(*fetched_at_ms) >= self.config.symbol_cache_ttl_ms
+        });
+        if due {
+            let registry = match &self.config.symbol_cache_path {
+                Some(path) => {
+                    SymbolRegistry::fetch_cached(
+                        &self.binance,
+                        &self.mb,
+                        path,
+                        self.config.symbol_cache_ttl_ms,
+                    )
+                    .await
+                }
+                None => SymbolRegistry::fetch(&self.binance, &self.mb).await,
+            };
+            match registry {
+                Ok(registry) => {
+                    let mut symbols = registry.known_symbols();
+                    // the registry is a map, so its order means nothing
+                    symbols.sort_by(|a, b| {
+                        a.get_binance_symbol_param()
+                            .cmp(b.get_binance_symbol_param())
+                    });
+                    log!("listed on both venues: {:?}", symbols);
+                    self.listed = Some((now_ms, symbols));
+                }
+                Err(error) => log!("could not refresh the listed symbols: {}", error),
+            }
+        }
+        self.listed
+            .as_ref()
+            .map(|(_, symbols)| symbols.clone())
+            .unwrap_or_default()
+    }
+
+    // fetches every scanned symbol at once; one failing doesn't drop the books of the others.
+    // With execute_scan_opportunities each opportunity goes through the same gates as the
+    // configured symbol's, one after the other, then those accepted execute concurrently and
+    // are booked in turn
+    async fn scan(&mut self) {
+        let symbols = self.scan_set().await;
+        let results = join_all(
+            symbols
+                .iter()
+                .map(|symbol| self.fetch_cached_books(*symbol)),
+        )
+        .await;
+
+        let mut failed = Vec::new();
+        let mut candidates = Vec::new();
+        for (symbol, result) in symbols.into_iter().zip(results) {
+            let (binance_order_book, mb_order_book) = match result {
+                Ok(books) => books,
+                Err(error) => {
+                    failed.push((symbol, error.to_string()));
+                    continue;
+                }
+            };
+            let min_profit = self.marks.convert(
+                self.config.min_profit,
+                self.config.base_currency,
+                symbol.quote_currency(),
+            );
+            let Some(action) = min_profit.and_then(|min_profit| {
+                best_arbitrage(
+                    &binance_order_book,
+                    &mb_order_book,
+                    symbol,
+                    &self.config,
+                    min_profit,
+                )
+            }) else {
+                continue;
+            };
+            output::emit(
+                "scan_opportunity",
+                action,
+                format_args!("scanned {:?}: {:?}", symbol, action),
+            );
+            // the configured symbol has already had its chance at these books this cycle
+            if self.config.execute_scan_opportunities && symbol != self.config.symbol {
+                candidates.push((symbol, binance_order_book, mb_order_book, min_profit));
+            }
+        }
+        if !failed.is_empty() {
+            output::error(
+                "scan_failed",
+                &failed,
+                format_args!("scan failed for {:?}", failed),
+            );
+        }
+
+        let mut accepted = Vec::new();
+        for (symbol, binance_order_book, mb_order_book, min_profit) in candidates {
+            let reference_price = match self.config.max_reference_deviation_pct {
+                Some(_) => self.refresh_reference_price(symbol).await,
+                None => None,
+            };
+            log!("evaluating scanned {:?}", symbol);
+            match self
+                .evaluate(
+                    symbol,
+                    &binance_order_book,
+                    &mb_order_book,
+                    reference_price,
+                    min_profit,
+                    accepted.len(),
+                )
+                .await
+            {
+                Ok(Some(Decision::Trade(action))) => {
+                    accepted.push((action, binance_order_book, mb_order_book))
+                }
+                Ok(Some(decision)) => report_skipped(&decision),
+                Ok(None) => {}
+                Err(error) => output::error(
+                    "scan_trade_failed",
+                    error.to_string(),
+                    format_args!("scanned {:?} trade failed: {}", symbol, error),
+                ),
+            }
+        }
+
+        let results = join_all(accepted.iter().map(
+            |(action, binance_order_book, mb_order_book)| {
+                self.execute_scanned(*action, binance_order_book, mb_order_book)
+            },
+        ))
+        .await;
+        for ((action, binance_order_book, mb_order_book), result) in
+            accepted.into_iter().zip(results)
+        {
+            match result {
+                // the reservation is released only once the trade is booked
+                Ok(Some((trade_result, balance_before, _reservation))) => self.book_trade(
+                    action,
+                    trade_result,
+                    &balance_before,
+                    &binance_order_book,
+                    &mb_order_book,
+                ),
+                Ok(None) => {}
+                Err(error) => output::error(
+                    "scan_trade_failed",
+                    error.to_string(),
+                    format_args!("scanned trade failed: {}", error),
+                ),
+            }
+        }
+    }
+
+    // runs alongside the other scanned trades, so the funds are reserved before any order
+    // goes out; returns the result with the balance it executed from and the reservation,
+    // to be held until the trade is booked, or None when the funds are already committed
+    async fn execute_scanned(
+        &self,
+        action: TradeAction,
+        binance_order_book: &OrderBook,
+        mb_order_book: &OrderBook,
+    ) -> Result<Option<(TradeResult, HashMap<Currency, f64>, Reservation)>, Error> {
+        let symbol = action.symbol();
+        let reservation = match self
+            .state
+            .reserve(&required_funds(&action, &self.config), &self.config)
+        {
+            Ok(reservation) => reservation,
+            Err(shortfall) => {
+                output::emit(
+                    "insufficient_balance",
+                    shortfall,
+                    format_args!(
+                        "skipping scanned {:?} trade, {:?} is already committed: required {}, available {}",
+                        symbol, shortfall.currency, shortfall.required, shortfall.available
+                    ),
+                );
+                return Ok(None);
+            }
+        };
+
+        let balance = self.state.balance();
+        let mut config = self.config.clone();
+        config.symbol = symbol;
+        let executor = SimulatedExecutor::new(binance_order_book, mb_order_book)
+            .with_maker_queue(config.maker_queue)
+            .with_clock(self.clock.as_ref());
+        let trade_result = take_trade_action(
+            action,
+            balance.clone(),
+            &config,
+            &executor,
+            binance_order_book,
+            mb_order_book,
+            &self.halt,
+        )
+        .await?;
+        Ok(Some((trade_result, balance, reservation)))
+    }
+
+    // compares Binance's USDT-quoted book, converted at the cached USDTBRL rate, with MB's
+    // BRL book; these are reported only since there is no USDT execution path
+    async fn report_cross_quote(&mut self, mb_order_book: &OrderBook) -> Result<(), Error> {
+        let symbol = self.config.symbol;
+        let Some(usdt_symbol) = symbol.get_binance_usdt_symbol_param() else {
+            return Ok(());
+        };
+
+        let Some(rate) = self.refresh_usdt_brl().await else {
+            log!("skipping cross-quote arbitrage, USDTBRL rate is stale");
+            return Ok(());
+        };
+        let min_profit = self
+            .marks
+            .convert(
+                self.config.min_profit,
+                self.config.base_currency,
+                symbol.quote_currency(),
+            )
+            .unwrap_or(self.config.min_profit);
+
+        let usdt_order_book = self
+            .binance
+            .fetch_depth(usdt_symbol)
+            .await?
+            .normalized(&self.config);
+        let converted_order_book = usdt_order_book.converted(rate);
+        for action in check_arbitrage(
+            &converted_order_book,
+            mb_order_book,
+            symbol,
+            &self.config,
+            min_profit,
+        ) {
+            output::emit(
+                "cross_quote_opportunity",
+                json!({ "via": usdt_symbol, "rate": rate, "action": action }),
+                format_args!(
+                    "cross-quote opportunity via {} at {} BRL/USDT: profit {}, {:?}",
+                    usdt_symbol,
+                    rate,
+                    action.net_profit(),
+                    action
+                ),
+            );
+        }
+        Ok(())
+    }
+
+    // whether freshly fetched books still show the action's direction above `min_profit`
+    async fn confirm(&self, action: &TradeAction, min_profit: f64) -> Result<bool, Error> {
+        let symbol = action.symbol();
+        let (binance_order_book, mb_order_book) = self.fetch_books(symbol).await?;
+        Ok(best_arbitrage(
+            &binance_order_book,
+            &mb_order_book,
+            symbol,
+            &self.config,
+            min_profit,
+        )
+        .is_some_and(|confirmed| confirmed.venues() == action.venues()))
+    }
+
+    // cycles run longer or shorter than the poll interval, so what is charged is the time that
+    // actually passed since the previous charge
+    fn charge_holding_cost(&mut self) {
+        let now_ms = self.clock.now_ms();
+        let elapsed_ms = self
+            .holding_cost_accrued_ms
+            .map_or(0, |accrued_ms| now_ms.saturating_sub(accrued_ms));
+        self.holding_cost_accrued_ms = Some(now_ms);
+        let mut state = self.state.write();
+        let cost = holding_cost(&state.balance, &self.config, &self.marks, elapsed_ms);
+        if cost > 0.0 {
+            *state
+                .balance
+                .entry(self.config.base_currency)
+                .or_insert(0.0) -= cost;
+            state.stats.holding_cost += cost;
+        }
+    }
+
+    // a book that is shallower than min_book_levels, crossed with resync_crossed_books set
+    // or failing its checksum with validate_book_checksums set, is fetched once more after a
+    // short delay; None if it still is, unlike a failed request which errors
+    async fn fetch_full_book<E: Exchange>(
+        &self,
+        exchange: &E,
+        symbol: Symbol,
+    ) -> Result<Option<OrderBook>, Error> {
+        let order_book = self.fetch_cached_book(exchange, symbol).await?;
+        let is_shallow = |order_book: &OrderBook| {
+            self.config.min_book_levels.is_some_and(|min_levels| {
+                order_book.bids.len() < min_levels.max(1)
+                    || order_book.asks.len() < min_levels.max(1)
+            })
+        };
+        let is_crossed = |order_book: &OrderBook| {
+            self.config.resync_crossed_books
+                && matches!(
+                    (order_book.bids.first(), order_book.asks.first()),
+                    (Some(bid), Some(ask)) if bid.price >= ask.price
+                )
+        };
+        let is_corrupt = |order_book: &OrderBook| {
+            self.config.validate_book_checksums
+                && order_book
+                    .checksum
+                    .is_some_and(|expected| expected != incremental::checksum(order_book))
+        };
+        if is_shallow(&order_book) {
+            log!(
+                "book came back with {} bids and {} asks, retrying",
+                order_book.bids.len(),
+                order_book.asks.len()
+            );
+        } else if is_crossed(&order_book) {
+            log!("book came back crossed, retrying");
+        } else if is_corrupt(&order_book) {
+            log!("book failed its checksum, resyncing");
+        } else {
+            return Ok(Some(order_book));
+        }
+
+        sleep(Duration::from_millis(self.config.book_retry_delay_ms)).await;
+        let order_book = self.refetch_book(exchange, symbol).await?;
+        Ok(Some(order_book).filter(|order_book| {
+            !is_shallow(order_book) && !is_crossed(order_book) && !is_corrupt(order_book)
+        }))
+    }
+
+    // the book fetched within the symbol's book_ttl_ms, if any, or a fresh one
+    async fn fetch_cached_book<E: Exchange>(
+        &self,
+        exchange: &E,
+        symbol: Symbol,
+    ) -> Result<OrderBook, Error> {
+        if let Some(&ttl_ms) = self.config.book_ttl_ms.get(&symbol) {
+            let ttl = Duration::from_millis(ttl_ms);
+            if let Some(order_book) =
+                self.book_cache
+                    .get(E::VENUE, symbol, ttl, self.clock.instant())
+            {
+                return Ok(order_book);
+            }
+        }
+        self.refetch_book(exchange, symbol).await
+    }
+
+    async fn refetch_book<E: Exchange>(
+        &self,
+        exchange: &E,
+        symbol: Symbol,
+    ) -> Result<OrderBook, Error> {
+        let order_book = exchange
+            .fetch_order_book(symbol)
+            .await?
+            .normalized(&self.config);
+        if self.config.book_ttl_ms.contains_key(&symbol) {
+            self.book_cache
+                .insert(E::VENUE, symbol, self.clock.instant(), &order_book);
+        }
+        Ok(order_book)
+    }
+
+    async fn fetch_cached_books(&self, symbol: Symbol) -> Result<(OrderBook, OrderBook), Error> {
+        Ok((
+            self.fetch_cached_book(&self.binance, symbol).await?,
+            self.fetch_cached_book(&self.mb, symbol).await?,
+        ))
+    }
+
+    async fn fetch_books(&self, symbol: Symbol) -> Result<(OrderBook, OrderBook), Error> {
+        let binance_order_book = self
+            .binance
+            .fetch_order_book(symbol)
+            .await?
+            .normalized(&self.config);
+        let mb_order_book = self
+            .mb
+            .fetch_order_book(symbol)
+            .await?
+            .normalized(&self.config);
+        Ok((binance_order_book, mb_order_book))
+    }
+
+    // the model priced the trade as profitable, so a loss in the holdings' value means the model
+    // is missing a cost
+    fn check_realized_profit(
+        &self,
+        predicted_profit: f64,
+        before: &HashMap<Currency, f64>,
+        after: &HashMap<Currency, f64>,
+    ) {
+        let base_currency = self.config.base_currency;
+        let Some(realized) = realized_net_profit(before, after, base_currency, &self.marks) else {
+            log!(
+                "could not value the trade's realized profit in {:?}",
+                base_currency
+            );
+            return;
+        };
+        if predicted_profit > 0.0 && realized < 0.0 {
+            output::error(
+                "unprofitable_trade",
+                json!({
+                    "predicted_profit": predicted_profit,
+                    "realized_net_profit": realized,
+                    "currency": base_currency,
+                }),
+                format_args!(
+                    "ALERT: trade predicted to make {} lost {} {:?} once everything is counted",
+                    predicted_profit, -realized, base_currency
+                ),
+            );
+        }
+    }
+
+    // on each venue, sells what its fills have left of the traded symbol's base currency above
+    // the conversion target back into the quote currency, or buys back what falls short, once
+    // the venue's holding leaves the tolerance band
+    async fn convert_stranded(
+        &self,
+        binance_order_book: &OrderBook,
+        mb_order_book: &OrderBook,
+    ) -> Result<(), Error> {
+        let config = &self.config;
+        let symbol = config.symbol;
+        let currency = symbol.base_currency();
+        let quote_currency = symbol.quote_currency();
+        let Some(target) = config.conversion_targets.get(&currency) else {
+            return Ok(());
+        };
+        let (mut balance, mut accounts, mut venue_balances) = {
+            let state = self.state.read();
+            (
+                state.balance.clone(),
+                state.accounts.clone(),
+                state.venue_balances.clone(),
+            )
+        };
+
+        let executor = SimulatedExecutor::new(binance_order_book, mb_order_book)
+            .with_clock(self.clock.as_ref());
+        let mut fees = Vec::new();
+        for (venue, order_book) in [
+            (Venue::Binance, binance_order_book),
+            (Venue::MercadoBitcoin, mb_order_book),
+        ] {
+            let held = venue_balances
+                .get(&venue)
+                .and_then(|venue_balance| venue_balance.get(&currency))
+                .copied()
+                .unwrap_or(0.0);
+            let Some(excess) = target.excess(held) else {
+                continue;
+            };
+            let side = if excess > 0.0 { Side::Sell } else { Side::Buy };
+            let best = match side {
+                Side::Sell => order_book.bids.first(),
+                Side::Buy => order_book.asks.first(),
+            };
+            let Some(best) = best else {
+                log!(
+                    "not converting on {:?}, its book has no {:?} side",
+                    venue,
+                    side
+                );
+                continue;
+            };
+            let order = Order {
+                leg: Leg { venue, side },
+                symbol,
+                price: best.price.0,
+                qty: currency.round_down(excess.abs()),
+                time_in_force: TimeInForce::IOC,
+                post_only: false,
+            };
+            if order.qty <= 0.0 {
+                continue;
+            }
+            if side == Side::Buy {
+                // what the fill would cost at its slippage-adjusted price, with the fee
+                // when it is charged in the quote currency
+                let price = buy_fill_price(order.price, config.slippage_bps_for(symbol, order.qty));
+                let fee_rate = match config.fee_currency_for(venue) {
+                    FeeCurrency::Quote => get_fee_rate(config, venue, price, order.qty),
+                    FeeCurrency::Received => 0.0,
+                };
+                let cost = order.qty * price * (1.0 + fee_rate);
+                let available = balance.get(&quote_currency).copied().unwrap_or(0.0);
+                if cost > available {
+                    log!(
+                        "not converting towards the {:?} target on {:?}, {} {:?} can't buy {} for {}",
+                        currency,
+                        venue,
+                        available,
+                        quote_currency,
+                        order.qty,
+                        cost
+                    );
+                    continue;
+                }
+            }
+            let fill = execute(&executor, &order, config).await?;
+            if fill.qty <= 0.0 {
+                continue;
+            }
+            let (fill, _, fee) = apply_fill(&mut balance, fill, config);
+            book_to_account(&mut accounts, fill, config);
+            book_to_venue(&mut venue_balances, fill, config);
+            fees.push(fee);
+            output::emit(
+                "conversion",
+                json!({ "fill": fill, "fee": fee, "held": held, "target": target.target }),
+                format_args!(
+                    "converting {} {:?} held on {:?} towards the {} target: {:?} {} @ {}, fee {}",
+                    held, currency, venue, target.target, fill.leg.side, fill.qty, fill.price, fee
+                ),
+            );
+        }
+        let mut state = self.state.write();
+        state.balance = balance;
+        state.accounts = accounts;
+        state.venue_balances = venue_balances;
+        for fee in fees {
+            state.stats.record_conversion(fee);
+        }
+        Ok(())
+    }
+
+    fn capture_decision(
+        &self,
+        binance_order_book: &OrderBook,
+        mb_order_book: &OrderBook,
+        balance: &HashMap<Currency, f64>,
+        config: &Config,
+        min_profit: f64,
+    ) {
+        let Some(dir) = &config.decision_capture_dir else {
+            return;
+        };
+        let input = DecisionInput {
+            binance_order_book: binance_order_book.clone(),
+            mb_order_book: mb_order_book.clone(),
+            balance: balance.clone(),
+            config: config.clone(),
+            min_profit,
+        };
+        match input.save(dir, self.clock.now_ms()) {
+            Ok(path) => log!("captured decision input to {}", path),
+            Err(error) => output::error(
+                "decision_capture_failed",
+                error.to_string(),
+                format_args!("decision capture failed: {}", error),
+            ),
+        }
+    }
+
+    // runs the trade against books fetched at execution time and reports how it would have
+    // filled; the balance, accounts and stats are left untouched
+    async fn shadow_trade(
+        &self,
+        action: TradeAction,
+        balance: HashMap<Currency, f64>,
+    ) -> Result<(), Error> {
+        let symbol = action.symbol();
+        let (binance_order_book, mb_order_book) = self.fetch_books(symbol).await?;
+
+        let (buy_venue, sell_venue) = action.venues();
+        let binance_leg = if buy_venue == Venue::Binance {
+            Some((Side::Buy, action.ask_price()))
+        } else if sell_venue == Venue::Binance {
+            Some((Side::Sell, action.bid_price()))
+        } else {
+            None
+        };
+        if let Some((side, price)) = binance_leg {
+            let order = Order {
+                leg: Leg {
+                    venue: Venue::Binance,
+                    side,
+                },
+                symbol,
+                price,
+                qty: action.qty(),
+                time_in_force: self.config.time_in_force,
+                post_only: false,
+            };
+            match self.binance.test_order(&order).await {
+                Ok(true) => log!("Binance accepted the {:?} test order", side),
+                Ok(false) => log!("no Binance credentials, skipping the test order"),
+                Err(error) => log!("Binance rejected the {:?} test order: {}", side, error),
+            }
+        }
+
+        let expected_profit = action.net_profit();
+        let executor = SimulatedExecutor::new(&binance_order_book, &mb_order_book)
+            .with_maker_queue(self.config.maker_queue)
+            .with_clock(self.clock.as_ref());
+        let trade_result = take_trade_action(
+            action,
+            balance,
+            &self.config,
+            &executor,
+            &binance_order_book,
+            &mb_order_book,
+            &self.halt,
+        )
+        .await?;
+        output::emit(
+            "shadow_trade",
+            json!({
+                "expected_profit": expected_profit,
+                "realized_profit": trade_result.realized_profit,
+                "fees": trade_result.fees,
+                "residual_exposure": trade_result.residual_exposure,
+                "fills": trade_result.fills,
+            }),
+            format_args!(
+                "shadow trade: expected profit {}, would have realized {} after {} in fees over {} fills, residual exposure {}",
+                expected_profit,
+                trade_result.realized_profit,
+                trade_result.fees,
+                trade_result.fills.len(),
+                trade_result.residual_exposure
+            ),
+        );
+        Ok(())
+    }
+
+    // the cached reference price, refetched once it expires
+    async fn refresh_reference_price(&mut self, symbol: Symbol) -> Option<f64> {
+        let now_ms = self.clock.now_ms();
+        let ttl_ms = self.config.reference_price_ttl_ms;
+        let cache = self
+            .reference_prices
+            .entry(symbol)
+            .or_insert_with(|| FxCache::new(ttl_ms));
+        if cache.fresh_rate(now_ms).is_none() {
+            match self.reference.fetch_price(symbol).await {
+                Ok(Some(price)) => cache.update(price, now_ms),
+                Ok(None) => log!("no reference price for {:?}", symbol),
+                Err(error) => log!("reference price refresh failed: {}", error),
+            }
+        }
+        cache.fresh_rate(now_ms)
+    }
+
+    // the cached USDTBRL rate, refetched from Binance once it expires
+    async fn refresh_usdt_brl(&mut self) -> Option<f64> {
+        let now_ms = self.clock.now_ms();
+        if self.usdt_brl.fresh_rate(now_ms).is_none() {
+            match self.binance.fetch_order_book(Symbol::USDTBRL).await {
+                Ok(order_book) => match order_book.normalized(&self.config).mid_price() {
+                    Some(rate) => {
+                        self.usdt_brl.update(rate, now_ms);
+                        self.marks.observe(Currency::USDT, Currency::BRL, rate);
+                    }
+                    None => log!("USDTBRL book is empty, keeping cached rate"),
+                },
+                Err(error) => output::error(
+                    "usdt_brl_refresh_failed",
+                    error.to_string(),
+                    format_args!("USDTBRL refresh failed: {}", error),
+                ),
+            }
+        }
+        self.usdt_brl.fresh_rate(now_ms)
+    }
+}
+
+// every profitable direction, most profitable first
+fn check_arbitrage(
+    binance_order_book: &OrderBook,
+    mb_order_book: &OrderBook,
+    symbol: Symbol,
+    config: &Config,
+    min_profit: f64,
+) -> Vec<TradeAction> {
+    let (Some(binance_ask), Some(binance_bid), Some(mb_ask), Some(mb_bid)) = (
+        binance_order_book.asks.first(),
+        binance_order_book.bids.first(),
+        mb_order_book.asks.first(),
+        mb_order_book.bids.first(),
+    ) else {
+        return Vec::new();
+    };
+
+    let short_mb = (
+        (Venue::Binance, binance_ask),
+        (Venue::MercadoBitcoin, mb_bid),
+    );
+    let short_binance = (
+        (Venue::MercadoBitcoin, mb_ask),
+        (Venue::Binance, binance_bid),
+    );
+
+    let spreads: Vec<Spread> =
+        [short_mb, short_binance]
+            .into_iter()
+            .filter(|((_, ask), (_, bid))| bid.price > ask.price)
+            .filter(|((buy_venue, _), (sell_venue, _))| {
+                let allowed = config.direction_allowed(symbol, *buy_venue, *sell_venue);
+                if !allowed {
+                    log!(
+                        "skipping spread, buying on {:?} and selling on {:?} is restricted",
+                        buy_venue,
+                        sell_venue
+                    );
+                }
+                allowed
+            })
+            // e.g. a fat-finger print on one venue, not free money
+            .filter(|((buy_venue, ask), (sell_venue, bid))| {
+                let spread = (bid.price - ask.price).0;
+                let Some(spread_pct) = bid.price.relative_to(ask.price).map(|spread| spread * 100.0)
+                else {
+                    log!("skipping spread, {:?} asks a price of 0", buy_venue);
+                    return false;
+                };
+                let suspect = config.max_spread.is_some_and(|max| spread > max)
+                    || config.max_spread_pct.is_some_and(|max| spread_pct > max);
+                if suspect {
+                    output::emit(
+                        "suspect_spread",
+                        json!({
+                            "symbol": symbol,
+                            "buy_venue": buy_venue,
+                            "sell_venue": sell_venue,
+                            "ask": ask.price,
+                            "bid": bid.price,
+                            "spread_pct": spread_pct,
+                        }),
+                        format_args!(
+                            "skipping suspect spread of {}% buying at {} on {:?} and selling at {} on {:?}, likely bad data",
+                            spread_pct, ask.price, buy_venue, bid.price, sell_venue
+                        ),
+                    );
+                }
+                !suspect
+            })
+            // fees and slippage alone sink these, so a non-negative threshold can't be met
+            .filter(|(buy, sell)| {
+                let Some(spread) = sell.1.price.relative_to(buy.1.price) else {
+                    return false;
+                };
+                let break_even = break_even_spread(*buy, *sell, symbol, config);
+                if min_profit >= 0.0 && spread < break_even {
+                    log!("spread {} below break-even {}", spread, break_even);
+                    return false;
+                }
+                true
+            })
+            .map(|(buy, sell)| Spread::new(buy, sell, symbol, config))
+            .inspect(|spread| {
+                output::emit(
+                    "spread",
+                    spread,
+                    format_args!("costless_profit {}\nprofit {}", spread.gross, spread.net),
+                )
+            })
+            // flat withdrawal fees are only recouped from a certain size on
+            .filter(|spread| {
+                let fixed_costs = transfer_cost(
+                    config,
+                    spread.buy_venue,
+                    spread.sell_venue,
+                    symbol,
+                    0.0,
+                    spread.buy_price.0,
+                );
+                if fixed_costs <= 0.0 {
+                    return true;
+                }
+                match min_quantity_for_breakeven(spread, fixed_costs) {
+                    Some(min_qty) if min_qty <= spread.qty => true,
+                    Some(min_qty) => {
+                        log!(
+                        "skipping spread, {} needed to cover fixed costs of {} but the book has {}",
+                        min_qty, fixed_costs, spread.qty
+                    );
+                        false
+                    }
+                    None => {
+                        log!(
+                            "skipping spread, its margin can't cover fixed costs of {}",
+                            fixed_costs
+                        );
+                        false
+                    }
+                }
+            })
+            .filter(|spread| match min_notional_shortfall(config, spread) {
+                Some((venue, min_notional, notional)) => {
+                    log!(
+                        "skipping spread, {:?} MIN_NOTIONAL is {} but the leg is {}, {} short",
+                        venue,
+                        min_notional,
+                        notional,
+                        min_notional - notional
+                    );
+                    false
+                }
+                None => true,
+            })
+            .filter(|spread| {
+                let margin = safety_margin(config, spread);
+                // a spread that clears the margin only exactly would leave none of it
+                if margin > 0.0 {
+                    spread.net.0 - margin > min_profit
+                } else {
+                    spread.net >= Notional(min_profit)
+                }
+            })
+            .collect();
+    let mut actions: Vec<RankedAction> = spreads
+        .into_iter()
+        .filter_map(|spread| match spread.into_action(symbol, config) {
+            Ok(action) => Some(RankedAction(action)),
+            Err(error) => {
+                log!("discarding spread, both legs are on {:?}", error.venue);
+                None
+            }
+        })
+        .collect();
+    actions.sort_by(|a, b| {
+        b.cmp(a).then_with(|| {
+            venue_rank(config, a.0.venues().1).cmp(&venue_rank(config, b.0.venues().1))
+        })
+    });
+    actions.into_iter().map(|ranked| ranked.0).collect()
+}
+
+// the first leg, if any, whose value is below its venue's minimum order value, with that
+// minimum and the leg's value
+fn min_notional_shortfall(config: &Config, spread: &Spread) -> Option<(Venue, f64, f64)> {
+    [
+        (spread.buy_venue, spread.buy_price),
+        (spread.sell_venue, spread.sell_price),
+    ]
+    .into_iter()
+    .find_map(|(venue, price)| {
+        let min_notional = *config.min_notional.get(&venue)?;
+        let notional = (price * spread.qty).0;
+        (notional < min_notional).then_some((venue, min_notional, notional))
+    })
+}
+
+fn safety_margin(config: &Config, spread: &Spread) -> f64 {
+    config.safety_margin + (spread.buy_price * spread.qty).0 * config.safety_margin_pct / 100.0
+}
+
+// relative spread, (bid - ask) / ask, at which both venues' fees at this size and the
+// slippage on both legs eat the whole gross profit
+fn break_even_spread(
+    buy: (Venue, &Data),
+    sell: (Venue, &Data),
+    symbol: Symbol,
+    config: &Config,
+) -> f64 {
+    let (buy_venue, ask) = buy;
+    let (sell_venue, bid) = sell;
+    let qty = ask.qty.min(bid.qty).0;
+    let buy_fee_rate = modeled_fee_rate(config, buy_venue, ask.price.0, qty);
+    let sell_fee_rate = modeled_fee_rate(config, sell_venue, bid.price.0, qty);
+    let slippage = config.slippage_bps_for(symbol, qty) / 10_000.0;
+    (1.0 + buy_fee_rate + slippage) / (1.0 - sell_fee_rate - slippage) - 1.0
+}
+
+// smallest quantity whose per-unit margin after fees, slippage and proportional transfer
+// costs pays for `fixed_costs`; None when there is no margin left to pay with
+fn min_quantity_for_breakeven(spread: &Spread, fixed_costs: f64) -> Option<Qty> {
+    if spread.qty.0 <= 0.0 {
+        return None;
+    }
+    let unit_margin = (spread.net.0 + fixed_costs) / spread.qty.0;
+    if unit_margin <= 0.0 {
+        return None;
+    }
+    Some(Qty(fixed_costs / unit_margin))
+}
+
+// venues missing from the priority list rank after all listed ones
+fn venue_rank(config: &Config, venue: Venue) -> usize {
+    config
+        .sell_venue_priority
+        .iter()
+        .position(|priority| *priority == venue)
+        .unwrap_or(config.sell_venue_priority.len())
+}
+
+// the most profitable trade across the two books, if any clears config.min_profit, taken as
+// already in the symbol's quote currency; pure, so it builds without io, e.g. for wasm32
+pub fn evaluate(
+    binance_order_book: &OrderBook,
+    mb_order_book: &OrderBook,
+    config: &Config,
+) -> Option<TradeAction> {
+    best_arbitrage(
+        binance_order_book,
+        mb_order_book,
+        config.symbol,
+        config,
+        config.min_profit,
+    )
+}
+
+fn best_arbitrage(
+    binance_order_book: &OrderBook,
+    mb_order_book: &OrderBook,
+    symbol: Symbol,
+    config: &Config,
+    min_profit: f64,
+) -> Option<TradeAction> {
+    check_arbitrage(
+        binance_order_book,
+        mb_order_book,
+        symbol,
+        config,
+        min_profit,
+    )
+    .into_iter()
+    .next()
+}
+
+// what taking the best opportunity would do, with its quantity clamped to what `balance`
+// can afford; nothing is executed
+#[derive(Clone, Debug, Serialize)]
+struct OpportunitySnapshot {
+    // best direction at the clamped quantity, if it still nets a profit
+    spread: Option<Spread>,
+    // top-of-book quantity before clamping
+    book_qty: f64,
+    // how far the books lean towards that direction, see direction_imbalance
+    imbalance: Option<f64>,
+    expected_balance: HashMap<Currency, f64>,
+}
+
+fn simulate(
+    binance_order_book: &OrderBook,
+    mb_order_book: &OrderBook,
+    symbol: Symbol,
+    balance: &HashMap<Currency, f64>,
+    config: &Config,
+) -> OpportunitySnapshot {
+    let directions = [
+        (
+            (Venue::Binance, binance_order_book.asks.first()),
+            (Venue::MercadoBitcoin, mb_order_book.bids.first()),
+        ),
+        (
+            (Venue::MercadoBitcoin, mb_order_book.asks.first()),
+            (Venue::Binance, binance_order_book.bids.first()),
+        ),
+    ];
+
+    let mut best: Option<(Spread, f64)> = None;
+    for ((buy_venue, ask), (sell_venue, bid)) in directions {
+        let (Some(ask), Some(bid)) = (ask, bid) else {
+            continue;
+        };
+        let book_qty = ask.qty.min(bid.qty).0;
+        let qty = f64::min(
+            book_qty,
+            affordable_qty(buy_venue, ask.price.0, book_qty, symbol, balance, config),
+        );
+        if qty <= 0.0 {
+            continue;
+        }
+        let spread = Spread::new(
+            (buy_venue, &Data::new(ask.price, Qty(qty))),
+            (sell_venue, &Data::new(bid.price, Qty(qty))),
+            symbol,
+            config,
+        );
+        let is_better = best.is_none_or(|(best, _)| spread.net.0 > best.net.0);
+        if spread.sell_price > spread.buy_price && spread.net.0 > 0.0 && is_better {
+            best = Some((spread, book_qty));
+        }
+    }
+
+    let mut expected_balance = balance.clone();
+    if let Some((spread, _)) = best {
+        for (venue, side, price) in [
+            (spread.buy_venue, Side::Buy, spread.buy_price.0),
+            (spread.sell_venue, Side::Sell, spread.sell_price.0),
+        ] {
+            let fill = Fill {
+                leg: Leg { venue, side },
+                symbol,
+                price,
+                qty: spread.qty.0,
+            };
+            apply_fill(&mut expected_balance, fill, config);
+        }
+    }
+
+    let imbalance = best.and_then(|(spread, _)| {
+        let order_book = |venue| match venue {
+            Venue::Binance => binance_order_book,
+            Venue::MercadoBitcoin => mb_order_book,
+        };
+        direction_imbalance(
+            order_book(spread.buy_venue),
+            order_book(spread.sell_venue),
+            config.imbalance_levels,
+        )
+    });
+
+    OpportunitySnapshot {
+        spread: best.map(|(spread, _)| spread),
+        book_qty: best.map_or(0.0, |(_, book_qty)| book_qty),
+        imbalance,
+        expected_balance,
+    }
+}
+
+// average of the buy book's imbalance and the negated sell book's: positive when bids
+// dominate where the trade buys and asks dominate where it sells
+fn direction_imbalance(
+    buy_order_book: &OrderBook,
+    sell_order_book: &OrderBook,
+    levels: usize,
+) -> Option<f64> {
+    let buy_imbalance = buy_order_book.imbalance(levels)?;
+    let sell_imbalance = sell_order_book.imbalance(levels)?;
+    Some((buy_imbalance - sell_imbalance) / 2.0)
+}
+
+// the most base currency the quote balance pays for at `ask_price`, including the fee and
+// slippage, and without shorting, the most that can be sold from inventory
+fn affordable_qty(
+    buy_venue: Venue,
+    ask_price: f64,
+    qty: f64,
+    symbol: Symbol,
+    balance: &HashMap<Currency, f64>,
+    config: &Config,
+) -> f64 {
+    let available = tradeable_balance(balance, symbol.quote_currency(), config);
+    let unit_cost = buy_fill_price(ask_price, config.slippage_bps_for(symbol, qty))
+        * (1.0 + get_fee_rate(config, buy_venue, ask_price, qty));
+    let affordable = f64::max(available / unit_cost, 0.0);
+    if config.allow_short {
+        return affordable;
+    }
+    let inventory = tradeable_balance(balance, symbol.base_currency(), config);
+    f64::min(affordable, f64::max(inventory, 0.0))
+}
+
+// outcome of evaluating a pair of books against a balance, before anything is executed
+#[derive(Debug, Serialize)]
+enum Decision {
+    NoOpportunity,
+    ImbalanceTooLow {
+        imbalance: Option<f64>,
+        min_imbalance: f64,
+    },
+    Insufficient {
+        shortfall: Shortfall,
+        snapshot: OpportunitySnapshot,
+    },
+    // the confirmation fetch no longer showed the opportunity
+    Unconfirmed,
+    // max_open_positions are still waiting to settle
+    PositionLimit {
+        open: usize,
+    },
+    // with hedged_inventory, no profitable direction is covered by the sell venue's inventory
+    InventoryExhausted,
+    // shutting down, see Bot::drain
+    Draining,
+    Trade(TradeAction),
+}
+
+// `min_profit` is in the symbol's quote currency
+// with a hedged pair, the gates apply to the action it chooses among the affordable ones
+// rather than to the most profitable
+fn decide(
+    binance_order_book: &OrderBook,
+    mb_order_book: &OrderBook,
+    balance: &HashMap<Currency, f64>,
+    config: &Config,
+    min_profit: f64,
+    hedged_pair: Option<&HedgedPair>,
+) -> Decision {
+    let symbol = config.symbol;
+    let Some(best) =
+        Arbitrage { config, min_profit }.evaluate(binance_order_book, mb_order_book, symbol)
+    else {
+        return Decision::NoOpportunity;
+    };
+    let action = match hedged_pair {
+        None => best,
+        Some(hedged_pair) => {
+            let actions = check_arbitrage(
+                binance_order_book,
+                mb_order_book,
+                symbol,
+                config,
+                min_profit,
+            )
+            .into_iter()
+            .filter(|action| check_sufficiency(action, balance, config).is_ok())
+            .collect();
+            match hedged_pair.choose(actions) {
+                Some(action) => action,
+                // nothing affordable at all is reported as such below
+                None if check_sufficiency(&best, balance, config).is_err() => best,
+                None => return Decision::InventoryExhausted,
+            }
+        }
+    };
+
+    let (buy_venue, sell_venue) = action.venues();
+    let order_book = |venue| match venue {
+        Venue::Binance => binance_order_book,
+        Venue::MercadoBitcoin => mb_order_book,
+    };
+    let imbalance = direction_imbalance(
+        order_book(buy_venue),
+        order_book(sell_venue),
+        config.imbalance_levels,
+    );
+    if let Some(min_imbalance) = config
+        .min_direction_imbalance
+        .filter(|min_imbalance| imbalance.is_none_or(|imbalance| imbalance < *min_imbalance))
+    {
+        return Decision::ImbalanceTooLow {
+            imbalance,
+            min_imbalance,
+        };
+    }
+
+    if let Err(shortfall) = check_sufficiency(&action, balance, config) {
+        let snapshot = simulate(binance_order_book, mb_order_book, symbol, balance, config);
+        return Decision::Insufficient {
+            shortfall,
+            snapshot,
+        };
+    }
+
+    Decision::Trade(action)
+}
+
+// everything `decide` looks at, captured so a decision can be replayed exactly
+#[derive(Debug, Serialize, Deserialize)]
+struct DecisionInput {
+    binance_order_book: OrderBook,
+    mb_order_book: OrderBook,
+    balance: HashMap<Currency, f64>,
+    config: Config,
+    // already converted to the symbol's quote currency
+    #[serde(default)]
+    min_profit: f64,
+}
+
+impl DecisionInput {
+    fn load(path: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let contents = std::fs::read_to_string(path)?;
+        Ok(serde_json::from_str(&contents)?)
+    }
+
+    fn save(&self, dir: &str, now_ms: u64) -> Result<String, Box<dyn std::error::Error>> {
+        std::fs::create_dir_all(dir)?;
+        let path = format!("{}/decision-{}.json", dir, now_ms);
+        std::fs::write(&path, serde_json::to_string_pretty(self)?)?;
+        Ok(path)
+    }
+}
+
+fn replay(input: &DecisionInput) -> Decision {
+    decide(
+        &input.binance_order_book,
+        &input.mb_order_book,
+        &input.balance,
+        &input.config,
+        input.min_profit,
+        None,
+    )
+}
+
+// one direction across the two books: buy at the best ask of one venue and sell at the best
+// bid of the other
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Spread {
+    buy_venue: Venue,
+    sell_venue: Venue,
+    buy_price: Price,
+    sell_price: Price,
+    qty: Qty,
+    gross: Notional,
+    fees: Notional,
+    slippage: Notional,
+    transfer: Notional,
+    net: Notional,
+}
+
+impl Spread {
+    fn new(buy: (Venue, &Data), sell: (Venue, &Data), symbol: Symbol, config: &Config) -> Self {
+        let (buy_venue, ask) = buy;
+        let (sell_venue, bid) = sell;
+        let qty = match config.max_trade_qty {
+            Some(max_qty) => ask.qty.min(bid.qty).min(Qty(max_qty)),
+            None => ask.qty.min(bid.qty),
+        };
+        let qty = Qty(symbol.base_currency().round_down(qty.0));
+
+        let gross = (bid.price - ask.price) * qty;
+        let fees = ask.price * qty * modeled_fee_rate(config, buy_venue, ask.price.0, qty.0)
+            + bid.price * qty * modeled_fee_rate(config, sell_venue, bid.price.0, qty.0);
+        let slippage = Notional(slippage_cost(
+            ask.price.0,
+            bid.price.0,
+            qty.0,
+            config.slippage_bps_for(symbol, qty.0),
+        ));
+        let transfer = Notional(transfer_cost(
+            config,
+            buy_venue,
+            sell_venue,
+            symbol,
+            qty.0,
+            ask.price.0,
+        ));
+
+        Self {
+            buy_venue,
+            sell_venue,
+            buy_price: ask.price,
+            sell_price: bid.price,
+            qty,
+            gross,
+            fees,
+            slippage,
+            transfer,
+            net: gross - fees - slippage - transfer,
+        }
+    }
+
+    fn into_action(self, symbol: Symbol, config: &Config) -> Result<TradeAction, SameVenue> {
+        TradeAction::new(
+            (self.buy_venue, self.buy_price.0),
+            (self.sell_venue, self.sell_price.0),
+            self.qty.0,
+            symbol,
+            -self.fees.0,
+            self.net.0,
+            config.time_in_force,
+        )
+        .map(|action| action.with_post_only(config.post_only_venue))
+    }
+}
+
+fn transfer_cost(
+    config: &Config,
+    buy_venue: Venue,
+    sell_venue: Venue,
+    symbol: Symbol,
+    qty: f64,
+    price: f64,
+) -> f64 {
+    // with netting the transfers are charged at settlement instead
+    if !config.charge_transfer_costs || config.transfer_settlement_trades.is_some() {
+        return 0.0;
+    }
+    config
+        .withdrawal_fees
+        .transfer_cost(buy_venue, sell_venue, symbol, qty, price)
+}
+
+// best relative spread across both directions, net of both venues' fees
+fn best_net_spread(
+    binance_order_book: &OrderBook,
+    mb_order_book: &OrderBook,
+    config: &Config,
+) -> Option<f64> {
+    let binance_ask = binance_order_book.asks.first()?;
+    let binance_bid = binance_order_book.bids.first()?;
+    let mb_ask = mb_order_book.asks.first()?;
+    let mb_bid = mb_order_book.bids.first()?;
+
+    let short_mb = mb_bid.price.relative_to(binance_ask.price)?
+        - config.binance_fee_rate
+        - get_mb_fee_rate(mb_bid.price.0, mb_bid.qty.0);
+    let short_binance = binance_bid.price.relative_to(mb_ask.price)?
+        - config.binance_fee_rate
+        - get_mb_fee_rate(mb_ask.price.0, mb_ask.qty.0);
+
+    Some(f64::max(short_mb, short_binance))
+}
+
+fn buy_fill_price(ask_price: f64, slippage_bps: f64) -> f64 {
+    ask_price * (1.0 + slippage_bps / 10_000.0)
+}
+
+fn sell_fill_price(bid_price: f64, slippage_bps: f64) -> f64 {
+    bid_price * (1.0 - slippage_bps / 10_000.0)
+}
+
+fn slippage_cost(ask_price: f64, bid_price: f64, qty: f64, slippage_bps: f64) -> f64 {
+    let buy_slippage = buy_fill_price(ask_price, slippage_bps) - ask_price;
+    let sell_slippage = bid_price - sell_fill_price(bid_price, slippage_bps);
+    (buy_slippage + sell_slippage) * qty
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub enum TradeAction {
+    ShortBinance {
+        ask_price: f64,
+        bid_price: f64,
+        qty: f64,
+        symbol: Symbol,
+        costs: f64,
+        // after fees, slippage and transfer costs, in the quote currency
+        net_profit: f64,
+        time_in_force: TimeInForce,
+        post_only_venue: Option<Venue>,
+    },
+    ShortMb {
+        ask_price: f64,
+        bid_price: f64,
+        qty: f64,
+        symbol: Symbol,
+        costs: f64,
+        // after fees, slippage and transfer costs, in the quote currency
+        net_profit: f64,
+        time_in_force: TimeInForce,
+        post_only_venue: Option<Venue>,
+    },
+}
+
+// orders actions by net profit, a NaN profit ranking below every other
+#[derive(Debug)]
+struct RankedAction(TradeAction);
+
+impl Ord for RankedAction {
+    fn cmp(&self, other: &Self) -> Ordering {
+        let (a, b) = (self.0.net_profit(), other.0.net_profit());
+        match (a.is_nan(), b.is_nan()) {
+            (true, true) => Ordering::Equal,
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+            (false, false) => a.total_cmp(&b),
+        }
+    }
+}
+
+impl PartialOrd for RankedAction {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for RankedAction {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for RankedAction {}
+
+// both legs of an arbitrage were routed to the same venue, which would self-trade
+#[derive(Clone, Copy, Debug)]
+struct SameVenue {
+    venue: Venue,
+}
+
+impl TradeAction {
+    fn direction(&self) -> &'static str {
+        match self {
+            Self::ShortBinance { .. } => "ShortBinance",
+            Self::ShortMb { .. } => "ShortMb",
+        }
+    }
+
+    // (buy venue, sell venue)
+    fn venues(&self) -> (Venue, Venue) {
+        match self {
+            Self::ShortBinance { .. } => (Venue::MercadoBitcoin, Venue::Binance),
+            Self::ShortMb { .. } => (Venue::Binance, Venue::MercadoBitcoin),
+        }
+    }
+
+    fn ask_price(&self) -> f64 {
+        match self {
+            Self::ShortBinance { ask_price, .. } | Self::ShortMb { ask_price, .. } => *ask_price,
+        }
+    }
+
+    fn bid_price(&self) -> f64 {
+        match self {
+            Self::ShortBinance { bid_price, .. } | Self::ShortMb { bid_price, .. } => *bid_price,
+        }
+    }
+
+    fn qty(&self) -> f64 {
+        match self {
+            Self::ShortBinance { qty, .. } | Self::ShortMb { qty, .. } => *qty,
+        }
+    }
+
+    fn symbol(&self) -> Symbol {
+        match self {
+            Self::ShortBinance { symbol, .. } | Self::ShortMb { symbol, .. } => *symbol,
+        }
+    }
+
+    fn net_profit(&self) -> f64 {
+        match self {
+            Self::ShortBinance { net_profit, .. } | Self::ShortMb { net_profit, .. } => *net_profit,
+        }
+    }
+
+    // sends the leg on `venue`, if any, post-only
+    fn with_post_only(mut self, venue: Option<Venue>) -> Self {
+        match &mut self {
+            Self::ShortBinance {
+                post_only_venue, ..
+            }
+            | Self::ShortMb {
+                post_only_venue, ..
+            } => *post_only_venue = venue,
+        }
+        self
+    }
+
+    fn new(
+        buy: (Venue, f64),
+        sell: (Venue, f64),
+        qty: f64,
+        symbol: Symbol,
+        costs: f64,
+        net_profit: f64,
+        time_in_force: TimeInForce,
+    ) -> Result<Self, SameVenue> {
+        let (buy_venue, ask_price) = buy;
+        let (sell_venue, bid_price) = sell;
+        match (buy_venue, sell_venue) {
+            (Venue::Binance, Venue::MercadoBitcoin) => Ok(Self::ShortMb {
+                ask_price,
+                bid_price,
+                qty,
+                symbol,
+                costs,
+                net_profit,
+                time_in_force,
+                post_only_venue: None,
+            }),
+            (Venue::MercadoBitcoin, Venue::Binance) => Ok(Self::ShortBinance {
+                ask_price,
+                bid_price,
+                qty,
+                symbol,
+                costs,
+                net_profit,
+                time_in_force,
+                post_only_venue: None,
+            }),
+            (venue, _) => Err(SameVenue { venue }),
+        }
+    }
+}
+
+fn get_default_balance(base_currency: Currency, starting_balance: f64) -> HashMap<Currency, f64> {
+    let mut balances: HashMap<Currency, f64> = HashMap::new();
+    balances.insert(Currency::BRL, 0.0);
+    balances.insert(Currency::BTC, 0.0);
+    balances.insert(Currency::ETH, 0.0);
+    balances.insert(Currency::USDT, 0.0);
+    balances.insert(base_currency, starting_balance);
+    balances
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize)]
+enum Side {
+    Buy,
+    Sell,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Leg {
+    venue: Venue,
+    side: Side,
+}
+
+#[derive(Clone, Copy, Debug, Serialize)]
+struct Fill {
+    leg: Leg,
+    symbol: Symbol,
+    price: f64,
+    qty: f64,
+}
+
+#[derive(Clone, Debug)]
+struct TradeResult {
+    symbol: Symbol,
+    // the action's variant, e.g. ShortMb
+    direction: &'static str,
+    new_balance: HashMap<Currency, f64>,
+    realized_profit: f64,
+    fees: f64,
+    // base quantity left open after all legs, positive when long
+    residual_exposure: f64,
+    // base quantity the trade's own legs were sent for and what they filled, corrective
+    // orders aside
+    attempted_qty: f64,
+    filled_qty: f64,
+    fills: Vec<Fill>,
+    executed_legs: Vec<Leg>,
+}
+
+#[cfg(feature = "io")]
+async fn take_trade_action(
+    action: TradeAction,
+    current_balance: HashMap<Currency, f64>,
+    config: &Config,
+    executor: &impl OrderExecutor,
+    binance_order_book: &OrderBook,
+    mb_order_book: &OrderBook,
+    halt: &CancellationToken,
+) -> Result<TradeResult, Error> {
+    let (buy_venue, sell_venue) = action.venues();
+    let direction = action.direction();
+    let (ask_price, bid_price, qty, symbol, time_in_force, post_only_venue) = match action {
+        TradeAction::ShortBinance {
+            ask_price,
+            bid_price,
+            qty,
+            symbol,
+            time_in_force,
+            post_only_venue,
+            ..
+        }
+        | TradeAction::ShortMb {
+            ask_price,
+            bid_price,
+            qty,
+            symbol,
+            time_in_force,
+            post_only_venue,
+            ..
+        } => (
+            ask_price,
+            bid_price,
+            qty,
+            symbol,
+            time_in_force,
+            post_only_venue,
+        ),
+    };
+
+    let orders_for = |qty| {
+        let mut orders = [
+            Order {
+                leg: Leg {
+                    venue: sell_venue,
+                    side: Side::Sell,
+                },
+                symbol,
+                price: bid_price,
+                qty,
+                time_in_force,
+                post_only: post_only_venue == Some(sell_venue),
+            },
+            Order {
+                leg: Leg {
+                    venue: buy_venue,
+                    side: Side::Buy,
+                },
+                symbol,
+                price: ask_price,
+                qty,
+                time_in_force,
+                post_only: post_only_venue == Some(buy_venue),
+            },
+        ];
+        // the post-only leg is the one that may not fill, so nothing else goes out before it
+        orders.sort_by_key(|order| !order.post_only);
+        orders
+    };
+
+    let mut new_balance = current_balance;
+    let mut executed_legs = Vec::new();
+    let mut fills = Vec::new();
+    let mut realized_profit = 0.0;
+    let mut fees = 0.0;
+    let mut attempted_qty = 0.0;
+    let mut filled_qty = 0.0;
+
+    let slices = ladder(qty, config.ladder_slices, symbol.base_currency());
+    for (index, slice_qty) in slices.iter().enumerate() {
+        if halt.is_cancelled() {
+            log!(
+                "halted, leaving {} of {} slices unsent",
+                slices.len() - index,
+                slices.len()
+            );
+            break;
+        }
+        if index > 0 && config.ladder_spacing_ms > 0 {
+            sleep(Duration::from_millis(config.ladder_spacing_ms)).await;
+        }
+        if slices.len() > 1 {
+            log!(
+                "executing slice {} of {}: {} of {}",
+                index + 1,
+                slices.len(),
+                slice_qty,
+                qty
+            );
+        }
+        let orders = orders_for(*slice_qty);
+        let leg_fills = if config.concurrent_legs && !orders.iter().any(|order| order.post_only) {
+            execute_concurrently(
+                executor,
+                &orders,
+                config,
+                binance_order_book,
+                mb_order_book,
+                halt,
+            )
+            .await
+        } else {
+            execute_sequentially(executor, &orders, config, halt).await
+        };
+        // a failed slice after others filled stops the ladder, keeping what already filled
+        let leg_fills = match leg_fills {
+            Ok(leg_fills) => leg_fills,
+            Err(error) if fills.is_empty() => return Err(error),
+            Err(error) => {
+                output::error(
+                    "ladder_slice_failed",
+                    json!({
+                        "slice": index + 1,
+                        "slices": slices.len(),
+                        "filled_qty": filled_qty,
+                        "error": error.to_string(),
+                    }),
+                    format_args!(
+                        "slice {} of {} failed, stopping the ladder with {} filled: {}",
+                        index + 1,
+                        slices.len(),
+                        filled_qty,
+                        error
+                    ),
+                );
+                break;
+            }
+        };
+        // a post-only leg left unfilled abandons the rest of the ladder along with its slice
+        let abandoned = leg_fills.len() < orders.len();
+        let sent = if abandoned {
+            leg_fills.len() + 1
+        } else {
+            orders.len()
+        };
+        attempted_qty += orders[..sent].iter().map(|order| order.qty).sum::<f64>();
+        filled_qty += leg_fills.iter().map(|fill| fill.qty).sum::<f64>();
+        for fill in leg_fills {
+            if fill.qty <= 0.0 {
+                continue;
+            }
+            let (fill, notional, fee) = apply_fill(&mut new_balance, fill, config);
+            executed_legs.push(fill.leg);
+            realized_profit += notional - fee;
+            fees += fee;
+            fills.push(fill);
+        }
+        if abandoned {
+            break;
+        }
+    }
+
+    // legs can fill differently, so flatten whatever is left open
+    let net_qty = net_filled_qty(&fills);
+    if net_qty.abs() > config.leg_imbalance_tolerance {
+        for order in corrective_orders(net_qty, symbol, config, binance_order_book, mb_order_book) {
+            log!(
+                "legs imbalanced by {}, correcting with {:?} {} on {:?}",
+                net_qty,
+                order.leg.side,
+                order.qty,
+                order.leg.venue
+            );
+            let fill = execute(executor, &order, config).await?;
+            if fill.qty > 0.0 {
+                let (fill, notional, fee) = apply_fill(&mut new_balance, fill, config);
+                executed_legs.push(fill.leg);
+                realized_profit += notional - fee;
+                fees += fee;
+                fills.push(fill);
+            }
+        }
+    }
+
+    Ok(TradeResult {
+        symbol,
+        direction,
+        new_balance,
+        realized_profit,
+        fees,
+        residual_exposure: net_filled_qty(&fills),
+        attempted_qty,
+        filled_qty,
+        fills,
+        executed_legs,
+    })
+}
+
+// splits qty into `slices` child quantities rounded to the currency's step, the last one
+// taking the remainder so that they add up to qty; too small a qty to split stays whole
+fn ladder(qty: f64, slices: usize, currency: Currency) -> Vec<f64> {
+    let slice_qty = currency.round_down(qty / slices.max(1) as f64);
+    if slices <= 1 || slice_qty <= 0.0 {
+        return vec![qty];
+    }
+    let mut ladder = vec![slice_qty; slices - 1];
+    // rounded to nearest so that float error in the subtraction doesn't drop a unit
+    ladder.push(
+        currency
+            .round(qty - slice_qty * (slices - 1) as f64)
+            .max(0.0),
+    );
+    ladder
+}
+
+// one leg after the other; nothing follows a post-only leg that didn't fill, and a halt
+// cancels the leg in flight but still sends the next so that the two can be netted
+#[cfg(feature = "io")]
+async fn execute_sequentially(
+    executor: &impl OrderExecutor,
+    orders: &[Order],
+    config: &Config,
+    halt: &CancellationToken,
+) -> Result<Vec<Fill>, Error> {
+    let mut fills = Vec::new();
+    for order in orders {
+        let fill = execute_leg(executor, order, config, &halt.child_token()).await?;
+        if order.post_only && fill.qty <= 0.0 {
+            log!(
+                "post-only {:?} leg on {:?} did not fill, abandoning the trade",
+                order.leg.side,
+                order.leg.venue
+            );
+            break;
+        }
+        fills.push(fill);
+    }
+    Ok(fills)
+}
+
+// both legs at once, returning only when both have confirmed; if either fails, whatever the
+// other filled is unwound and nothing is returned to be booked, and if either times out the
+// other is cancelled with it and the imbalance is left to the corrective order, as on a halt
+#[cfg(feature = "io")]
+async fn execute_concurrently(
+    executor: &impl OrderExecutor,
+    orders: &[Order; 2],
+    config: &Config,
+    binance_order_book: &OrderBook,
+    mb_order_book: &OrderBook,
+    halt: &CancellationToken,
+) -> Result<Vec<Fill>, Error> {
+    let legs = halt.child_token();
+    let (first, second) = tokio::join!(
+        execute_leg(executor, &orders[0], config, &legs),
+        execute_leg(executor, &orders[1], config, &legs)
+    );
+    let (fill, error) = match (first, second) {
+        (Ok(first), Ok(second)) => return Ok(vec![first, second]),
+        (Ok(fill), Err(error)) | (Err(error), Ok(fill)) => (fill, error),
+        (Err(error), Err(_)) => return Err(error),
+    };
+
+    let net_qty = net_filled_qty(&[fill]);
+    if net_qty.abs() > config.leg_imbalance_tolerance {
+        for order in corrective_orders(
+            net_qty,
+            fill.symbol,
+            config,
+            binance_order_book,
+            mb_order_book,
+        ) {
+            log!(
+                "a leg failed, rolling back the other with {:?} {} on {:?}",
+                order.leg.side,
+                order.qty,
+                order.leg.venue
+            );
+            if let Err(rollback_error) = execute(executor, &order, config).await {
+                log!("rollback failed: {}", rollback_error);
+            }
+        }
+    }
+    Err(error)
+}
+
+// moves the balance by a fill at its slippage-adjusted price, returning the adjusted fill,
+// its signed quote notional and its fee valued in quote
+fn apply_fill(
+    balance: &mut HashMap<Currency, f64>,
+    fill: Fill,
+    config: &Config,
+) -> (Fill, f64, f64) {
+    let slippage_bps = config.slippage_bps_for(fill.symbol, fill.qty);
+    let price = match fill.leg.side {
+        Side::Buy => buy_fill_price(fill.price, slippage_bps),
+        Side::Sell => sell_fill_price(fill.price, slippage_bps),
+    };
+    let fill = Fill { price, ..fill };
+    let (notional, fee) = apply_adjusted_fill(balance, fill, config);
+    (fill, notional, fee)
+}
+
+// moves the balance by a fill whose price already includes slippage
+fn apply_adjusted_fill(
+    balance: &mut HashMap<Currency, f64>,
+    fill: Fill,
+    config: &Config,
+) -> (f64, f64) {
+    let base_currency = fill.symbol.base_currency();
+    let quote_currency = fill.symbol.quote_currency();
+    let notional = match fill.leg.side {
+        Side::Buy => {
+            *balance.entry(base_currency).or_insert(0.0) += fill.qty;
+            *balance.entry(quote_currency).or_insert(0.0) -= fill.qty * fill.price;
+            -fill.qty * fill.price
+        }
+        Side::Sell => {
+            *balance.entry(base_currency).or_insert(0.0) -= fill.qty;
+            *balance.entry(quote_currency).or_insert(0.0) += fill.qty * fill.price;
+            fill.qty * fill.price
+        }
+    };
+    let fee_rate = get_fee_rate(config, fill.leg.venue, fill.price, fill.qty);
+    let fee = fill.price * fill.qty * fee_rate;
+    match (config.fee_currency_for(fill.leg.venue), fill.leg.side) {
+        (FeeCurrency::Received, Side::Buy) => {
+            *balance.entry(base_currency).or_insert(0.0) -= fill.qty * fee_rate;
+        }
+        (FeeCurrency::Received, Side::Sell) | (FeeCurrency::Quote, _) => {
+            *balance.entry(quote_currency).or_insert(0.0) -= fee;
+        }
+    }
+    (notional, fee)
+}
+
+// books a fill, already adjusted by apply_fill, to the account its leg is picked for
+#[cfg(feature = "io")]
+fn book_to_account(accounts: &mut Accounts, fill: Fill, config: &Config) {
+    let account = accounts.pick();
+    log!(
+        "booking {:?} leg on {:?} to account {}",
+        fill.leg.side,
+        fill.leg.venue,
+        account.name
+    );
+    apply_adjusted_fill(&mut account.balance, fill, config);
+}
+
+fn book_to_venue(
+    venue_balances: &mut HashMap<Venue, HashMap<Currency, f64>>,
+    fill: Fill,
+    config: &Config,
+) {
+    apply_adjusted_fill(
+        venue_balances.entry(fill.leg.venue).or_default(),
+        fill,
+        config,
+    );
+}
+
+// quote notional of the fills executed on Binance, which counts towards its VIP tier
+fn binance_notional(fills: &[Fill]) -> f64 {
+    fills
+        .iter()
+        .filter(|fill| fill.leg.venue == Venue::Binance)
+        .map(|fill| fill.price * fill.qty)
+        .sum()
+}
+
+// base quantity bought minus sold across the fills
+fn net_filled_qty(fills: &[Fill]) -> f64 {
+    fills
+        .iter()
+        .map(|fill| match fill.leg.side {
+            Side::Buy => fill.qty,
+            Side::Sell => -fill.qty,
+        })
+        .sum()
+}
+
+// offsets a net position on whichever venue currently gives the better price for it, or
+// routed across both with route_corrective_orders
+fn corrective_orders(
+    net_qty: f64,
+    symbol: Symbol,
+    config: &Config,
+    binance_order_book: &OrderBook,
+    mb_order_book: &OrderBook,
+) -> Vec<Order> {
+    let book = OrderBook::merge_tagged(&[
+        (Venue::Binance, binance_order_book),
+        (Venue::MercadoBitcoin, mb_order_book),
+    ]);
+    let side = if net_qty > 0.0 { Side::Sell } else { Side::Buy };
+    let order = |venue, price, qty| Order {
+        leg: Leg { venue, side },
+        symbol,
+        price,
+        qty: symbol.base_currency().round_down(qty),
+        time_in_force: TimeInForce::IOC,
+        post_only: false,
+    };
+
+    if config.route_corrective_orders {
+        return route(&book, side, net_qty.abs(), config)
+            .into_iter()
+            .map(|sub_order| order(sub_order.venue, sub_order.price, sub_order.qty))
+            .collect();
+    }
+    let best = match side {
+        Side::Sell => book.bids.first(),
+        Side::Buy => book.asks.first(),
+    };
+    best.map(|(venue, level)| order(*venue, level.price.0, net_qty.abs()))
+        .into_iter()
+        .collect()
+}
+
+fn get_fee_rate(config: &Config, venue: Venue, price: f64, qty: f64) -> f64 {
+    match venue {
+        Venue::Binance => config.binance_fee_rate,
+        Venue::MercadoBitcoin => get_mb_fee_rate(price, qty),
+    }
+}
+
+// the rate decisions are priced at, with the configured buffer on top of the schedule
+fn modeled_fee_rate(config: &Config, venue: Venue, price: f64, qty: f64) -> f64 {
+    get_fee_rate(config, venue, price, qty) * config.fee_buffer
+}
+
+fn get_mb_fee_rate(price: f64, qty: f64) -> f64 {
+    let deal_total = price * qty;
+
+    match deal_total {
+        t if t <= 500_000.0 => 0.007,
+        t if t <= 1_000_000.0 => 0.006,
+        t if t <= 2_000_000.0 => 0.005,
+        t if t <= 5_000_000.0 => 0.0045,
+        t if t <= 10_000_000.0 => 0.004,
+        t if t <= 20_000_000.0 => 0.003,
+        _ => 0.0025,
+    }
+}
+
+#[derive(Clone, Copy, Serialize, Deserialize, Debug)]
+struct Data {
+    qty: Qty,
+    price: Price,
+}
+
+impl Data {
+    fn new(price: Price, qty: Qty) -> Self {
+        Self { qty, price }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct OrderBook {
+    bids: Vec<Data>,
+    asks: Vec<Data>,
+    // exchange-reported update time, in server clock
+    updated_at_ms: Option<u64>,
+    // local time the book was fetched
+    fetched_at_ms: Option<u64>,
+    // published by the venue along with the levels, when its feed carries one; see
+    // incremental::checksum
+    #[serde(default)]
+    checksum: Option<i32>,
+}
+
+impl OrderBook {
+    fn builder() -> OrderBookBuilder {
+        OrderBookBuilder::default()
+    }
+
+    // a level that doesn't parse is dropped rather than guessed at
+    pub fn new_from_string(bids: Vec<[String; 2]>, asks: Vec<[String; 2]>) -> Self {
+        let parse = |[price, qty]: [String; 2]| match (
+            parse_number(&price, false),
+            parse_number(&qty, false),
+        ) {
+            (Ok(price), Ok(qty)) => Some([price, qty]),
+            (Err(error), _) | (_, Err(error)) => {
+                log!("dropping book level: {}", error);
+                None
+            }
+        };
+        Self::new_from_f64(
+            bids.into_iter().filter_map(parse).collect(),
+            asks.into_iter().filter_map(parse).collect(),
+        )
+    }
+
+    fn new_from_f64(bids: Vec<[f64; 2]>, asks: Vec<[f64; 2]>) -> Self {
+        let builder = bids
+            .into_iter()
+            .fold(Self::builder(), |builder, [price, qty]| {
+                builder.bid(price, qty)
+            });
+        asks.into_iter()
+            .fold(builder, |builder, [price, qty]| builder.ask(price, qty))
+            .build()
+    }
+
+    fn quotes_zero_price(&self) -> bool {
+        [self.bids.first(), self.asks.first()]
+            .into_iter()
+            .flatten()
+            .any(|level| level.price.0 <= 0.0)
+    }
+
+    // some feeds keep zero-quantity levels around, e.g. as deletions in a diff
+    fn normalized(mut self, config: &Config) -> Self {
+        if config.drop_zero_qty_levels {
+            self.bids.retain(|level| level.qty > Qty(0.0));
+            self.asks.retain(|level| level.qty > Qty(0.0));
+        }
+        self
+    }
+
+    // same book with prices multiplied by `rate`, e.g. to quote a USDT book in BRL
+    fn converted(&self, rate: f64) -> OrderBook {
+        let convert = |levels: &[Data]| {
+            levels
+                .iter()
+                .map(|level| Data::new(level.price * rate, level.qty))
+                .collect::<Vec<Data>>()
+        };
+        OrderBook {
+            bids: convert(&self.bids),
+            asks: convert(&self.asks),
+            updated_at_ms: self.updated_at_ms,
+            fetched_at_ms: self.fetched_at_ms,
+            // covers the original prices, not the converted ones
+            checksum: None,
+        }
+    }
+
+    // (bid volume - ask volume) / total volume over the best `levels` of each side, in -1..=1
+    fn imbalance(&self, levels: usize) -> Option<f64> {
+        let volume = |side: &[Data]| {
+            side.iter()
+                .take(levels)
+                .map(|level| level.qty)
+                .sum::<Qty>()
+                .0
+        };
+        let bid_volume = volume(&self.bids);
+        let ask_volume = volume(&self.asks);
+        let total = bid_volume + ask_volume;
+        if total <= 0.0 {
+            return None;
+        }
+        Some((bid_volume - ask_volume) / total)
+    }
+
+    fn mid_price(&self) -> Option<f64> {
+        let best_bid = self.bids.first()?;
+        let best_ask = self.asks.first()?;
+        Some((best_bid.price.0 + best_ask.price.0) / 2.0)
+    }
+
+    // top-of-book prices weighted by the size on the opposite side: a thin ask against a deep
+    // bid puts the price near the ask, where it's likely to move next
+    fn microprice(&self) -> Option<f64> {
+        let best_bid = self.bids.first()?;
+        let best_ask = self.asks.first()?;
+        let total_qty = best_bid.qty.0 + best_ask.qty.0;
+        if total_qty <= 0.0 {
+            return self.mid_price();
+        }
+        Some((best_bid.price.0 * best_ask.qty.0 + best_ask.price.0 * best_bid.qty.0) / total_qty)
+    }
+
+    fn fair_value(&self, fair_value: FairValue) -> Option<f64> {
+        match fair_value {
+            FairValue::Mid => self.mid_price(),
+            FairValue::Microprice => self.microprice(),
+        }
+    }
+
+    // every venue's levels in one ladder per side, best first; equal prices keep the order
+    // the books are given in
+    fn merge_tagged(books: &[(Venue, &OrderBook)]) -> TaggedBook {
+        let tagged = |side: fn(&OrderBook) -> &Vec<Data>| -> Vec<(Venue, Data)> {
+            books
+                .iter()
+                .flat_map(|(venue, order_book)| {
+                    side(order_book).iter().map(move |level| (*venue, *level))
+                })
+                .collect()
+        };
+        let mut bids = tagged(|order_book| &order_book.bids);
+        let mut asks = tagged(|order_book| &order_book.asks);
+        bids.sort_by(|a, b| b.1.price.0.total_cmp(&a.1.price.0));
+        asks.sort_by(|a, b| a.1.price.0.total_cmp(&b.1.price.0));
+        TaggedBook { bids, asks }
+    }
+
+    // books without an exchange timestamp fall back to their fetch time in server clock
+    fn last_update_ms(&self, clock_offset_ms: i64) -> Option<u64> {
+        self.updated_at_ms.or_else(|| {
+            self.fetched_at_ms
+                .map(|fetched_at_ms| (fetched_at_ms as i64 + clock_offset_ms) as u64)
+        })
+    }
+}
+
+// a consolidated book across venues, each level tagged with the venue it rests on
+#[derive(Clone, Debug, Default)]
+struct TaggedBook {
+    bids: Vec<(Venue, Data)>,
+    asks: Vec<(Venue, Data)>,
+}
+
+fn book_age_gap_ms(a: &OrderBook, b: &OrderBook, clock_offset_ms: i64) -> Option<u64> {
+    let a = a.last_update_ms(clock_offset_ms)?;
+    let b = b.last_update_ms(clock_offset_ms)?;
+    Some(a.abs_diff(b))
+}
+
+// collects levels in any order; `build` puts the best bid and the best ask first
+#[derive(Clone, Debug, Default)]
+struct OrderBookBuilder {
+    bids: Vec<Data>,
+    asks: Vec<Data>,
+}
+
+impl OrderBookBuilder {
+    fn bid(mut self, price: f64, qty: f64) -> Self {
+        self.bids.push(Data::new(Price(price), Qty(qty)));
+        self
+    }
+
+    fn ask(mut self, price: f64, qty: f64) -> Self {
+        self.asks.push(Data::new(Price(price), Qty(qty)));
+        self
+    }
+
+    fn build(mut self) -> OrderBook {
+        debug_assert!(
+            self.bids.iter().chain(&self.asks).all(|level| {
+                level.price.0.is_finite() && level.price.0 >= 0.0 && level.qty.0.is_finite()
+            }),
+            "order book levels need finite, non-negative prices and finite quantities"
+        );
+        self.bids.sort_by(|a, b| b.price.0.total_cmp(&a.price.0));
+        self.asks.sort_by(|a, b| a.price.0.total_cmp(&b.price.0));
+        OrderBook {
+            bids: self.bids,
+            asks: self.asks,
+            updated_at_ms: None,
+            fetched_at_ms: None,
+            checksum: None,
+        }
+    }
+}
+
+impl From<BinanceOrderBookData> for OrderBook {
+    fn from(value: BinanceOrderBookData) -> Self {
+        OrderBook::new_from_string(value.bids, value.asks)
+    }
+}
+
+impl From<MBOrderBookData> for OrderBook {
+    fn from(value: MBOrderBookData) -> Self {
+        let mut order_book = OrderBook::new_from_f64(value.bids, value.asks);
+        // MB reports the timestamp in seconds
+        order_book.updated_at_ms = Some(value.timestamp * 1000);
+        order_book
+    }
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct BinanceOrderBookData {
+    #[allow(dead_code)]
+    #[serde(rename = "lastUpdateId")]
+    pub last_update_id: u64,
+    pub bids: Vec<[String; 2]>,
+    pub asks: Vec<[String; 2]>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+struct MBOrderBookData {
+    pub timestamp: u64,
+    pub bids: Vec<[f64; 2]>,
+    pub asks: Vec<[f64; 2]>,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub enum Currency {
+    BRL,
+    BTC,
+    USDT,
+    ETH,
+}
+
+impl Currency {
+    fn from_asset(asset: &str) -> Option<Self> {
+        match asset {
+            "BRL" => Some(Self::BRL),
+            "BTC" => Some(Self::BTC),
+            "USDT" => Some(Self::USDT),
+            "ETH" => Some(Self::ETH),
+            _ => None,
+        }
+    }
+
+    // precision amounts are kept and shown at; ETH trades at 8 on both venues despite its 18
+    fn decimals(self) -> u32 {
+        match self {
+            Self::BRL => 2,
+            Self::BTC => 8,
+            Self::USDT => 6,
+            Self::ETH => 8,
+        }
+    }
+
+    // smallest representable amount
+    fn unit(self) -> f64 {
+        1.0 / 10f64.powi(self.decimals() as i32)
+    }
+
+    // to the nearest amount at the currency's precision, for presentation only
+    fn round(self, amount: f64) -> f64 {
+        let scale = 10f64.powi(self.decimals() as i32);
+        (amount * scale).round() / scale
+    }
+
+    // truncates to the currency's precision, so an amount never rounds up past what's held
+    fn round_down(self, amount: f64) -> f64 {
+        let scale = 10f64.powi(self.decimals() as i32);
+        let scaled = amount * scale;
+        // the nudge keeps e.g. 0.3 from flooring to 0.29999999 through representation error;
+        // it's relative to the amount so that an amount just below a step isn't pushed over it
+        (scaled + scaled.abs() * 4.0 * f64::EPSILON).floor() / scale
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Venue {
+    Binance,
+    MercadoBitcoin,
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Symbol {
+    #[default]
+    BTCBRL,
+    USDTBRL,
+    ETHBRL,
+}
+
+impl Symbol {
+    fn get_binance_symbol_param(&self) -> &str {
+        match self {
+            Self::BTCBRL => "BTCBRL",
+            Self::USDTBRL => "USDTBRL",
+            Self::ETHBRL => "ETHBRL",
+        }
+    }
+
+    // the USDT-quoted pair on Binance with the same base asset
+    fn get_binance_usdt_symbol_param(&self) -> Option<&str> {
+        match self {
+            Self::BTCBRL => Some("BTCUSDT"),
+            Self::ETHBRL => Some("ETHUSDT"),
+            Self::USDTBRL => None,
+        }
+    }
+
+    fn from_binance_symbol_param(param: &str) -> Option<Self> {
+        match param {
+            "BTCBRL" => Some(Self::BTCBRL),
+            "USDTBRL" => Some(Self::USDTBRL),
+            "ETHBRL" => Some(Self::ETHBRL),
+            _ => None,
+        }
+    }
+
+    fn base_currency(&self) -> Currency {
+        match self {
+            Self::BTCBRL => Currency::BTC,
+            Self::USDTBRL => Currency::USDT,
+            Self::ETHBRL => Currency::ETH,
+        }
+    }
+
+    // every listed pair is BRL-quoted on both venues
+    fn quote_currency(&self) -> Currency {
+        Currency::BRL
+    }
+
+    fn get_mb_symbol_param(&self) -> &str {
+        match self {
+            Self::BTCBRL => "BTC",
+            Self::USDTBRL => "USDT",
+            Self::ETHBRL => "ETH",
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Binance offering below MB's bid, leaving a ShortMb opportunity
+    pub(super) fn profitable_books() -> (OrderBook, OrderBook) {
+        (
+            OrderBook::builder()
+                .bid(9_900.0, 1.0)
+                .ask(10_000.0, 1.0)
+                .build(),
+            OrderBook::builder()
+                .bid(10_500.0, 1.0)
+                .ask(10_600.0, 1.0)
+                .build(),
+        )
+    }
+
+    #[test]
+    fn gates_apply_to_the_action_the_hedged_pair_chooses() {
+        let config = Config {
+            min_direction_imbalance: Some(0.1),
+            starting_balance: 1_000_000.0,
+            ..Config::default()
+        };
+        // both directions pay; bids dominate on Binance, which favours buying there
+        let binance = OrderBook::builder()
+            .bid(10_700.0, 3.0)
+            .ask(10_000.0, 1.0)
+            .build();
+        let mb = OrderBook::builder()
+            .bid(10_500.0, 1.0)
+            .ask(10_600.0, 1.0)
+            .build();
+        let balance = get_default_balance(config.base_currency, config.starting_balance);
+        let decision = decide(&binance, &mb, &balance, &config, 0.0, None);
+        assert!(
+            matches!(decision, Decision::Trade(TradeAction::ShortMb { .. })),
+            "{:?}",
+            decision
+        );
+
+        // with nothing left to sell on MB, the pair picks selling on Binance, which the
+        // imbalance gate rejects
+        let mut hedged_pair = HedgedPair::new(1.0);
+        hedged_pair.record(&[Fill {
+            leg: Leg {
+                venue: Venue::MercadoBitcoin,
+                side: Side::Sell,
+            },
+            symbol: Symbol::BTCBRL,
+            price: 10_500.0,
+            qty: 1.0,
+        }]);
+        let decision = decide(&binance, &mb, &balance, &config, 0.0, Some(&hedged_pair));
+        assert!(
+            matches!(decision, Decision::ImbalanceTooLow { .. }),
+            "{:?}",
+            decision
+        );
+    }
+
+    // Binance asking 10000 and MB bidding 10085, a little over the 0.8% both venues charge
+    fn marginal_books() -> (OrderBook, OrderBook) {
+        (
+            OrderBook::builder()
+                .bid(9_900.0, 1.0)
+                .ask(10_000.0, 1.0)
+                .build(),
+            OrderBook::builder()
+                .bid(10_085.0, 1.0)
+                .ask(10_200.0, 1.0)
+                .build(),
+        )
+    }
+
+    #[test]
+    fn a_slippage_buffer_rejects_marginal_trades() {
+        let (binance, mb) = marginal_books();
+        let config = Config::default();
+        let actions = check_arbitrage(&binance, &mb, Symbol::BTCBRL, &config, 0.0);
+        assert!(matches!(actions[..], [TradeAction::ShortMb { .. }]));
+
+        let config = Config {
+            slippage_bps: 5.0,
+            ..Config::default()
+        };
+        assert!(check_arbitrage(&binance, &mb, Symbol::BTCBRL, &config, 0.0).is_empty());
+    }
+
+    #[test]
+    fn slippage_worsens_the_booked_fill_prices() {
+        let config = Config {
+            slippage_bps: 5.0,
+            ..Config::default()
+        };
+        let mut balance = HashMap::new();
+        let fill = |side| Fill {
+            leg: Leg {
+                venue: Venue::Binance,
+                side,
+            },
+            symbol: Symbol::BTCBRL,
+            price: 10_000.0,
+            qty: 1.0,
+        };
+        assert_eq!(
+            apply_fill(&mut balance, fill(Side::Buy), &config).0.price,
+            10_005.0
+        );
+        assert_eq!(
+            apply_fill(&mut balance, fill(Side::Sell), &config).0.price,
+            9_995.0
+        );
+    }
+
+    fn brl(amount: f64) -> HashMap<Currency, f64> {
+        HashMap::from([(Currency::BRL, amount)])
+    }
+
+    #[test]
+    fn simulating_a_profitable_opportunity_books_nothing() {
+        let (binance, mb) = profitable_books();
+        let balance = brl(50_000.0);
+        let snapshot = simulate(&binance, &mb, Symbol::BTCBRL, &balance, &Config::default());
+        let spread = snapshot.spread.expect("MB bids over Binance's ask");
+        assert_eq!(spread.buy_venue, Venue::Binance);
+        assert_eq!(spread.qty.0, 1.0);
+        assert_eq!(snapshot.book_qty, 1.0);
+        assert_eq!(snapshot.expected_balance[&Currency::BTC], 0.0);
+        assert!(snapshot.expected_balance[&Currency::BRL] > 50_000.0);
+    }
+
+    #[test]
+    fn simulating_crossed_out_books_finds_nothing() {
+        let (binance, _) = profitable_books();
+        let mb = OrderBook::builder()
+            .bid(9_950.0, 1.0)
+            .ask(10_050.0, 1.0)
+            .build();
+        let balance = brl(50_000.0);
+        let snapshot = simulate(&binance, &mb, Symbol::BTCBRL, &balance, &Config::default());
+        assert!(snapshot.spread.is_none());
+        assert_eq!(snapshot.expected_balance, balance);
+    }
+
+    #[test]
+    fn simulating_is_limited_by_the_balance() {
+        let (binance, mb) = profitable_books();
+        let snapshot = simulate(
+            &binance,
+            &mb,
+            Symbol::BTCBRL,
+            &brl(5_000.0),
+            &Config::default(),
+        );
+        let spread = snapshot.spread.expect("half a BTC is still profitable");
+        assert_eq!(snapshot.book_qty, 1.0);
+        // 5000 pays for a little under half a BTC once the fee is added
+        assert!(
+            spread.qty.0 < 0.5 && spread.qty.0 > 0.49,
+            "{}",
+            spread.qty.0
+        );
+        assert!(snapshot.expected_balance[&Currency::BRL] >= 0.0);
+    }
+
+    #[test]
+    fn the_short_mb_fixture_replays_to_its_trade() {
+        let input = DecisionInput::load("fixtures/replay/short_mb.json").unwrap();
+        match replay(&input) {
+            Decision::Trade(TradeAction::ShortMb { net_profit, .. }) => {
+                assert!((net_profit - 256.5).abs() < 1e-6, "{}", net_profit)
+            }
+            decision => panic!("expected a ShortMb trade, got {:?}", decision),
+        }
+    }
+
+    #[test]
+    fn evaluate_finds_the_trade_on_plain_inputs() {
+        let (binance, mb) = profitable_books();
+        let Some(TradeAction::ShortMb {
+            ask_price,
+            bid_price,
+            qty,
+            ..
+        }) = evaluate(&binance, &mb, &Config::default())
+        else {
+            panic!("MB bids over Binance's ask");
+        };
+        assert_eq!((ask_price, bid_price, qty), (10_000.0, 10_500.0, 1.0));
+
+        let config = Config {
+            min_profit: 1_000.0,
+            ..Config::default()
+        };
+        assert!(evaluate(&binance, &mb, &config).is_none());
+    }
+
+    #[test]
+    fn the_microprice_leans_towards_the_thinner_side() {
+        let book = OrderBook::builder()
+            .bid(9_900.0, 3.0)
+            .ask(10_000.0, 1.0)
+            .build();
+        assert_eq!(book.fair_value(FairValue::Mid), Some(9_950.0));
+        // 9_900 * 1 + 10_000 * 3 over 4
+        assert_eq!(book.fair_value(FairValue::Microprice), Some(9_975.0));
+
+        let balanced = OrderBook::builder()
+            .bid(9_900.0, 2.0)
+            .ask(10_000.0, 2.0)
+            .build();
+        assert_eq!(balanced.microprice(), balanced.mid_price());
+        // no size quoted on either side falls back to the mid
+        let empty_sizes = OrderBook::builder()
+            .bid(9_900.0, 0.0)
+            .ask(10_000.0, 0.0)
+            .build();
+        assert_eq!(empty_sizes.microprice(), Some(9_950.0));
+    }
+
+    #[test]
+    fn a_book_with_an_empty_side_has_no_fair_value() {
+        let no_asks = OrderBook::builder().bid(9_900.0, 1.0).build();
+        let no_bids = OrderBook::builder().ask(10_000.0, 1.0).build();
+        for book in [no_asks, no_bids] {
+            assert_eq!(book.fair_value(FairValue::Mid), None);
+            assert_eq!(book.fair_value(FairValue::Microprice), None);
+        }
+    }
+
+    #[test]
+    fn the_tagged_book_merges_the_venues_best_first() {
+        let binance = OrderBook::builder()
+            .bid(10_000.0, 1.0)
+            .bid(9_900.0, 2.0)
+            .ask(10_200.0, 1.0)
+            .build();
+        let mb = OrderBook::builder()
+            .bid(10_000.0, 0.5)
+            .bid(9_950.0, 0.5)
+            .ask(10_100.0, 0.5)
+            .ask(10_300.0, 0.5)
+            .build();
+        let book =
+            OrderBook::merge_tagged(&[(Venue::Binance, &binance), (Venue::MercadoBitcoin, &mb)]);
+        let levels = |side: &[(Venue, Data)]| -> Vec<(Venue, f64, f64)> {
+            side.iter()
+                .map(|(venue, level)| (*venue, level.price.0, level.qty.0))
+                .collect()
+        };
+        // equal prices keep the order the books were given in
+        assert_eq!(
+            levels(&book.bids),
+            [
+                (Venue::Binance, 10_000.0, 1.0),
+                (Venue::MercadoBitcoin, 10_000.0, 0.5),
+                (Venue::MercadoBitcoin, 9_950.0, 0.5),
+                (Venue::Binance, 9_900.0, 2.0),
+            ]
+        );
+        assert_eq!(
+            levels(&book.asks),
+            [
+                (Venue::MercadoBitcoin, 10_100.0, 0.5),
+                (Venue::Binance, 10_200.0, 1.0),
+                (Venue::MercadoBitcoin, 10_300.0, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn rounding_down_stays_below_the_next_step() {
+        assert_eq!(Currency::BTC.round_down(0.3), 0.3);
+        assert_eq!(Currency::BRL.round_down(0.29), 0.29);
+        assert_eq!(Currency::BTC.round_down(0.123456789999999), 0.12345678);
+        assert_eq!(Currency::BRL.round_down(10.00999999999), 10.0);
+        assert_eq!(Currency::BTC.round_down(1.999999999), 1.99999999);
+    }
+}
+
+#[cfg(all(test, feature = "io"))]
+mod bot_tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::{tests::profitable_books, *};
+    use config::PegBand;
+    use conversion::ConversionTarget;
+    use executor::{OrderState, OrderStatus};
+
+    fn bot(config: Config) -> Bot {
+        Bot::new(
+            config,
+            Binance::default(),
+            MercadoBitcoin::default(),
+            CoinGecko::default(),
+        )
+    }
+
+    fn scanning_config() -> Config {
+        Config {
+            scan_symbols: vec![Symbol::ETHBRL],
+            execute_scan_opportunities: true,
+            allow_short: true,
+            fixed_clock_ms: Some(100_000),
+            ..Config::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn requests_reuse_the_pooled_connection() {
+        for (max_idle, expected_connections) in [(None, 1), (Some(0), 2)] {
+            let (base_url, connections) =
+                crate::exchange::tests::respond_keeping_alive(r#"{"serverTime": 5}"#).await;
+            let config = Config {
+                http_pool_max_idle_per_host: max_idle,
+                http_pool_idle_timeout_ms: Some(60_000),
+                ..Config::default()
+            };
+            let client = http_client(&config).unwrap().unwrap();
+            let binance = Binance::with_client(client).with_base_url(Some(base_url));
+            for _ in 0..2 {
+                assert_eq!(binance.server_time().await.unwrap(), 5);
+            }
+            assert_eq!(
+                connections.load(Ordering::Relaxed),
+                expected_connections,
+                "max idle {:?}",
+                max_idle
+            );
+        }
+    }
+
+    #[tokio::test]
+    async fn a_high_used_weight_stretches_the_next_poll() {
+        let (base_url, _) = crate::exchange::tests::respond_with(
+            "200 OK",
+            "x-mbx-used-weight-1m: 5400\r\n",
+            r#"{"serverTime": 5}"#,
+        )
+        .await;
+        // throttling starts at 80% of the 6000 allowance
+        let mut bot = bot(Config::default());
+        assert_eq!(
+            bot.throttled(Duration::from_secs(4)),
+            Duration::from_secs(4)
+        );
+        bot.binance = Binance::default().with_base_url(Some(base_url));
+        bot.binance.server_time().await.unwrap();
+        assert_eq!(bot.binance.used_weight(), 5_400);
+        assert_eq!(
+            bot.throttled(Duration::from_secs(4)),
+            Duration::from_millis(4_500)
+        );
+    }
+
+    #[tokio::test]
+    async fn scanned_symbols_are_held_to_the_staleness_gate() {
+        let mut bot = bot(Config {
+            max_book_age_ms: Some(1_000),
+            ..scanning_config()
+        });
+        let (binance, mut mb) = profitable_books();
+        mb.updated_at_ms = Some(90_000);
+        let decision = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap();
+        assert!(decision.is_none(), "stale book traded: {:?}", decision);
+
+        mb.updated_at_ms = Some(99_900);
+        let decision = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap();
+        assert!(
+            matches!(decision, Some(Decision::Trade(_))),
+            "{:?}",
+            decision
+        );
+    }
+
+    #[tokio::test]
+    async fn books_go_stale_as_the_clock_advances() {
+        let mut bot = bot(Config {
+            max_book_age_ms: Some(1_000),
+            ..scanning_config()
+        });
+        let clock = Arc::new(MockClock::new(100_000));
+        bot.clock = clock.clone();
+        let (mut binance, mut mb) = profitable_books();
+        binance.updated_at_ms = Some(99_900);
+        mb.updated_at_ms = Some(99_900);
+        let decision = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap();
+        assert!(
+            matches!(decision, Some(Decision::Trade(_))),
+            "{:?}",
+            decision
+        );
+
+        clock.advance(2_000);
+        let decision = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap();
+        assert!(decision.is_none(), "stale book traded: {:?}", decision);
+    }
+
+    #[tokio::test]
+    async fn the_estimated_skew_moves_the_staleness_boundary() {
+        let mut bot = bot(Config {
+            max_book_age_ms: Some(1_000),
+            ..scanning_config()
+        });
+        bot.clock = Arc::new(MockClock::new(100_000));
+        let (mut binance, mut mb) = profitable_books();
+        binance.updated_at_ms = Some(99_900);
+        mb.updated_at_ms = Some(99_900);
+        let decision = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap();
+        assert!(
+            matches!(decision, Some(Decision::Trade(_))),
+            "{:?}",
+            decision
+        );
+
+        // Binance's clock runs 5s ahead, so the same book is 5.1s old in server time
+        let server_time = format!(r#"{{"serverTime": {}}}"#, clock::unix_millis() + 5_000);
+        let (base_url, _) =
+            exchange::tests::respond_with("200 OK", "", Box::leak(server_time.into_boxed_str()))
+                .await;
+        bot.binance = Binance::default().with_base_url(Some(base_url));
+        bot.clock_skew
+            .refresh_if_due(&bot.binance, bot.clock.as_ref())
+            .await
+            .unwrap();
+        assert!((4_900..=5_000).contains(&bot.clock_skew.offset_ms()));
+        let decision = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap();
+        assert!(decision.is_none(), "stale book traded: {:?}", decision);
+    }
+
+    #[test]
+    fn holding_cost_accrues_on_the_time_that_passed() {
+        let mut bot = bot(Config {
+            holding_cost_rate: 0.1,
+            poll_interval_ms: 1_000,
+            ..scanning_config()
+        });
+        let clock = Arc::new(MockClock::new(100_000));
+        bot.clock = clock.clone();
+        bot.marks.observe(Currency::BTC, Currency::BRL, 100_000.0);
+        bot.state.write().balance.insert(Currency::BTC, 1.0);
+        let state = bot.shared_state();
+        let brl = || state.read().balance[&Currency::BRL];
+        let before = brl();
+
+        // the first charge only starts the clock
+        bot.charge_holding_cost();
+        assert_eq!(brl(), before);
+
+        // a cycle that took a minute pays for the minute, not for one poll interval
+        clock.advance(60_000);
+        bot.charge_holding_cost();
+        let minute = 0.1 * 100_000.0 * 60_000.0 / (365.0 * 24.0 * 60.0 * 60.0 * 1000.0);
+        assert!((before - brl() - minute).abs() < 1e-9, "{}", before - brl());
+        assert!((state.read().stats.holding_cost - minute).abs() < 1e-9);
+    }
+
+    fn converting_config() -> Config {
+        Config {
+            conversion_targets: HashMap::from([(
+                Currency::BTC,
+                ConversionTarget {
+                    target: 0.0,
+                    tolerance: 0.01,
+                },
+            )]),
+            fixed_clock_ms: Some(100_000),
+            ..Config::default()
+        }
+    }
+
+    fn hold_on_venues(bot: &Bot, btc: &[(Venue, f64)], brl: f64) {
+        let mut state = bot.state.write();
+        for (venue, qty) in btc {
+            state
+                .venue_balances
+                .entry(*venue)
+                .or_default()
+                .insert(Currency::BTC, *qty);
+        }
+        state
+            .balance
+            .insert(Currency::BTC, btc.iter().map(|(_, qty)| qty).sum());
+        state.balance.insert(Currency::BRL, brl);
+    }
+
+    fn venue_btc(bot: &Bot, venue: Venue) -> f64 {
+        bot.state.read().venue_balances[&venue][&Currency::BTC]
+    }
+
+    #[tokio::test]
+    async fn stranded_inventory_is_converted_on_each_venue() {
+        let bot = bot(converting_config());
+        // the combined 0.1 BTC is within reach of the target, each venue's holding isn't
+        hold_on_venues(
+            &bot,
+            &[(Venue::Binance, 0.5), (Venue::MercadoBitcoin, -0.4)],
+            100_000.0,
+        );
+        let (binance, mb) = profitable_books();
+        bot.convert_stranded(&binance, &mb).await.unwrap();
+
+        assert!(venue_btc(&bot, Venue::Binance).abs() < 1e-9);
+        assert!(venue_btc(&bot, Venue::MercadoBitcoin).abs() < 1e-9);
+        let state = bot.state.read();
+        assert_eq!(state.stats.conversions, 2);
+        let expected_fees = 0.5 * 9_900.0 * 0.001 + 0.4 * 10_600.0 * 0.007;
+        assert!(
+            (state.stats.conversion_fees - expected_fees).abs() < 1e-9,
+            "{}",
+            state.stats.conversion_fees
+        );
+    }
+
+    #[tokio::test]
+    async fn a_conversion_whose_fee_is_unaffordable_is_skipped() {
+        let bot = bot(converting_config());
+        // enough for the notional of buying back 0.4 BTC on MB, not for its fee
+        hold_on_venues(&bot, &[(Venue::MercadoBitcoin, -0.4)], 0.4 * 10_600.0);
+        let (binance, mb) = profitable_books();
+        bot.convert_stranded(&binance, &mb).await.unwrap();
+
+        assert_eq!(venue_btc(&bot, Venue::MercadoBitcoin), -0.4);
+        assert_eq!(bot.state.read().stats.conversions, 0);
+    }
+
+    #[tokio::test]
+    async fn the_scan_set_follows_the_listings() {
+        let mut bot = bot(Config {
+            scan_symbols: vec![Symbol::ETHBRL],
+            scan_listed_symbols: true,
+            enabled_symbols: HashMap::from([(Symbol::USDTBRL, false)]),
+            ..scanning_config()
+        });
+        bot.listed = Some((100_000, vec![Symbol::BTCBRL, Symbol::ETHBRL]));
+        assert_eq!(bot.scan_set().await, vec![Symbol::ETHBRL]);
+
+        bot.listed = Some((100_000, vec![Symbol::ETHBRL, Symbol::USDTBRL]));
+        assert_eq!(bot.scan_set().await, vec![Symbol::ETHBRL]);
+
+        bot.config.enabled_symbols.clear();
+        assert_eq!(bot.scan_set().await, vec![Symbol::ETHBRL, Symbol::USDTBRL]);
+    }
+
+    #[tokio::test]
+    async fn a_failed_listing_refresh_keeps_the_previous_scan_set() {
+        let unreachable = || Some("http://127.0.0.1:9".to_string());
+        let mut bot = Bot::new(
+            Config {
+                scan_listed_symbols: true,
+                symbol_cache_ttl_ms: 1_000,
+                ..scanning_config()
+            },
+            Binance::default().with_base_url(unreachable()),
+            MercadoBitcoin::default().with_base_url(unreachable()),
+            CoinGecko::default(),
+        );
+        // fetched long enough ago to be refreshed
+        bot.listed = Some((0, vec![Symbol::USDTBRL]));
+        assert_eq!(bot.scan_set().await, vec![Symbol::ETHBRL, Symbol::USDTBRL]);
+    }
+
+    #[tokio::test]
+    async fn a_book_quoting_zero_skips_the_cycle() {
+        let mut bot = bot(scanning_config());
+        let binance = OrderBook::builder().bid(9_900.0, 1.0).ask(0.0, 1.0).build();
+        let (_, mb) = profitable_books();
+        let decision = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap();
+        assert!(decision.is_none(), "{:?}", decision);
+
+        // a replayed decision doesn't go through the gate but mustn't trade it either
+        let balance = bot.state.balance();
+        let config = Config {
+            symbol: Symbol::ETHBRL,
+            ..scanning_config()
+        };
+        let decision = decide(&binance, &mb, &balance, &config, 0.0, None);
+        assert!(
+            matches!(decision, Decision::NoOpportunity),
+            "{:?}",
+            decision
+        );
+    }
+
+    #[tokio::test]
+    async fn scanned_symbols_are_held_to_the_depeg_guard() {
+        let mut bot = bot(Config {
+            usdt_peg_band: Some(PegBand { min: 4.5, max: 6.5 }),
+            scan_symbols: vec![Symbol::USDTBRL],
+            ..scanning_config()
+        });
+        let binance = OrderBook::builder()
+            .bid(7.0, 1_000.0)
+            .ask(7.1, 1_000.0)
+            .build();
+        let mb = OrderBook::builder()
+            .bid(7.5, 1_000.0)
+            .ask(7.6, 1_000.0)
+            .build();
+        let decision = bot
+            .evaluate(Symbol::USDTBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap();
+        assert!(decision.is_none(), "depegged USDT traded: {:?}", decision);
+    }
+
+    #[tokio::test]
+    async fn scanned_trades_count_against_the_position_limit() {
+        let mut bot = bot(Config {
+            max_open_positions: Some(1),
+            ..scanning_config()
+        });
+        let (binance, mb) = profitable_books();
+        let decision = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap();
+        assert!(
+            matches!(decision, Some(Decision::Trade(_))),
+            "{:?}",
+            decision
+        );
+        // a second one accepted in the same cycle finds the only slot taken
+        let decision = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 1)
+            .await
+            .unwrap();
+        assert!(
+            matches!(decision, Some(Decision::PositionLimit { open: 1 })),
+            "{:?}",
+            decision
+        );
+    }
+
+    #[tokio::test]
+    async fn booked_scanned_trades_update_positions_stats_and_accounts() {
+        let mut bot = bot(scanning_config());
+        let (binance, mb) = profitable_books();
+        let Some(Decision::Trade(action)) = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap()
+        else {
+            panic!("expected a trade");
+        };
+        let (trade_result, balance_before, reservation) = bot
+            .execute_scanned(action, &binance, &mb)
+            .await
+            .unwrap()
+            .expect("funds were available");
+        bot.book_trade(action, trade_result, &balance_before, &binance, &mb);
+        drop(reservation);
+
+        assert_eq!(bot.positions.len(), 1);
+        let state = bot.state.read();
+        assert_eq!(state.stats.trades, 1);
+        assert!(state
+            .stats
+            .attribution
+            .contains_key(&(Symbol::ETHBRL, "ShortMb")));
+        assert!(state.reserved.values().all(|amount| *amount == 0.0));
+        assert!(state.balance[&Currency::BRL] > balance_before[&Currency::BRL]);
+    }
+
+    // a simulated executor whose placements start failing once `places` have gone out
+    struct FailingExecutor<'a> {
+        inner: SimulatedExecutor<'a>,
+        places: AtomicUsize,
+    }
+
+    impl OrderExecutor for FailingExecutor<'_> {
+        async fn place(&self, order: &Order) -> Result<u64, Error> {
+            let left = self.places.load(Ordering::Relaxed);
+            if left == 0 {
+                return Err(reqwest::get("not a url").await.unwrap_err());
+            }
+            self.places.store(left - 1, Ordering::Relaxed);
+            self.inner.place(order).await
+        }
+
+        async fn order_status(&self, symbol: Symbol, order_id: u64) -> Result<OrderState, Error> {
+            self.inner.order_status(symbol, order_id).await
+        }
+
+        async fn cancel(&self, symbol: Symbol, order_id: u64) -> Result<OrderState, Error> {
+            self.inner.cancel(symbol, order_id).await
+        }
+
+        fn clock(&self) -> &dyn Clock {
+            self.inner.clock()
+        }
+    }
+
+    async fn trade_action(bot: &mut Bot, binance: &OrderBook, mb: &OrderBook) -> TradeAction {
+        match bot
+            .evaluate(Symbol::ETHBRL, binance, mb, None, Some(0.0), 0)
+            .await
+            .unwrap()
+        {
+            Some(Decision::Trade(action)) => action,
+            decision => panic!("expected a trade, got {:?}", decision),
+        }
+    }
+
+    #[tokio::test]
+    async fn a_failed_slice_keeps_the_slices_that_filled() {
+        let config = Config {
+            ladder_slices: 2,
+            ..scanning_config()
+        };
+        let mut bot = bot(config.clone());
+        let (binance, mb) = profitable_books();
+        let action = trade_action(&mut bot, &binance, &mb).await;
+        let executor = FailingExecutor {
+            inner: SimulatedExecutor::new(&binance, &mb),
+            places: 2.into(),
+        };
+
+        let trade_result = take_trade_action(
+            action,
+            bot.state.balance(),
+            &config,
+            &executor,
+            &binance,
+            &mb,
+            &CancellationToken::new(),
+        )
+        .await
+        .expect("the first slice filled, so the trade is booked");
+        assert_eq!(trade_result.fills.len(), 2);
+        assert_eq!(trade_result.filled_qty, trade_result.attempted_qty);
+        assert!(trade_result.filled_qty > 0.0);
+        assert_eq!(trade_result.residual_exposure, 0.0);
+        assert!(trade_result.realized_profit > 0.0);
+    }
+
+    #[tokio::test]
+    async fn a_failed_first_slice_is_an_error() {
+        let config = Config {
+            ladder_slices: 2,
+            ..scanning_config()
+        };
+        let mut bot = bot(config.clone());
+        let (binance, mb) = profitable_books();
+        let action = trade_action(&mut bot, &binance, &mb).await;
+        let executor = FailingExecutor {
+            inner: SimulatedExecutor::new(&binance, &mb),
+            places: 0.into(),
+        };
+
+        let trade_result = take_trade_action(
+            action,
+            bot.state.balance(),
+            &config,
+            &executor,
+            &binance,
+            &mb,
+            &CancellationToken::new(),
+        )
+        .await;
+        assert!(trade_result.is_err());
+    }
+
+    // serves the given books in turn, one per fetch
+    struct BookSequence(std::sync::Mutex<Vec<OrderBook>>);
+
+    impl Exchange for BookSequence {
+        const VENUE: Venue = Venue::Binance;
+
+        async fn fetch_order_book(&self, _symbol: Symbol) -> Result<OrderBook, Error> {
+            Ok(self.0.lock().unwrap().remove(0))
+        }
+
+        async fn balances(&self) -> Result<Option<HashMap<Currency, f64>>, Error> {
+            Ok(None)
+        }
+    }
+
+    #[tokio::test]
+    async fn a_book_failing_its_checksum_is_fetched_again() {
+        let bot = bot(Config {
+            validate_book_checksums: true,
+            book_retry_delay_ms: 0,
+            ..Config::default()
+        });
+        let (fresh, _) = profitable_books();
+        let corrupted = OrderBook {
+            checksum: Some(incremental::checksum(&fresh)),
+            ..OrderBook::builder()
+                .bid(9_800.0, 1.0)
+                .ask(10_000.0, 1.0)
+                .build()
+        };
+        let fresh = OrderBook {
+            checksum: Some(incremental::checksum(&fresh)),
+            ..fresh
+        };
+        let exchange = BookSequence(std::sync::Mutex::new(vec![corrupted, fresh]));
+
+        let order_book = bot
+            .fetch_full_book(&exchange, Symbol::BTCBRL)
+            .await
+            .unwrap()
+            .expect("the resynced book matches");
+        assert_eq!(order_book.bids[0].price.0, 9_900.0);
+        assert!(exchange.0.lock().unwrap().is_empty());
+    }
+
+    #[tokio::test]
+    async fn an_ioc_trade_against_a_thinner_book_fills_partly() {
+        let config = scanning_config();
+        let mut bot = bot(config.clone());
+        let (binance, mb) = profitable_books();
+        let action = trade_action(&mut bot, &binance, &mb).await;
+        // by execution time only 0.4 is left at the sell price
+        let thinner_mb = OrderBook::builder()
+            .bid(10_500.0, 0.4)
+            .ask(10_600.0, 1.0)
+            .build();
+        let executor = SimulatedExecutor::new(&binance, &thinner_mb);
+
+        let trade_result = take_trade_action(
+            action,
+            bot.state.balance(),
+            &config,
+            &executor,
+            &binance,
+            &thinner_mb,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        let mut stats = Stats::default();
+        stats.record(&trade_result);
+        assert_eq!(stats.partial_fills, 1);
+        assert_eq!(stats.fill_ratio(), Some(1.4 / 2.0));
+    }
+
+    #[tokio::test]
+    async fn a_halted_trade_sends_no_further_slices() {
+        let config = Config {
+            ladder_slices: 2,
+            ..scanning_config()
+        };
+        let mut bot = bot(config.clone());
+        let (binance, mb) = profitable_books();
+        let Some(Decision::Trade(action)) = bot
+            .evaluate(Symbol::ETHBRL, &binance, &mb, None, Some(0.0), 0)
+            .await
+            .unwrap()
+        else {
+            panic!("expected a trade");
+        };
+        let balance = bot.state.balance();
+        let executor = SimulatedExecutor::new(&binance, &mb);
+        let halt = CancellationToken::new();
+        halt.cancel();
+
+        let trade_result = take_trade_action(
+            action,
+            balance.clone(),
+            &config,
+            &executor,
+            &binance,
+            &mb,
+            &halt,
+        )
+        .await
+        .unwrap();
+        assert!(trade_result.fills.is_empty());
+        assert_eq!(trade_result.attempted_qty, 0.0);
+        assert_eq!(trade_result.new_balance, balance);
+    }
+
+    #[tokio::test]
+    async fn a_crossing_post_only_leg_abandons_the_trade() {
+        let config = scanning_config();
+        let mut bot = bot(config.clone());
+        let (binance, mb) = profitable_books();
+        // the Binance buy at the ask would take liquidity
+        let action = trade_action(&mut bot, &binance, &mb)
+            .await
+            .with_post_only(Some(Venue::Binance));
+        let balance = bot.state.balance();
+        let executor = SimulatedExecutor::new(&binance, &mb);
+
+        let trade_result = take_trade_action(
+            action,
+            balance.clone(),
+            &config,
+            &executor,
+            &binance,
+            &mb,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        assert!(trade_result.fills.is_empty());
+        // only the post-only leg went out, the MB leg was never sent
+        assert_eq!(trade_result.attempted_qty, 1.0);
+        assert_eq!(trade_result.new_balance, balance);
+        let state = executor.order_status(Symbol::BTCBRL, 0).await.unwrap();
+        assert_eq!(state.status, OrderStatus::Rejected);
+    }
+
+    #[tokio::test]
+    async fn a_trade_result_details_what_was_executed() {
+        let config = scanning_config();
+        let mut bot = bot(config.clone());
+        let (binance, mb) = profitable_books();
+        let action = trade_action(&mut bot, &binance, &mb).await;
+        let balance = bot.state.balance();
+        let executor = SimulatedExecutor::new(&binance, &mb);
+
+        let trade_result = take_trade_action(
+            action,
+            balance.clone(),
+            &config,
+            &executor,
+            &binance,
+            &mb,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        assert_eq!(trade_result.direction, "ShortMb");
+        assert!(matches!(
+            trade_result.executed_legs[..],
+            [
+                Leg {
+                    venue: Venue::MercadoBitcoin,
+                    side: Side::Sell,
+                },
+                Leg {
+                    venue: Venue::Binance,
+                    side: Side::Buy,
+                },
+            ]
+        ));
+        assert_eq!(trade_result.fills.len(), 2);
+        // 500 of spread less 10 of Binance fees and 73.5 of MB's
+        assert!((trade_result.realized_profit - 416.5).abs() < 1e-6);
+        assert!((trade_result.fees - 83.5).abs() < 1e-6);
+        let brl = |balance: &HashMap<Currency, f64>| balance[&Currency::BRL];
+        assert!(
+            (brl(&trade_result.new_balance) - brl(&balance) - trade_result.realized_profit).abs()
+                < 1e-6
+        );
+    }
+
+    #[tokio::test]
+    async fn the_balance_moves_by_the_ioc_filled_portion_only() {
+        let config = Config {
+            // left open so that only the legs' own fills are booked
+            leg_imbalance_tolerance: 1.0,
+            ..scanning_config()
+        };
+        let mut bot = bot(config.clone());
+        let (binance, mb) = profitable_books();
+        let action = trade_action(&mut bot, &binance, &mb).await;
+        let thinner_mb = OrderBook::builder()
+            .bid(10_500.0, 0.4)
+            .ask(10_600.0, 1.0)
+            .build();
+        let balance = bot.state.balance();
+        let executor = SimulatedExecutor::new(&binance, &thinner_mb);
+
+        let trade_result = take_trade_action(
+            action,
+            balance.clone(),
+            &config,
+            &executor,
+            &binance,
+            &thinner_mb,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        let eth =
+            |balance: &HashMap<Currency, f64>| balance.get(&Currency::ETH).copied().unwrap_or(0.0);
+        assert!((eth(&trade_result.new_balance) - eth(&balance) - 0.6).abs() < 1e-9);
+        assert!((trade_result.residual_exposure - 0.6).abs() < 1e-9);
+    }
+
+    #[tokio::test]
+    async fn mismatched_leg_fills_are_corrected() {
+        let config = Config {
+            route_corrective_orders: true,
+            ..scanning_config()
+        };
+        let mut bot = bot(config.clone());
+        let (binance, mb) = profitable_books();
+        let action = trade_action(&mut bot, &binance, &mb).await;
+        // the buy leg only finds 0.4, leaving 0.6 sold short
+        let thinner_binance = OrderBook::builder()
+            .bid(9_900.0, 1.0)
+            .ask(10_000.0, 0.4)
+            .build();
+        let executor = SimulatedExecutor::new(&thinner_binance, &mb);
+
+        let trade_result = take_trade_action(
+            action,
+            bot.state.balance(),
+            &config,
+            &executor,
+            &thinner_binance,
+            &mb,
+            &CancellationToken::new(),
+        )
+        .await
+        .unwrap();
+        assert!(trade_result.fills.len() > 2, "{:?}", trade_result.fills);
+        assert!(trade_result.residual_exposure.abs() < 1e-8);
+        let mut stats = Stats::default();
+        stats.record(&trade_result);
+        assert!(stats.residual_exposure.abs() < 1e-8);
+    }
+
+    #[tokio::test]
+    async fn a_failed_concurrent_leg_books_neither() {
+        let config = Config {
+            concurrent_legs: true,
+            ..scanning_config()
+        };
+        let mut bot = bot(config.clone());
+        let (binance, mb) = profitable_books();
+        let action = trade_action(&mut bot, &binance, &mb).await;
+        let executor = FailingExecutor {
+            inner: SimulatedExecutor::new(&binance, &mb),
+            places: 1.into(),
+        };
+
+        let trade_result = take_trade_action(
+            action,
+            bot.state.balance(),
+            &config,
+            &executor,
+            &binance,
+            &mb,
+            &CancellationToken::new(),
+        )
+        .await;
+        assert!(trade_result.is_err(), "the filled leg is not booked alone");
+    }
+}
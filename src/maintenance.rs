@@ -0,0 +1,39 @@
+use reqwest::{Error, StatusCode};
+
+use crate::output::log;
+
+// whether a venue is under maintenance, so the loop backs off until a cycle goes through again
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    since_ms: Option<u64>,
+}
+
+impl Maintenance {
+    pub fn is_active(&self) -> bool {
+        self.since_ms.is_some()
+    }
+
+    // logs entering and leaving maintenance
+    pub fn observe(&mut self, in_maintenance: bool, now_ms: u64, reason: &str) {
+        match (self.since_ms, in_maintenance) {
+            (None, true) => {
+                log!("entering maintenance: {}", reason);
+                self.since_ms = Some(now_ms);
+            }
+            (Some(since_ms), false) => {
+                log!(
+                    "leaving maintenance after {} ms: {}",
+                    now_ms.saturating_sub(since_ms),
+                    reason
+                );
+                self.since_ms = None;
+            }
+            _ => {}
+        }
+    }
+}
+
+// MB answers every endpoint with a 503 while it's down for maintenance
+pub fn is_maintenance_error(error: &Error) -> bool {
+    error.status() == Some(StatusCode::SERVICE_UNAVAILABLE)
+}
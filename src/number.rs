@@ -0,0 +1,79 @@
+// a number as a feed might format it: "1234.56", "1.2e4" or "1,234.56", or "1.234,56" with a
+// decimal comma; misplaced group separators and non-finite values are rejected. Binance and
+// MB always send dot decimals, only imported files may use a decimal comma
+pub fn parse_number(text: &str, decimal_comma: bool) -> Result<f64, String> {
+    let (group, decimal) = if decimal_comma {
+        ('.', ',')
+    } else {
+        (',', '.')
+    };
+    let invalid = || format!("not a number: {:?}", text);
+    let trimmed = text.trim();
+
+    let (mantissa, exponent) = trimmed.split_at(trimmed.find(['e', 'E']).unwrap_or(trimmed.len()));
+    let (integer, fraction) = match mantissa.split_once(decimal) {
+        Some((integer, fraction)) => (integer, Some(fraction)),
+        None => (mantissa, None),
+    };
+    let (sign, digits) = match integer.strip_prefix(['-', '+']) {
+        Some(digits) => (&integer[..1], digits),
+        None => ("", integer),
+    };
+    if digits.contains(group) {
+        let mut groups = digits.split(group);
+        let leading = groups.next().unwrap_or_default();
+        if leading.is_empty() || leading.len() > 3 || groups.any(|group| group.len() != 3) {
+            return Err(invalid());
+        }
+    }
+
+    let mut normalized = format!("{}{}", sign, digits.replace(group, ""));
+    if let Some(fraction) = fraction {
+        normalized.push('.');
+        normalized.push_str(fraction);
+    }
+    normalized.push_str(exponent);
+    match normalized.parse::<f64>() {
+        Ok(value) if value.is_finite() => Ok(value),
+        _ => Err(invalid()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_scientific_notation() {
+        assert_eq!(parse_number("1.2e4", false), Ok(12_000.0));
+        assert_eq!(parse_number("-3E-2", false), Ok(-0.03));
+    }
+
+    #[test]
+    fn parses_dot_decimals_as_the_venues_send_them() {
+        assert_eq!(parse_number("350123.45000000", false), Ok(350_123.45));
+        assert_eq!(parse_number("0.00100000", false), Ok(0.001));
+    }
+
+    #[test]
+    fn strips_thousands_separators() {
+        assert_eq!(parse_number("1,234.56", false), Ok(1_234.56));
+        assert_eq!(parse_number("12,345,678", false), Ok(12_345_678.0));
+    }
+
+    #[test]
+    fn parses_decimal_commas_only_when_asked_to() {
+        assert_eq!(parse_number("1.234,56", true), Ok(1_234.56));
+        assert_eq!(parse_number("0,5", true), Ok(0.5));
+        assert!(parse_number("1.234,56", false).is_err());
+        // a dot-decimal venue number can't be read as grouped
+        assert!(parse_number("350123.45000000", true).is_err());
+    }
+
+    #[test]
+    fn rejects_garbage() {
+        for text in ["", "abc", "1,23.4", ",123", "1.2.3", "inf", "NaN", "1e999"] {
+            assert!(parse_number(text, false).is_err(), "{:?} parsed", text);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+use std::{
+    fmt,
+    sync::atomic::{AtomicBool, Ordering},
+};
+
+use serde::Serialize;
+use serde_json::json;
+
+// set once from --json before anything is printed
+static JSON: AtomicBool = AtomicBool::new(false);
+
+pub fn set_json(json: bool) {
+    JSON.store(json, Ordering::Relaxed);
+}
+
+fn is_json() -> bool {
+    JSON.load(Ordering::Relaxed)
+}
+
+fn json_line(event: &str, data: impl Serialize) -> String {
+    json!({ "event": event, "data": data }).to_string()
+}
+
+// one line of output: `{"event": ..., "data": ...}` in --json mode, the human text otherwise
+pub fn emit(event: &str, data: impl Serialize, human: fmt::Arguments) {
+    if is_json() {
+        println!("{}", json_line(event, data));
+    } else {
+        println!("{}", human);
+    }
+}
+
+// like emit, but to stderr and tagged as an error
+pub fn error(event: &str, data: impl Serialize, human: fmt::Arguments) {
+    if is_json() {
+        eprintln!(
+            "{}",
+            json!({ "event": event, "level": "error", "data": data })
+        );
+    } else {
+        eprintln!("{}", human);
+    }
+}
+
+// free-form progress lines, `{"event": "message", "data": "<text>"}` in --json mode
+pub fn message(text: String) {
+    if is_json() {
+        println!("{}", json_line("message", text));
+    } else {
+        println!("{}", text);
+    }
+}
+
+macro_rules! log {
+    ($($arg:tt)*) => {
+        $crate::output::message(format!($($arg)*))
+    };
+}
+
+pub(crate) use log;
+
+#[cfg(test)]
+mod tests {
+    use serde_json::Value;
+
+    use super::*;
+    use crate::{check_arbitrage, config::Config, Decision, OrderBook, Symbol};
+
+    #[test]
+    fn a_trade_is_one_parseable_json_line() {
+        let binance = OrderBook::builder()
+            .bid(9_900.0, 1.0)
+            .ask(10_000.0, 1.0)
+            .build();
+        let mb = OrderBook::builder()
+            .bid(10_500.0, 1.0)
+            .ask(10_600.0, 1.0)
+            .build();
+        let action =
+            check_arbitrage(&binance, &mb, Symbol::BTCBRL, &Config::default(), 0.0).remove(0);
+
+        let line = json_line("decision", Decision::Trade(action));
+        assert!(!line.contains('\n'));
+        let parsed: Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(parsed["event"], "decision");
+        let trade = &parsed["data"]["Trade"]["ShortMb"];
+        assert_eq!(trade["ask_price"], 10_000.0);
+        assert_eq!(trade["bid_price"], 10_500.0);
+        assert_eq!(trade["symbol"], "BTCBRL");
+    }
+}
@@ -0,0 +1,54 @@
+use std::{
+    collections::HashMap,
+    fs::OpenOptions,
+    io::{self, Write},
+};
+
+use crate::{valuation::Marks, Currency};
+
+// appends one CSV row per cycle with the value of all holdings and the change since the run
+// started, for plotting
+pub struct PnlExport {
+    path: String,
+    start_value: Option<f64>,
+}
+
+impl PnlExport {
+    pub fn new(path: String) -> Self {
+        Self {
+            path,
+            start_value: None,
+        }
+    }
+
+    // holdings without a known rate are left out, as in the reported total
+    pub fn append(
+        &mut self,
+        now_ms: u64,
+        balance: &HashMap<Currency, f64>,
+        marks: &Marks,
+        base_currency: Currency,
+    ) -> io::Result<()> {
+        let value: f64 = balance
+            .iter()
+            .filter_map(|(currency, amount)| marks.convert(*amount, *currency, base_currency))
+            .sum();
+        let start_value = *self.start_value.get_or_insert(value);
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(file, "timestamp_ms,value,pnl,currency")?;
+        }
+        writeln!(
+            file,
+            "{},{},{},{:?}",
+            now_ms,
+            value,
+            value - start_value,
+            base_currency
+        )
+    }
+}
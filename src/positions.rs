@@ -0,0 +1,52 @@
+use std::sync::Arc;
+
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+// executed arbitrage positions whose rebalancing hasn't settled yet; each holds a permit so
+// that no more than the limit are open at once
+pub struct OpenPositions {
+    semaphore: Arc<Semaphore>,
+    // opened at, oldest first
+    open: Vec<(u64, OwnedSemaphorePermit)>,
+    settle_ms: u64,
+}
+
+impl OpenPositions {
+    pub fn new(limit: Option<usize>, settle_ms: u64) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(limit.unwrap_or(Semaphore::MAX_PERMITS))),
+            open: Vec::new(),
+            settle_ms,
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.open.len()
+    }
+
+    // positions that can still be opened before the limit
+    pub fn available(&self) -> usize {
+        self.semaphore.available_permits()
+    }
+
+    // false when the limit is reached
+    pub fn open(&mut self, now_ms: u64) -> bool {
+        match Arc::clone(&self.semaphore).try_acquire_owned() {
+            Ok(permit) => {
+                self.open.push((now_ms, permit));
+                true
+            }
+            Err(_) => false,
+        }
+    }
+
+    // releases the positions open for at least settle_ms
+    pub fn settle_elapsed(&mut self, now_ms: u64) {
+        self.open
+            .retain(|(opened_at_ms, _)| now_ms.saturating_sub(*opened_at_ms) < self.settle_ms);
+    }
+
+    pub fn settle_all(&mut self) {
+        self.open.clear();
+    }
+}
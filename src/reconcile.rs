@@ -0,0 +1,177 @@
+use std::collections::HashMap;
+
+use reqwest::Error;
+
+use serde::Serialize;
+
+use crate::{
+    balance::{amounts_equal, balances_equal},
+    config::Config,
+    exchange::Exchange,
+    output::{self, log},
+    Currency, Venue,
+};
+
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct Discrepancy {
+    pub currency: Currency,
+    pub internal: f64,
+    pub reported: f64,
+}
+
+pub fn find_discrepancies(
+    internal: &HashMap<Currency, f64>,
+    reported: &HashMap<Currency, f64>,
+    tolerance: impl Fn(Currency) -> f64,
+) -> Vec<Discrepancy> {
+    let mut currencies: Vec<Currency> = internal.keys().chain(reported.keys()).copied().collect();
+    currencies.sort();
+    currencies.dedup();
+
+    currencies
+        .into_iter()
+        .filter_map(|currency| {
+            let internal = internal.get(&currency).copied().unwrap_or(0.0);
+            let reported = reported.get(&currency).copied().unwrap_or(0.0);
+            if !amounts_equal(internal, reported, tolerance(currency)) {
+                Some(Discrepancy {
+                    currency,
+                    internal,
+                    reported,
+                })
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+// the internal balance covers both venues together, so it is only compared, and snapped,
+// when both report theirs; a venue that can't is skipped rather than taken to hold nothing
+pub async fn reconcile_balances<B: Exchange, M: Exchange>(
+    binance: &B,
+    mb: &M,
+    balance: HashMap<Currency, f64>,
+    config: &Config,
+) -> Result<HashMap<Currency, f64>, Error> {
+    let (mut reported, mb_reported) = match (binance.balances().await?, mb.balances().await?) {
+        (Some(binance), Some(mb)) => (binance, mb),
+        (binance, mb) => {
+            let unavailable: Vec<Venue> = [(B::VENUE, binance.is_none()), (M::VENUE, mb.is_none())]
+                .into_iter()
+                .filter_map(|(venue, missing)| missing.then_some(venue))
+                .collect();
+            log!(
+                "skipping reconciliation, no balances from {:?}",
+                unavailable
+            );
+            return Ok(balance);
+        }
+    };
+    for (currency, amount) in mb_reported {
+        *reported.entry(currency).or_insert(0.0) += amount;
+    }
+
+    let tolerance = |currency| config.reconcile_tolerance_for(currency);
+    if balances_equal(&balance, &reported, tolerance) {
+        return Ok(balance);
+    }
+
+    let discrepancies = find_discrepancies(&balance, &reported, tolerance);
+    for discrepancy in &discrepancies {
+        output::emit(
+            "balance_discrepancy",
+            discrepancy,
+            format_args!(
+                "balance discrepancy {:?}: internal = {}, reported = {}",
+                discrepancy.currency, discrepancy.internal, discrepancy.reported
+            ),
+        );
+    }
+
+    if !config.snap_to_reported_balances || discrepancies.is_empty() {
+        return Ok(balance);
+    }
+
+    let mut balance = balance;
+    for discrepancy in discrepancies {
+        balance.insert(discrepancy.currency, discrepancy.reported);
+    }
+    Ok(balance)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{OrderBook, Symbol};
+
+    struct MockExchange(Option<HashMap<Currency, f64>>);
+
+    impl Exchange for MockExchange {
+        const VENUE: Venue = Venue::MercadoBitcoin;
+
+        async fn fetch_order_book(&self, _symbol: Symbol) -> Result<OrderBook, Error> {
+            Ok(OrderBook::builder().build())
+        }
+
+        async fn balances(&self) -> Result<Option<HashMap<Currency, f64>>, Error> {
+            Ok(self.0.clone())
+        }
+    }
+
+    fn reporting(balances: &[(Currency, f64)]) -> MockExchange {
+        MockExchange(Some(balances.iter().copied().collect()))
+    }
+
+    fn internal() -> HashMap<Currency, f64> {
+        HashMap::from([(Currency::BRL, 1_000.0), (Currency::BTC, 0.5)])
+    }
+
+    #[test]
+    fn finds_discrepancies_beyond_the_tolerance_only() {
+        let reported = HashMap::from([(Currency::BRL, 1_000.0 + 1e-12), (Currency::BTC, 0.4)]);
+        let discrepancies = find_discrepancies(&internal(), &reported, |_| 1e-8);
+        assert_eq!(discrepancies.len(), 1);
+        assert_eq!(discrepancies[0].currency, Currency::BTC);
+        assert_eq!(discrepancies[0].internal, 0.5);
+        assert_eq!(discrepancies[0].reported, 0.4);
+    }
+
+    #[tokio::test]
+    async fn snaps_to_the_reported_balances() {
+        let config = Config {
+            snap_to_reported_balances: true,
+            ..Config::default()
+        };
+        let binance = reporting(&[(Currency::BRL, 600.0), (Currency::BTC, 0.3)]);
+        let mb = reporting(&[(Currency::BRL, 400.0), (Currency::BTC, 0.1)]);
+        let balance = reconcile_balances(&binance, &mb, internal(), &config)
+            .await
+            .unwrap();
+        assert_eq!(balance[&Currency::BRL], 1_000.0);
+        assert!((balance[&Currency::BTC] - 0.4).abs() < 1e-12);
+    }
+
+    #[tokio::test]
+    async fn keeps_the_internal_balance_without_snapping() {
+        let binance = reporting(&[(Currency::BRL, 600.0), (Currency::BTC, 0.3)]);
+        let mb = reporting(&[(Currency::BRL, 400.0), (Currency::BTC, 0.1)]);
+        let balance = reconcile_balances(&binance, &mb, internal(), &Config::default())
+            .await
+            .unwrap();
+        assert_eq!(balance, internal());
+    }
+
+    #[tokio::test]
+    async fn a_venue_without_balances_is_not_taken_to_hold_nothing() {
+        let config = Config {
+            snap_to_reported_balances: true,
+            ..Config::default()
+        };
+        let binance = reporting(&[(Currency::BRL, 600.0)]);
+        let balance = reconcile_balances(&binance, &MockExchange(None), internal(), &config)
+            .await
+            .unwrap();
+        assert_eq!(balance, internal());
+    }
+}
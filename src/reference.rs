@@ -0,0 +1,61 @@
+use std::collections::HashMap;
+
+use reqwest::{Client, Error};
+
+use crate::{Currency, Symbol};
+
+// an independent spot price to sanity-check the venues' books against
+pub trait ReferencePrice {
+    async fn fetch_price(&self, symbol: Symbol) -> Result<Option<f64>, Error>;
+}
+
+#[derive(Default)]
+pub struct CoinGecko {
+    client: Client,
+}
+
+impl CoinGecko {
+    pub fn with_client(client: Client) -> Self {
+        Self { client }
+    }
+}
+
+fn coingecko_id(currency: Currency) -> Option<&'static str> {
+    match currency {
+        Currency::BTC => Some("bitcoin"),
+        Currency::ETH => Some("ethereum"),
+        Currency::USDT => Some("tether"),
+        Currency::BRL => None,
+    }
+}
+
+impl ReferencePrice for CoinGecko {
+    // None when CoinGecko doesn't price the pair
+    async fn fetch_price(&self, symbol: Symbol) -> Result<Option<f64>, Error> {
+        let Some(id) = coingecko_id(symbol.base_currency()) else {
+            return Ok(None);
+        };
+        let vs_currency = format!("{:?}", symbol.quote_currency()).to_lowercase();
+        let url = format!(
+            "https://api.coingecko.com/api/v3/simple/price?ids={}&vs_currencies={}",
+            id, vs_currency
+        );
+        let prices: HashMap<String, HashMap<String, f64>> = self
+            .client
+            .get(&url)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(prices
+            .get(id)
+            .and_then(|quotes| quotes.get(&vs_currency))
+            .copied())
+    }
+}
+
+// relative distance of `price` from `reference`, in percent
+pub fn deviation_pct(price: f64, reference: f64) -> f64 {
+    (price - reference).abs() / reference * 100.0
+}
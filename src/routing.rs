@@ -0,0 +1,155 @@
+use crate::{config::Config, get_fee_rate, Side, TaggedBook, Venue};
+
+// the part of a routed order sent to one venue, limited to the worst price it reaches there
+#[derive(Clone, Copy, Debug)]
+pub struct SubOrder {
+    pub venue: Venue,
+    pub side: Side,
+    pub qty: f64,
+    pub price: f64,
+    notional: f64,
+}
+
+// fills up to `qty` from `levels` in the order given, one sub-order per venue
+fn fill(levels: &[(Venue, f64, f64)], side: Side, qty: f64) -> Vec<SubOrder> {
+    let mut sub_orders: Vec<SubOrder> = Vec::new();
+    let mut remaining = qty;
+    for &(venue, price, level_qty) in levels {
+        if remaining <= 0.0 {
+            break;
+        }
+        let take = level_qty.min(remaining);
+        remaining -= take;
+        match sub_orders
+            .iter_mut()
+            .find(|sub_order| sub_order.venue == venue)
+        {
+            Some(sub_order) => {
+                sub_order.qty += take;
+                sub_order.notional += take * price;
+                // levels come best first per venue, so the latest is the worst reached
+                sub_order.price = price;
+            }
+            None => sub_orders.push(SubOrder {
+                venue,
+                side,
+                qty: take,
+                price,
+                notional: take * price,
+            }),
+        }
+    }
+    sub_orders
+}
+
+// quote paid for a buy or given up on a sell, fees included; lower is better either way
+fn cost(sub_orders: &[SubOrder], config: &Config) -> f64 {
+    sub_orders
+        .iter()
+        .map(|sub_order| {
+            let average_price = sub_order.notional / sub_order.qty;
+            let fee = sub_order.notional
+                * get_fee_rate(config, sub_order.venue, average_price, sub_order.qty);
+            match sub_order.side {
+                Side::Buy => sub_order.notional + fee,
+                Side::Sell => fee - sub_order.notional,
+            }
+        })
+        .sum()
+}
+
+// splits `qty` across the venues in `book` for the lowest cost after each venue's fees; the
+// fee-adjusted ladder is weighed against sending everything to a single venue, since a
+// venue's fee tier depends on the size it gets
+pub fn route(book: &TaggedBook, side: Side, qty: f64, config: &Config) -> Vec<SubOrder> {
+    let ladder: Vec<(Venue, f64, f64)> = match side {
+        Side::Buy => &book.asks,
+        Side::Sell => &book.bids,
+    }
+    .iter()
+    .map(|(venue, level)| (*venue, level.price.0, level.qty.0))
+    .collect();
+
+    let fee_adjusted = |(venue, price, _): &(Venue, f64, f64)| {
+        let fee_rate = get_fee_rate(config, *venue, *price, qty);
+        match side {
+            Side::Buy => price * (1.0 + fee_rate),
+            Side::Sell => -price * (1.0 - fee_rate),
+        }
+    };
+    let mut split = ladder.clone();
+    split.sort_by(|a, b| fee_adjusted(a).total_cmp(&fee_adjusted(b)));
+
+    let venues = [Venue::Binance, Venue::MercadoBitcoin];
+    let single_venue = venues.iter().map(|venue| {
+        let levels: Vec<(Venue, f64, f64)> = ladder
+            .iter()
+            .filter(|(level_venue, _, _)| level_venue == venue)
+            .copied()
+            .collect();
+        fill(&levels, side, qty)
+    });
+
+    let filled = |sub_orders: &[SubOrder]| {
+        sub_orders
+            .iter()
+            .map(|sub_order| sub_order.qty)
+            .sum::<f64>()
+    };
+    std::iter::once(fill(&split, side, qty))
+        .chain(single_venue)
+        .filter(|sub_orders| !sub_orders.is_empty())
+        // the most filled first, then the cheapest
+        .min_by(|a, b| {
+            filled(b)
+                .total_cmp(&filled(a))
+                .then_with(|| cost(a, config).total_cmp(&cost(b, config)))
+        })
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::OrderBook;
+
+    fn buy(binance: &OrderBook, mb: &OrderBook, qty: f64) -> Vec<(Venue, f64)> {
+        let book =
+            OrderBook::merge_tagged(&[(Venue::Binance, binance), (Venue::MercadoBitcoin, mb)]);
+        route(&book, Side::Buy, qty, &Config::default())
+            .iter()
+            .map(|sub_order| (sub_order.venue, sub_order.qty))
+            .collect()
+    }
+
+    #[test]
+    fn a_buy_is_split_across_venues_by_fee_adjusted_cost() {
+        // MB's cheaper ask still beats Binance's second level after its higher fee
+        let binance = OrderBook::builder()
+            .ask(10_000.0, 0.5)
+            .ask(10_100.0, 1.0)
+            .build();
+        let mb = OrderBook::builder().ask(9_990.0, 0.5).build();
+        assert_eq!(
+            buy(&binance, &mb, 1.0),
+            [(Venue::Binance, 0.5), (Venue::MercadoBitcoin, 0.5)]
+        );
+    }
+
+    #[test]
+    fn a_thin_best_level_is_topped_up_on_the_other_venue() {
+        let binance = OrderBook::builder().ask(10_000.0, 2.0).build();
+        let mb = OrderBook::builder().ask(9_900.0, 0.1).build();
+        assert_eq!(
+            buy(&binance, &mb, 1.0),
+            [(Venue::MercadoBitcoin, 0.1), (Venue::Binance, 0.9)]
+        );
+    }
+
+    #[test]
+    fn a_better_price_that_fees_outweigh_is_left_alone() {
+        let binance = OrderBook::builder().ask(10_000.0, 2.0).build();
+        let mb = OrderBook::builder().ask(9_980.0, 0.5).build();
+        assert_eq!(buy(&binance, &mb, 1.0), [(Venue::Binance, 1.0)]);
+    }
+}
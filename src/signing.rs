@@ -0,0 +1,78 @@
+use ring::hmac;
+
+// hex-encoded HMAC-SHA256 of the query string, as Binance expects in `signature`
+pub fn sign_request(query: &str, secret: &str) -> String {
+    let key = hmac::Key::new(hmac::HMAC_SHA256, secret.as_bytes());
+    let tag = hmac::sign(&key, query.as_bytes());
+    tag.as_ref()
+        .iter()
+        .map(|byte| format!("{:02x}", byte))
+        .collect()
+}
+
+// query parameters for a signed endpoint; recvWindow, timestamp and the signature are
+// appended last, in that order
+#[derive(Clone, Debug, Default)]
+pub struct SignedRequest {
+    params: Vec<(String, String)>,
+}
+
+impl SignedRequest {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn param(mut self, key: &str, value: impl ToString) -> Self {
+        self.params.push((key.to_string(), value.to_string()));
+        self
+    }
+
+    pub fn query(self, secret: &str, timestamp_ms: u64, recv_window_ms: u64) -> String {
+        let query = self
+            .param("recvWindow", recv_window_ms)
+            .param("timestamp", timestamp_ms)
+            .params
+            .iter()
+            .map(|(key, value)| format!("{}={}", key, value))
+            .collect::<Vec<String>>()
+            .join("&");
+        let signature = sign_request(&query, secret);
+        format!("{}&signature={}", query, signature)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the example from Binance's documentation on SIGNED endpoints
+    const SECRET: &str = "NhqPtmdSJYdKjVHjA7PZj4Mge3R5YNiP1e3UZjInClVN65XAbvqqM6A7H5fATj0j";
+    const SIGNATURE: &str = "c8db56825ae71d6d79447849e617115f4a920fa2acdcab2b053c4b2838bd6b71";
+
+    #[test]
+    fn signs_binance_documented_example() {
+        let query = "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1\
+                     &recvWindow=5000&timestamp=1499827319559";
+        assert_eq!(sign_request(query, SECRET), SIGNATURE);
+    }
+
+    #[test]
+    fn appends_the_window_timestamp_and_signature_last() {
+        let query = SignedRequest::new()
+            .param("symbol", "LTCBTC")
+            .param("side", "BUY")
+            .param("type", "LIMIT")
+            .param("timeInForce", "GTC")
+            .param("quantity", 1)
+            .param("price", 0.1)
+            .query(SECRET, 1_499_827_319_559, 5_000);
+        assert_eq!(
+            query,
+            format!(
+                "symbol=LTCBTC&side=BUY&type=LIMIT&timeInForce=GTC&quantity=1&price=0.1\
+                 &recvWindow=5000&timestamp=1499827319559&signature={}",
+                SIGNATURE
+            )
+        );
+    }
+}
@@ -0,0 +1,53 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+// how many recent klines to size trades from, and how far above the typical trade to go
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct KlineSizing {
+    pub interval: String,
+    pub limit: u32,
+    pub max_size_multiple: f64,
+}
+
+// the parts of a Binance kline row that tell trade sizes apart
+#[derive(Clone, Copy, Debug)]
+pub struct Kline {
+    pub volume: f64,
+    pub quote_volume: f64,
+    pub trades: u64,
+}
+
+impl Kline {
+    // Binance rows are positional: volume at 5, quote volume at 7 and the trade count at 8,
+    // the volumes as strings
+    pub fn from_row(row: &[Value]) -> Option<Self> {
+        let number = |index: usize| row.get(index)?.as_str()?.parse::<f64>().ok();
+        Some(Self {
+            volume: number(5)?,
+            quote_volume: number(7)?,
+            trades: row.get(8)?.as_u64()?,
+        })
+    }
+}
+
+// average base quantity and price of a trade across the klines
+#[derive(Clone, Copy, Debug)]
+pub struct TypicalTrade {
+    pub qty: f64,
+    pub price: f64,
+}
+
+impl TypicalTrade {
+    pub fn from_klines(klines: &[Kline]) -> Option<Self> {
+        let trades: u64 = klines.iter().map(|kline| kline.trades).sum();
+        let volume: f64 = klines.iter().map(|kline| kline.volume).sum();
+        let quote_volume: f64 = klines.iter().map(|kline| kline.quote_volume).sum();
+        if trades == 0 || volume <= 0.0 {
+            return None;
+        }
+        Some(Self {
+            qty: volume / trades as f64,
+            price: quote_volume / volume,
+        })
+    }
+}
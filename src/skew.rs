@@ -0,0 +1,88 @@
+use std::time::Duration;
+
+use reqwest::Error;
+
+use crate::{
+    clock::{unix_millis, Clock},
+    exchange::Binance,
+    output::log,
+};
+
+// offset between Binance's server clock and the local clock, used so that exchange
+// timestamps are compared against server time rather than our own
+pub struct ClockSkew {
+    offset_ms: i64,
+    estimated_at_ms: Option<u64>,
+    refresh_interval: Duration,
+}
+
+impl ClockSkew {
+    pub fn new(refresh_interval: Duration) -> Self {
+        Self {
+            offset_ms: 0,
+            estimated_at_ms: None,
+            refresh_interval,
+        }
+    }
+
+    pub fn offset_ms(&self) -> i64 {
+        self.offset_ms
+    }
+
+    // the local clock moved onto Binance's server clock
+    pub fn adjusted_now(&self, clock: &dyn Clock) -> u64 {
+        (clock.now_ms() as i64 + self.offset_ms).max(0) as u64
+    }
+
+    pub async fn refresh_if_due(
+        &mut self,
+        binance: &Binance,
+        clock: &dyn Clock,
+    ) -> Result<(), Error> {
+        if let Some(estimated_at_ms) = self.estimated_at_ms {
+            let elapsed_ms = clock.now_ms().saturating_sub(estimated_at_ms);
+            if elapsed_ms < self.refresh_interval.as_millis() as u64 {
+                return Ok(());
+            }
+        }
+
+        // the round trip itself is always measured against the real clock
+        let sent_at = unix_millis();
+        let server_time = binance.server_time().await?;
+        let received_at = unix_millis();
+
+        self.offset_ms = estimate_offset_ms(sent_at, server_time, received_at);
+        self.estimated_at_ms = Some(clock.now_ms());
+        log!("estimated clock skew = {} ms", self.offset_ms);
+        Ok(())
+    }
+}
+
+// assumes the server read its clock halfway through the round trip; the wall clock can step
+// back while the request is in flight, which counts as no round trip rather than underflowing
+fn estimate_offset_ms(sent_at: u64, server_time: u64, received_at: u64) -> i64 {
+    let local_time = sent_at + received_at.saturating_sub(sent_at) / 2;
+    server_time as i64 - local_time as i64
+}
+
+// `updated_at_ms` is an exchange timestamp, so it's aged against adjusted_now rather than
+// the local clock, which may have drifted
+pub fn is_stale(updated_at_ms: u64, server_now_ms: u64, max_age_ms: u64) -> bool {
+    server_now_ms.saturating_sub(updated_at_ms) > max_age_ms
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn estimates_the_offset_at_the_middle_of_the_round_trip() {
+        assert_eq!(estimate_offset_ms(1_000, 1_600, 1_200), 500);
+        assert_eq!(estimate_offset_ms(1_000, 900, 1_200), -200);
+    }
+
+    #[test]
+    fn a_clock_stepping_back_mid_request_does_not_underflow() {
+        assert_eq!(estimate_offset_ms(1_000, 1_500, 990), 500);
+    }
+}
@@ -0,0 +1,56 @@
+use std::{collections::HashMap, error::Error, fs};
+
+use serde::{Deserialize, Serialize};
+
+use crate::Symbol;
+
+// slippage seen at one order size, from recorded fills against the prices they were expected at
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct SlippagePoint {
+    pub qty: f64,
+    pub bps: f64,
+}
+
+// calibrated slippage by symbol and order size: linear between the calibrated sizes and flat
+// beyond the smallest and largest
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct SlippageModel {
+    curves: HashMap<Symbol, Vec<SlippagePoint>>,
+}
+
+impl SlippageModel {
+    // e.g. {"BTCBRL": [{"qty": 0.01, "bps": 1.0}, {"qty": 1.0, "bps": 12.0}]}
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        let mut model: Self = serde_json::from_str(&fs::read_to_string(path)?)?;
+        for curve in model.curves.values_mut() {
+            curve.retain(|point| point.qty.is_finite() && point.bps.is_finite());
+            curve.sort_by(|a, b| a.qty.total_cmp(&b.qty));
+        }
+        Ok(model)
+    }
+
+    pub fn len(&self) -> usize {
+        self.curves.len()
+    }
+
+    // None for a symbol without a calibration
+    pub fn bps(&self, symbol: Symbol, qty: f64) -> Option<f64> {
+        let curve = self.curves.get(&symbol)?;
+        let (first, last) = (curve.first()?, curve.last()?);
+        if qty <= first.qty {
+            return Some(first.bps);
+        }
+        if qty >= last.qty {
+            return Some(last.bps);
+        }
+        curve.windows(2).find_map(|pair| {
+            let (low, high) = (pair[0], pair[1]);
+            if qty > high.qty {
+                return None;
+            }
+            let share = (qty - low.qty) / (high.qty - low.qty);
+            Some(low.bps + (high.bps - low.bps) * share)
+        })
+    }
+}
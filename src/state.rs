@@ -0,0 +1,192 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, PoisonError, RwLock, RwLockReadGuard, RwLockWriteGuard},
+};
+
+use crate::{
+    accounts::Accounts,
+    admin::RuntimeSettings,
+    balance::{tradeable_balance, Shortfall},
+    config::Config,
+    stats::Stats,
+    Currency, Venue,
+};
+
+// mutable state shared between the trade loop and any task that reports on it
+#[derive(Clone, Debug)]
+pub struct State {
+    pub balance: HashMap<Currency, f64>,
+    // skimmed profit, held outside balance so that sizing and decisions never see it
+    pub reserve: HashMap<Currency, f64>,
+    // set aside by trades in flight and not yet booked, so concurrent trades can't
+    // commit the same funds twice
+    pub reserved: HashMap<Currency, f64>,
+    pub stats: Stats,
+    // the same capital broken down per sub-account
+    pub accounts: Accounts,
+    // what each venue's own fills have added to or taken from it, which is where inventory
+    // ends up stranded; decisions go by the combined balance
+    pub venue_balances: HashMap<Venue, HashMap<Currency, f64>>,
+    // what the admin API has changed, applied by the trade loop at the next cycle
+    pub settings: RuntimeSettings,
+}
+
+impl State {
+    pub fn skim(&mut self, currency: Currency, amount: f64) {
+        *self.balance.entry(currency).or_insert(0.0) -= amount;
+        *self.reserve.entry(currency).or_insert(0.0) += amount;
+        self.stats.reserved_profit += amount;
+    }
+
+    // the trading balance plus the reserve, which is what the venues hold
+    pub fn held_balance(&self) -> HashMap<Currency, f64> {
+        let mut held = self.balance.clone();
+        for (currency, amount) in &self.reserve {
+            *held.entry(*currency).or_insert(0.0) += amount;
+        }
+        held
+    }
+
+    // sets the trading balance from what the venues hold, less the reserve
+    pub fn set_held_balance(&mut self, held: HashMap<Currency, f64>) {
+        let mut balance = held;
+        for (currency, amount) in &self.reserve {
+            *balance.entry(*currency).or_insert(0.0) -= amount;
+        }
+        self.balance = balance;
+    }
+}
+
+// Locking discipline: balance and stats sit behind a single lock, so there is no lock order
+// to get wrong. Guards must never be held across an `.await`: take a snapshot, release the
+// guard, do the async work, then write the result back under a fresh guard. Scanned trades
+// execute concurrently, each from its own snapshot, so a snapshot can be outdated by the time
+// it's written back: trades reserve their funds under the lock before any order goes out,
+// and are booked as their change to the snapshot they executed from rather than by
+// overwriting the balance, keeping the reservation until then.
+#[derive(Clone, Debug)]
+pub struct SharedState(Arc<RwLock<State>>);
+
+impl SharedState {
+    pub fn new(state: State) -> Self {
+        Self(Arc::new(RwLock::new(state)))
+    }
+
+    // a panic while holding the lock leaves plain data behind, still fine to read
+    pub fn read(&self) -> RwLockReadGuard<'_, State> {
+        self.0.read().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn write(&self) -> RwLockWriteGuard<'_, State> {
+        self.0.write().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    pub fn balance(&self) -> HashMap<Currency, f64> {
+        self.read().balance.clone()
+    }
+
+    // sets `funds` aside, all or nothing, when what isn't already reserved covers them; they
+    // are released when the reservation drops, whether or not the trade went through
+    pub fn reserve(
+        &self,
+        funds: &[(Currency, f64)],
+        config: &Config,
+    ) -> Result<Reservation, Shortfall> {
+        let mut state = self.write();
+        for (currency, required) in funds {
+            let reserved = state.reserved.get(currency).copied().unwrap_or(0.0);
+            let available = tradeable_balance(&state.balance, *currency, config) - reserved;
+            if available < *required {
+                return Err(Shortfall {
+                    currency: *currency,
+                    required: *required,
+                    available,
+                });
+            }
+        }
+        for (currency, required) in funds {
+            *state.reserved.entry(*currency).or_insert(0.0) += required;
+        }
+        Ok(Reservation {
+            state: self.clone(),
+            funds: funds.to_vec(),
+        })
+    }
+}
+
+pub struct Reservation {
+    state: SharedState,
+    funds: Vec<(Currency, f64)>,
+}
+
+impl Drop for Reservation {
+    fn drop(&mut self) {
+        let mut state = self.state.write();
+        for (currency, amount) in &self.funds {
+            *state.reserved.entry(*currency).or_insert(0.0) -= amount;
+        }
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use crate::{
+        accounts::AccountPolicy, balance::required_funds, balance::tests::action,
+        get_default_balance,
+    };
+
+    pub(crate) fn shared_state(brl: f64) -> SharedState {
+        let config = Config::default();
+        let balance = get_default_balance(Currency::BRL, brl);
+        SharedState::new(State {
+            accounts: Accounts::new(&[], &balance, AccountPolicy::default()),
+            balance,
+            reserve: HashMap::new(),
+            reserved: HashMap::new(),
+            stats: Stats::default(),
+            venue_balances: HashMap::new(),
+            settings: RuntimeSettings::from_config(&config),
+        })
+    }
+
+    #[test]
+    fn two_trades_exceeding_the_balance_together_only_reserve_one() {
+        let state = shared_state(1_000.0);
+        let config = Config::default();
+        let first = state.reserve(&[(Currency::BRL, 600.0)], &config);
+        let second = state.reserve(&[(Currency::BRL, 600.0)], &config);
+        assert!(first.is_ok());
+        let shortfall = second.err().expect("the second trade over-commits");
+        assert_eq!(shortfall.required, 600.0);
+        assert_eq!(shortfall.available, 400.0);
+    }
+
+    #[test]
+    fn a_dropped_reservation_releases_its_funds() {
+        let state = shared_state(1_000.0);
+        let config = Config::default();
+        let reservation = state.reserve(&[(Currency::BRL, 600.0)], &config).unwrap();
+        drop(reservation);
+        assert!(state.reserve(&[(Currency::BRL, 600.0)], &config).is_ok());
+    }
+
+    #[test]
+    fn concurrent_trades_reserve_their_slippage_and_fee_buffers() {
+        let quoted = required_funds(&action(10_000.0, 0.1), &Config::default())[0].1;
+        // both trades fit at their quoted prices, not once the buy slips
+        let state = shared_state(2.0 * quoted + 1.0);
+        let config = Config {
+            slippage_bps: 100.0,
+            ..Config::default()
+        };
+        let funds = required_funds(&action(10_000.0, 0.1), &config);
+        let first = state.reserve(&funds, &config);
+        let second = state.reserve(&funds, &config);
+        assert!(first.is_ok());
+        let shortfall = second
+            .err()
+            .expect("the buffered trades over-commit together");
+        assert_eq!(shortfall.required, funds[0].1);
+    }
+}
@@ -0,0 +1,584 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    time::Duration,
+};
+
+use serde::Serialize;
+use serde_json::json;
+
+use crate::{
+    balance::{dust_balance, tradeable_balance},
+    config::Config,
+    latency::{percentile, CycleLatency},
+    output,
+    valuation::Marks,
+    Currency, Side, Symbol, TradeResult,
+};
+
+// cycles whose latency is kept for the percentiles, so a long --loop run doesn't grow them
+const LATENCY_WINDOW: usize = 1_000;
+
+// at the currency's precision when round_reported_amounts is set; accounting keeps the full one
+fn reported_amount(amount: f64, currency: Currency, config: &Config) -> f64 {
+    if config.round_reported_amounts {
+        currency.round(amount)
+    } else {
+        amount
+    }
+}
+
+// the totals line of the report
+#[derive(Debug, Serialize)]
+struct Summary {
+    trades: u32,
+    legs: u32,
+    realized_profit: f64,
+    fees: f64,
+    currency: Currency,
+    bought_qty: f64,
+    sold_qty: f64,
+    residual_exposure: f64,
+    holding_cost: f64,
+    transfer_costs: f64,
+    settlements: u32,
+    conversion_fees: f64,
+    conversions: u32,
+    peak_binance_weight: u32,
+    reserved_profit: f64,
+}
+
+// what one symbol traded in one direction has made
+#[derive(Clone, Copy, Debug, Default)]
+pub struct Attribution {
+    pub trades: u32,
+    pub realized_profit: f64,
+    pub fees: f64,
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct Stats {
+    pub trades: u32,
+    pub legs: u32,
+    pub realized_profit: f64,
+    pub fees: f64,
+    pub bought_qty: f64,
+    pub sold_qty: f64,
+    pub residual_exposure: f64,
+    // the last LATENCY_WINDOW cycles, oldest first
+    pub latencies: VecDeque<CycleLatency>,
+    // funding charged on idle inventory, in the base currency
+    pub holding_cost: f64,
+    // charged at netting settlements, in the symbol's quote currency
+    pub transfer_costs: f64,
+    pub settlements: u32,
+    // paid converting stranded inventory back towards its target, in the symbol's quote
+    // currency
+    pub conversion_fees: f64,
+    pub conversions: u32,
+    // highest per-minute request weight Binance reported
+    pub peak_binance_weight: u32,
+    // moved out of the trading balance, in the symbol's quote currency
+    pub reserved_profit: f64,
+    // base quantity the trades' own legs were sent for and filled, and how many trades
+    // filled completely, partly or not at all
+    pub attempted_qty: f64,
+    pub filled_qty: f64,
+    pub full_fills: u32,
+    pub partial_fills: u32,
+    pub no_fills: u32,
+    // keyed by symbol and direction, in each symbol's quote currency
+    pub attribution: HashMap<(Symbol, &'static str), Attribution>,
+}
+
+impl Stats {
+    pub fn record(&mut self, result: &TradeResult) {
+        self.trades += 1;
+        self.legs += result.executed_legs.len() as u32;
+        self.realized_profit += result.realized_profit;
+        self.fees += result.fees;
+        self.residual_exposure += result.residual_exposure;
+        self.attempted_qty += result.attempted_qty;
+        self.filled_qty += result.filled_qty;
+        // a shortfall below the smallest quantity step, 1e-8, counts as complete
+        if result.filled_qty <= 0.0 {
+            self.no_fills += 1;
+        } else if result.filled_qty >= result.attempted_qty - 1e-8 {
+            self.full_fills += 1;
+        } else {
+            self.partial_fills += 1;
+        }
+        let attribution = self
+            .attribution
+            .entry((result.symbol, result.direction))
+            .or_default();
+        attribution.trades += 1;
+        attribution.realized_profit += result.realized_profit;
+        attribution.fees += result.fees;
+        for fill in &result.fills {
+            match fill.leg.side {
+                Side::Buy => self.bought_qty += fill.qty,
+                Side::Sell => self.sold_qty += fill.qty,
+            }
+        }
+    }
+
+    // share of the attempted quantity that filled, None before any attempt
+    pub fn fill_ratio(&self) -> Option<f64> {
+        (self.attempted_qty > 0.0).then(|| self.filled_qty / self.attempted_qty)
+    }
+
+    pub fn record_settlement(&mut self, transfer_cost: f64) {
+        self.settlements += 1;
+        self.transfer_costs += transfer_cost;
+    }
+
+    pub fn record_conversion(&mut self, fee: f64) {
+        self.conversions += 1;
+        self.conversion_fees += fee;
+    }
+
+    pub fn record_latency(&mut self, latency: CycleLatency) {
+        if self.latencies.len() == LATENCY_WINDOW {
+            self.latencies.pop_front();
+        }
+        self.latencies.push_back(latency);
+    }
+
+    // p50 and p95 of each stage over the recorded cycles, None before the first
+    fn latency_breakdown(&self) -> Option<Vec<(&'static str, Duration, Duration)>> {
+        let samples = |stage: fn(&CycleLatency) -> Duration| -> Vec<Duration> {
+            self.latencies.iter().map(stage).collect()
+        };
+        let stages = [
+            ("fetch binance", samples(|latency| latency.fetch_a)),
+            ("fetch mb", samples(|latency| latency.fetch_b)),
+            ("decide", samples(|latency| latency.decide)),
+            ("execute", samples(|latency| latency.execute)),
+            ("total", samples(CycleLatency::total)),
+        ];
+        stages
+            .into_iter()
+            .map(|(stage, samples)| {
+                Some((
+                    stage,
+                    percentile(&samples, 50.0)?,
+                    percentile(&samples, 95.0)?,
+                ))
+            })
+            .collect()
+    }
+
+    fn report_latency(&self) {
+        for (stage, p50, p95) in self.latency_breakdown().unwrap_or_default() {
+            output::emit(
+                "latency",
+                json!({
+                    "stage": stage,
+                    "p50_ms": p50.as_secs_f64() * 1000.0,
+                    "p95_ms": p95.as_secs_f64() * 1000.0,
+                }),
+                format_args!("{} latency: p50 = {:?}, p95 = {:?}", stage, p50, p95),
+            );
+        }
+    }
+
+    // the run's totals as reported; profits and fees accrue in the symbol's quote currency
+    // and are shown in the base currency when there's a rate for it
+    fn summary(&self, config: &Config, marks: &Marks) -> Summary {
+        let shown = |amount, currency| reported_amount(amount, currency, config);
+        let (base_currency, quote_currency) =
+            (config.base_currency, config.symbol.quote_currency());
+        let (realized_profit, fees, currency) = match (
+            marks.convert(self.realized_profit, quote_currency, base_currency),
+            marks.convert(self.fees, quote_currency, base_currency),
+        ) {
+            (Some(realized_profit), Some(fees)) => (realized_profit, fees, base_currency),
+            _ => (self.realized_profit, self.fees, quote_currency),
+        };
+        let traded_currency = config.symbol.base_currency();
+        Summary {
+            trades: self.trades,
+            legs: self.legs,
+            realized_profit: shown(realized_profit, currency),
+            fees: shown(fees, currency),
+            currency,
+            bought_qty: shown(self.bought_qty, traded_currency),
+            sold_qty: shown(self.sold_qty, traded_currency),
+            residual_exposure: shown(self.residual_exposure, traded_currency),
+            holding_cost: shown(self.holding_cost, base_currency),
+            transfer_costs: shown(self.transfer_costs, quote_currency),
+            settlements: self.settlements,
+            conversion_fees: shown(self.conversion_fees, quote_currency),
+            conversions: self.conversions,
+            peak_binance_weight: self.peak_binance_weight,
+            reserved_profit: shown(self.reserved_profit, quote_currency),
+        }
+    }
+
+    pub fn report(&self, balance: &HashMap<Currency, f64>, config: &Config, marks: &Marks) {
+        let base_currency = config.base_currency;
+        let shown = |amount, currency| reported_amount(amount, currency, config);
+        let mut currencies: Vec<Currency> = balance.keys().copied().collect();
+        currencies.sort();
+
+        let mut total = 0.0;
+        let mut unconverted = Vec::new();
+        for currency in currencies {
+            match marks.convert(
+                tradeable_balance(balance, currency, config),
+                currency,
+                base_currency,
+            ) {
+                Some(value) => total += value,
+                None => unconverted.push(currency),
+            }
+            let amount = shown(tradeable_balance(balance, currency, config), currency);
+            let dust = shown(dust_balance(balance, currency, config), currency);
+            let data = json!({ "currency": currency, "amount": amount, "dust": dust });
+            if dust != 0.0 {
+                output::emit(
+                    "balance",
+                    data,
+                    format_args!(
+                        "{:?} = {:.*} (dust {})",
+                        currency,
+                        currency.decimals() as usize,
+                        amount,
+                        dust
+                    ),
+                );
+            } else {
+                output::emit(
+                    "balance",
+                    data,
+                    format_args!(
+                        "{:?} = {:.*}",
+                        currency,
+                        currency.decimals() as usize,
+                        amount
+                    ),
+                );
+            }
+        }
+
+        // holdings without a known rate are left out of the total rather than guessed
+        let total = shown(total, base_currency);
+        output::emit(
+            "total",
+            json!({ "total": total, "currency": base_currency, "unconverted": unconverted }),
+            format_args!("total = {} {:?}", total, base_currency),
+        );
+        if !unconverted.is_empty() {
+            output::log!(
+                "no {:?} rate for {:?}, excluded from the total",
+                base_currency,
+                unconverted
+            );
+        }
+
+        let summary = self.summary(config, marks);
+        output::emit(
+            "stats",
+            &summary,
+            format_args!(
+                "trades = {}, legs = {}, realized profit = {} {:?}, fees = {} {:?}, bought = {}, sold = {}, residual exposure = {}",
+                summary.trades,
+                summary.legs,
+                summary.realized_profit,
+                summary.currency,
+                summary.fees,
+                summary.currency,
+                summary.bought_qty,
+                summary.sold_qty,
+                summary.residual_exposure
+            ),
+        );
+        let (quote_currency, traded_currency) = (
+            config.symbol.quote_currency(),
+            config.symbol.base_currency(),
+        );
+        if self.holding_cost != 0.0 {
+            output::log!(
+                "holding cost = {} {:?}",
+                summary.holding_cost,
+                base_currency
+            );
+        }
+        if self.settlements > 0 {
+            output::log!(
+                "transfer costs = {} {:?} over {} settlements",
+                summary.transfer_costs,
+                quote_currency,
+                self.settlements
+            );
+        }
+        if self.conversions > 0 {
+            output::log!(
+                "conversion fees = {} {:?} over {} conversions",
+                summary.conversion_fees,
+                quote_currency,
+                self.conversions
+            );
+        }
+        if self.reserved_profit > 0.0 {
+            output::log!(
+                "reserved profit = {} {:?}",
+                summary.reserved_profit,
+                quote_currency
+            );
+        }
+        if let Some(fill_ratio) = self.fill_ratio() {
+            output::emit(
+                "fill_ratio",
+                json!({
+                    "fill_ratio": fill_ratio,
+                    "attempted_qty": shown(self.attempted_qty, traded_currency),
+                    "filled_qty": shown(self.filled_qty, traded_currency),
+                    "full": self.full_fills,
+                    "partial": self.partial_fills,
+                    "none": self.no_fills,
+                }),
+                format_args!(
+                    "fill ratio = {:.1}% ({} filled fully, {} partly, {} not at all)",
+                    fill_ratio * 100.0,
+                    self.full_fills,
+                    self.partial_fills,
+                    self.no_fills
+                ),
+            );
+        }
+        output::log!("peak Binance weight = {}", self.peak_binance_weight);
+        self.report_attribution(config);
+        self.report_latency();
+    }
+
+    // one row per symbol and direction, most profitable first, as reported
+    fn attribution_rows(&self, config: &Config) -> Vec<(Symbol, &'static str, Attribution)> {
+        let mut rows: Vec<_> = self
+            .attribution
+            .iter()
+            .map(|((symbol, direction), attribution)| {
+                let quote_currency = symbol.quote_currency();
+                let attribution = Attribution {
+                    realized_profit: reported_amount(
+                        attribution.realized_profit,
+                        quote_currency,
+                        config,
+                    ),
+                    fees: reported_amount(attribution.fees, quote_currency, config),
+                    ..*attribution
+                };
+                (*symbol, *direction, attribution)
+            })
+            .collect();
+        rows.sort_by(|(_, _, a), (_, _, b)| b.realized_profit.total_cmp(&a.realized_profit));
+        rows
+    }
+
+    fn report_attribution(&self, config: &Config) {
+        if self.attribution.is_empty() {
+            return;
+        }
+        output::log!(
+            "{:<8} {:<13} {:>7} {:>16} {:>12}",
+            "symbol",
+            "direction",
+            "trades",
+            "realized profit",
+            "fees"
+        );
+        for (symbol, direction, attribution) in self.attribution_rows(config) {
+            output::emit(
+                "attribution",
+                json!({
+                    "symbol": symbol,
+                    "direction": direction,
+                    "trades": attribution.trades,
+                    "realized_profit": attribution.realized_profit,
+                    "fees": attribution.fees,
+                    "currency": symbol.quote_currency(),
+                }),
+                format_args!(
+                    "{:<8} {:<13} {:>7} {:>16} {:>12}",
+                    format!("{:?}", symbol),
+                    direction,
+                    attribution.trades,
+                    attribution.realized_profit,
+                    attribution.fees
+                ),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{Fill, Leg, Venue};
+
+    fn traded(
+        symbol: Symbol,
+        direction: &'static str,
+        realized_profit: f64,
+        fees: f64,
+    ) -> TradeResult {
+        TradeResult {
+            symbol,
+            direction,
+            realized_profit,
+            fees,
+            ..result(1.0, 1.0)
+        }
+    }
+
+    fn result(attempted_qty: f64, filled_qty: f64) -> TradeResult {
+        TradeResult {
+            symbol: Symbol::BTCBRL,
+            direction: "ShortMb",
+            new_balance: HashMap::new(),
+            realized_profit: 0.0,
+            fees: 0.0,
+            residual_exposure: 0.0,
+            attempted_qty,
+            filled_qty,
+            fills: Vec::new(),
+            executed_legs: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn no_fill_ratio_before_anything_is_attempted() {
+        let mut stats = Stats::default();
+        assert_eq!(stats.fill_ratio(), None);
+
+        // a trade whose legs went out for nothing can't divide by zero either
+        stats.record(&result(0.0, 0.0));
+        assert_eq!(stats.fill_ratio(), None);
+        assert_eq!(stats.no_fills, 1);
+    }
+
+    #[test]
+    fn partial_ioc_fills_lower_the_ratio() {
+        let mut stats = Stats::default();
+        stats.record(&result(2.0, 2.0));
+        // an IOC leg that only found half its quantity, its remainder cancelled
+        stats.record(&result(2.0, 1.5));
+        stats.record(&result(2.0, 0.0));
+
+        assert_eq!(stats.full_fills, 1);
+        assert_eq!(stats.partial_fills, 1);
+        assert_eq!(stats.no_fills, 1);
+        assert_eq!(stats.fill_ratio(), Some(3.5 / 6.0));
+    }
+
+    fn latency(ms: u64) -> CycleLatency {
+        CycleLatency {
+            fetch_a: Duration::from_millis(ms),
+            fetch_b: Duration::from_millis(2 * ms),
+            decide: Duration::from_millis(1),
+            execute: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn latency_breaks_down_into_percentiles_per_stage() {
+        let mut stats = Stats::default();
+        assert!(stats.latency_breakdown().is_none());
+        for ms in 1..=100 {
+            stats.record_latency(latency(ms));
+        }
+        let breakdown = stats.latency_breakdown().unwrap();
+        let stage = |name| {
+            breakdown
+                .iter()
+                .find(|(stage, ..)| *stage == name)
+                .map(|(_, p50, p95)| (p50.as_millis(), p95.as_millis()))
+                .unwrap()
+        };
+        assert_eq!(stage("fetch binance"), (50, 95));
+        assert_eq!(stage("fetch mb"), (100, 190));
+        assert_eq!(stage("decide"), (1, 1));
+        assert_eq!(stage("total"), (151, 286));
+    }
+
+    #[test]
+    fn only_the_latest_cycles_latencies_are_kept() {
+        let mut stats = Stats::default();
+        for ms in 0..LATENCY_WINDOW as u64 + 10 {
+            stats.record_latency(latency(ms));
+        }
+        assert_eq!(stats.latencies.len(), LATENCY_WINDOW);
+        assert_eq!(stats.latencies[0].fetch_a, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn a_shortfall_below_the_quantity_step_counts_as_full() {
+        let mut stats = Stats::default();
+        stats.record(&result(1.0, 1.0 - 1e-9));
+        assert_eq!(stats.full_fills, 1);
+        assert_eq!(stats.partial_fills, 0);
+    }
+
+    #[test]
+    fn profits_are_attributed_per_symbol_and_direction() {
+        let mut stats = Stats::default();
+        stats.record(&traded(Symbol::BTCBRL, "ShortMb", 10.0, 1.0));
+        stats.record(&traded(Symbol::BTCBRL, "ShortBinance", -2.0, 0.5));
+        stats.record(&traded(Symbol::ETHBRL, "ShortMb", 4.0, 0.25));
+        stats.record(&traded(Symbol::BTCBRL, "ShortMb", 5.0, 1.0));
+
+        let rows: Vec<_> = stats
+            .attribution_rows(&Config::default())
+            .into_iter()
+            .map(|(symbol, direction, attribution)| {
+                (
+                    symbol,
+                    direction,
+                    attribution.trades,
+                    attribution.realized_profit,
+                    attribution.fees,
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                (Symbol::BTCBRL, "ShortMb", 2, 15.0, 2.0),
+                (Symbol::ETHBRL, "ShortMb", 1, 4.0, 0.25),
+                (Symbol::BTCBRL, "ShortBinance", 1, -2.0, 0.5),
+            ]
+        );
+    }
+
+    #[test]
+    fn reported_amounts_are_rounded_to_each_currencys_decimals() {
+        let mut stats = Stats::default();
+        stats.record(&TradeResult {
+            fills: vec![Fill {
+                leg: Leg {
+                    venue: Venue::Binance,
+                    side: Side::Buy,
+                },
+                symbol: Symbol::BTCBRL,
+                price: 10_000.0,
+                qty: 0.123456789,
+            }],
+            ..traded(Symbol::BTCBRL, "ShortMb", 12.345678, 0.004)
+        });
+        let config = Config {
+            round_reported_amounts: true,
+            ..Config::default()
+        };
+        let summary = stats.summary(&config, &Marks::default());
+        assert_eq!(summary.currency, Currency::BRL);
+        assert_eq!(summary.realized_profit, 12.35);
+        assert_eq!(summary.fees, 0.0);
+        assert_eq!(summary.bought_qty, 0.12345679);
+        assert_eq!(stats.attribution_rows(&config)[0].2.realized_profit, 12.35);
+
+        // accounting itself keeps full precision
+        assert_eq!(stats.realized_profit, 12.345678);
+        let summary = stats.summary(&Config::default(), &Marks::default());
+        assert_eq!(summary.realized_profit, 12.345678);
+    }
+}
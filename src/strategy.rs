@@ -0,0 +1,408 @@
+use std::collections::HashMap;
+
+use crate::{
+    best_arbitrage,
+    config::{Config, FairValue},
+    get_mb_fee_rate, Fill, OrderBook, Side, Symbol, TradeAction, Venue,
+};
+#[cfg(feature = "io")]
+use {crate::output::log, reqwest::Error, std::time::Duration, tokio::time::sleep};
+
+pub(crate) trait Strategy {
+    type Action;
+
+    fn evaluate(
+        &mut self,
+        binance_order_book: &OrderBook,
+        mb_order_book: &OrderBook,
+        symbol: Symbol,
+    ) -> Option<Self::Action>;
+}
+
+pub struct Arbitrage<'a> {
+    pub config: &'a Config,
+    // in the symbol's quote currency
+    pub min_profit: f64,
+}
+
+impl Strategy for Arbitrage<'_> {
+    type Action = TradeAction;
+
+    fn evaluate(
+        &mut self,
+        binance_order_book: &OrderBook,
+        mb_order_book: &OrderBook,
+        symbol: Symbol,
+    ) -> Option<Self::Action> {
+        best_arbitrage(
+            binance_order_book,
+            mb_order_book,
+            symbol,
+            self.config,
+            self.min_profit,
+        )
+    }
+}
+
+// inventory of the symbol's base currency held on both venues, so that either direction can
+// be traded as the spread swings without waiting for transfers; each trade moves inventory
+// from the sell venue to the buy venue and trades the other way move it back
+pub struct HedgedPair {
+    // what each venue starts with, and what trades are steered back towards
+    target: f64,
+    inventory: HashMap<Venue, f64>,
+}
+
+impl HedgedPair {
+    pub fn new(target: f64) -> Self {
+        Self {
+            target,
+            inventory: [(Venue::Binance, target), (Venue::MercadoBitcoin, target)].into(),
+        }
+    }
+
+    fn inventory(&self, venue: Venue) -> f64 {
+        self.inventory.get(&venue).copied().unwrap_or(0.0)
+    }
+
+    // of the profitable `actions`, best first, those the sell venue holds enough for; one that
+    // brings inventory back towards the target goes before one that pushes it further out
+    pub fn choose(&self, actions: Vec<TradeAction>) -> Option<TradeAction> {
+        let mut feasible: Vec<TradeAction> = actions
+            .into_iter()
+            .filter(|action| self.inventory(action.venues().1) >= action.qty())
+            .collect();
+        let rebalances = |action: &TradeAction| self.inventory(action.venues().1) > self.target;
+        // stable, so the best-first order holds within each group
+        feasible.sort_by_key(|action| !rebalances(action));
+        feasible.into_iter().next()
+    }
+
+    pub fn record(&mut self, fills: &[Fill]) {
+        for fill in fills {
+            let change = match fill.leg.side {
+                Side::Buy => fill.qty,
+                Side::Sell => -fill.qty,
+            };
+            *self.inventory.entry(fill.leg.venue).or_insert(0.0) += change;
+        }
+    }
+
+    // how far inventory has drifted from even, positive when it has piled up on Binance
+    pub fn imbalance(&self) -> f64 {
+        (self.inventory(Venue::Binance) - self.inventory(Venue::MercadoBitcoin)) / 2.0
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct Quote {
+    pub venue: Venue,
+    pub symbol: Symbol,
+    pub bid_price: f64,
+    pub ask_price: f64,
+    pub qty: f64,
+    pub reference_mid: f64,
+}
+
+#[derive(Clone, Copy, Debug)]
+pub enum QuoteUpdate {
+    Place(Quote),
+    Replace { old: Quote, new: Quote },
+    Cancel(Quote),
+    Keep(Quote),
+}
+
+pub struct MarketMaker {
+    fair_value: FairValue,
+    edge_bps: f64,
+    requote_bps: f64,
+    binance_fee_rate: f64,
+    active: Option<Quote>,
+}
+
+impl MarketMaker {
+    pub fn new(config: &Config) -> Self {
+        Self {
+            fair_value: config.fair_value,
+            edge_bps: config.mm_edge_bps,
+            requote_bps: config.mm_requote_bps,
+            binance_fee_rate: config.binance_fee_rate,
+            active: None,
+        }
+    }
+
+    pub fn set_binance_fee_rate(&mut self, fee_rate: f64) {
+        self.binance_fee_rate = fee_rate;
+    }
+
+    // quotes go on the venue with the lower mid, priced around the other venue's mid so
+    // that a fill can be hedged there after paying both fees
+    pub fn compute_quote(
+        &self,
+        binance_order_book: &OrderBook,
+        mb_order_book: &OrderBook,
+        symbol: Symbol,
+    ) -> Option<Quote> {
+        let binance_mid = binance_order_book.fair_value(self.fair_value)?;
+        let mb_mid = mb_order_book.fair_value(self.fair_value)?;
+
+        let (venue, reference_book, reference_mid) = if binance_mid < mb_mid {
+            (Venue::Binance, mb_order_book, mb_mid)
+        } else {
+            (Venue::MercadoBitcoin, binance_order_book, binance_mid)
+        };
+
+        let reference_bid = reference_book.bids.first()?;
+        let reference_ask = reference_book.asks.first()?;
+        let qty = reference_bid.qty.min(reference_ask.qty).0;
+
+        let mb_fee_rate = get_mb_fee_rate(reference_mid, qty);
+        let fee_rate = self.binance_fee_rate + mb_fee_rate;
+        let half_spread = fee_rate + self.edge_bps / 10_000.0;
+
+        Some(Quote {
+            venue,
+            symbol,
+            bid_price: reference_mid * (1.0 - half_spread),
+            ask_price: reference_mid * (1.0 + half_spread),
+            qty,
+            reference_mid,
+        })
+    }
+
+    fn needs_requote(&self, active: &Quote, desired: &Quote) -> bool {
+        if active.venue != desired.venue {
+            return true;
+        }
+        let drift = (desired.reference_mid - active.reference_mid).abs() / active.reference_mid;
+        drift * 10_000.0 > self.requote_bps
+    }
+}
+
+impl Strategy for MarketMaker {
+    type Action = QuoteUpdate;
+
+    fn evaluate(
+        &mut self,
+        binance_order_book: &OrderBook,
+        mb_order_book: &OrderBook,
+        symbol: Symbol,
+    ) -> Option<Self::Action> {
+        let desired = self.compute_quote(binance_order_book, mb_order_book, symbol);
+
+        let update = match (self.active, desired) {
+            (None, None) => return None,
+            (Some(active), None) => QuoteUpdate::Cancel(active),
+            (None, Some(new)) => QuoteUpdate::Place(new),
+            (Some(old), Some(new)) if self.needs_requote(&old, &new) => {
+                QuoteUpdate::Replace { old, new }
+            }
+            (Some(active), Some(_)) => QuoteUpdate::Keep(active),
+        };
+
+        self.active = match update {
+            QuoteUpdate::Cancel(_) => None,
+            QuoteUpdate::Place(quote) | QuoteUpdate::Replace { new: quote, .. } => Some(quote),
+            QuoteUpdate::Keep(quote) => Some(quote),
+        };
+
+        Some(update)
+    }
+}
+
+#[cfg(feature = "io")]
+pub async fn apply_quote_update(update: QuoteUpdate) -> Result<(), Error> {
+    match update {
+        QuoteUpdate::Place(quote) => post_quote(quote).await,
+        QuoteUpdate::Replace { old, new } => {
+            cancel_quote(old).await?;
+            post_quote(new).await
+        }
+        QuoteUpdate::Cancel(quote) => cancel_quote(quote).await,
+        QuoteUpdate::Keep(_) => Ok(()),
+    }
+}
+
+// placeholders until quotes are sent to the venues, which is why market_making is held to
+// simulated mode
+#[cfg(feature = "io")]
+async fn post_quote(quote: Quote) -> Result<(), Error> {
+    log!(
+        "posting {:?} quote on {:?}: bid {} / ask {} x {}",
+        quote.symbol,
+        quote.venue,
+        quote.bid_price,
+        quote.ask_price,
+        quote.qty
+    );
+    sleep(Duration::from_secs(1)).await;
+    Ok(())
+}
+
+#[cfg(feature = "io")]
+async fn cancel_quote(quote: Quote) -> Result<(), Error> {
+    log!("cancelling {:?} quote on {:?}", quote.symbol, quote.venue);
+    sleep(Duration::from_secs(1)).await;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn market_maker() -> MarketMaker {
+        MarketMaker::new(&Config {
+            mm_edge_bps: 10.0,
+            mm_requote_bps: 5.0,
+            ..Config::default()
+        })
+    }
+
+    fn book(bid: f64, ask: f64) -> OrderBook {
+        OrderBook::builder().bid(bid, 1.0).ask(ask, 1.0).build()
+    }
+
+    // buying on `buy_venue` and selling qty on the other
+    fn action(buy_venue: Venue, qty: f64, net_profit: f64) -> TradeAction {
+        let sell_venue = match buy_venue {
+            Venue::Binance => Venue::MercadoBitcoin,
+            Venue::MercadoBitcoin => Venue::Binance,
+        };
+        TradeAction::new(
+            (buy_venue, 10_000.0),
+            (sell_venue, 10_500.0),
+            qty,
+            Symbol::BTCBRL,
+            0.0,
+            net_profit,
+            Default::default(),
+        )
+        .unwrap()
+    }
+
+    fn fill(venue: Venue, side: Side, qty: f64) -> Fill {
+        Fill {
+            leg: crate::Leg { venue, side },
+            symbol: Symbol::BTCBRL,
+            price: 10_000.0,
+            qty,
+        }
+    }
+
+    #[test]
+    fn skips_directions_the_sell_venue_cannot_cover() {
+        let mut hedged_pair = HedgedPair::new(1.0);
+        hedged_pair.record(&[fill(Venue::MercadoBitcoin, Side::Sell, 0.8)]);
+        // MB holds 0.2, so only selling on Binance fits a 0.5 trade
+        let chosen = hedged_pair
+            .choose(vec![
+                action(Venue::Binance, 0.5, 100.0),
+                action(Venue::MercadoBitcoin, 0.5, 50.0),
+            ])
+            .unwrap();
+        assert_eq!(chosen.venues().1, Venue::Binance);
+        assert!(hedged_pair
+            .choose(vec![action(Venue::Binance, 0.5, 100.0)])
+            .is_none());
+    }
+
+    #[test]
+    fn prefers_the_direction_that_rebalances() {
+        let mut hedged_pair = HedgedPair::new(1.0);
+        hedged_pair.record(&[
+            fill(Venue::Binance, Side::Buy, 0.5),
+            fill(Venue::MercadoBitcoin, Side::Sell, 0.5),
+        ]);
+        // selling on Binance, where inventory piled up, goes first though it pays less
+        let chosen = hedged_pair
+            .choose(vec![
+                action(Venue::Binance, 0.1, 100.0),
+                action(Venue::MercadoBitcoin, 0.1, 50.0),
+            ])
+            .unwrap();
+        assert_eq!(chosen.venues().1, Venue::Binance);
+        assert_eq!(chosen.net_profit(), 50.0);
+
+        // at even inventory the best one goes first
+        let chosen = HedgedPair::new(1.0)
+            .choose(vec![
+                action(Venue::Binance, 0.1, 100.0),
+                action(Venue::MercadoBitcoin, 0.1, 50.0),
+            ])
+            .unwrap();
+        assert_eq!(chosen.net_profit(), 100.0);
+    }
+
+    #[test]
+    fn fills_move_inventory_between_the_venues() {
+        let mut hedged_pair = HedgedPair::new(1.0);
+        assert_eq!(hedged_pair.imbalance(), 0.0);
+        hedged_pair.record(&[
+            fill(Venue::Binance, Side::Buy, 0.3),
+            fill(Venue::MercadoBitcoin, Side::Sell, 0.3),
+        ]);
+        assert!((hedged_pair.inventory(Venue::Binance) - 1.3).abs() < 1e-12);
+        assert!((hedged_pair.inventory(Venue::MercadoBitcoin) - 0.7).abs() < 1e-12);
+        assert!((hedged_pair.imbalance() - 0.3).abs() < 1e-12);
+    }
+
+    #[test]
+    fn quotes_the_cheaper_venue_around_the_other_mid() {
+        let quote = market_maker()
+            .compute_quote(
+                &book(9_990.0, 10_010.0),
+                &book(10_090.0, 10_110.0),
+                Symbol::BTCBRL,
+            )
+            .unwrap();
+        assert_eq!(quote.venue, Venue::Binance);
+        assert_eq!(quote.reference_mid, 10_100.0);
+        // Binance's 0.1% and MB's 0.7% at this size, plus the 10bps edge
+        let half_spread = 0.001 + 0.007 + 0.001;
+        assert!((quote.bid_price - 10_100.0 * (1.0 - half_spread)).abs() < 1e-6);
+        assert!((quote.ask_price - 10_100.0 * (1.0 + half_spread)).abs() < 1e-6);
+
+        let quote = market_maker()
+            .compute_quote(
+                &book(10_090.0, 10_110.0),
+                &book(9_990.0, 10_010.0),
+                Symbol::BTCBRL,
+            )
+            .unwrap();
+        assert_eq!(quote.venue, Venue::MercadoBitcoin);
+    }
+
+    #[test]
+    fn requotes_only_once_the_reference_drifts_past_requote_bps() {
+        let mut market_maker = market_maker();
+        let binance = book(9_990.0, 10_010.0);
+        let evaluate = |market_maker: &mut MarketMaker, mb: &OrderBook| {
+            market_maker.evaluate(&binance, mb, Symbol::BTCBRL)
+        };
+
+        let mb = book(10_090.0, 10_110.0);
+        assert!(matches!(
+            evaluate(&mut market_maker, &mb),
+            Some(QuoteUpdate::Place(_))
+        ));
+        // 1bp of drift
+        let mb = book(10_091.0, 10_111.0);
+        assert!(matches!(
+            evaluate(&mut market_maker, &mb),
+            Some(QuoteUpdate::Keep(_))
+        ));
+        // 1%
+        let mb = book(10_191.0, 10_211.0);
+        assert!(matches!(
+            evaluate(&mut market_maker, &mb),
+            Some(QuoteUpdate::Replace { .. })
+        ));
+        // nothing to price against
+        let mb = OrderBook::builder().ask(10_211.0, 1.0).build();
+        assert!(matches!(
+            evaluate(&mut market_maker, &mb),
+            Some(QuoteUpdate::Cancel(_))
+        ));
+        assert!(evaluate(&mut market_maker, &mb).is_none());
+    }
+}
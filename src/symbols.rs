@@ -0,0 +1,245 @@
+use std::{collections::HashMap, fs};
+
+use reqwest::Error;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    clock::unix_millis,
+    exchange::{Binance, MercadoBitcoin},
+    output::log,
+    Currency, Symbol,
+};
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BinanceExchangeInfo {
+    pub symbols: Vec<BinanceSymbolData>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct BinanceSymbolData {
+    pub symbol: String,
+    pub status: String,
+    #[serde(rename = "baseAsset")]
+    pub base_asset: String,
+    #[serde(rename = "quoteAsset")]
+    pub quote_asset: String,
+    pub filters: Vec<BinanceSymbolFilter>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+#[serde(tag = "filterType")]
+pub enum BinanceSymbolFilter {
+    #[serde(rename = "PRICE_FILTER")]
+    Price {
+        #[serde(rename = "tickSize")]
+        tick_size: String,
+    },
+    #[serde(rename = "LOT_SIZE")]
+    LotSize {
+        #[serde(rename = "stepSize")]
+        step_size: String,
+        #[serde(rename = "minQty")]
+        min_qty: String,
+    },
+    #[serde(rename = "NOTIONAL", alias = "MIN_NOTIONAL")]
+    Notional {
+        #[serde(rename = "minNotional")]
+        min_notional: String,
+    },
+    #[serde(other)]
+    Other,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MBSymbolsData {
+    pub symbol: Vec<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SymbolInfo {
+    pub binance_symbol: String,
+    pub mb_symbol: String,
+    pub base_asset: String,
+    pub quote_asset: String,
+    pub tick_size: f64,
+    pub step_size: f64,
+    pub min_qty: f64,
+    pub min_notional: f64,
+}
+
+impl SymbolInfo {
+    fn from_binance(data: &BinanceSymbolData) -> Self {
+        let mut info = Self {
+            binance_symbol: data.symbol.clone(),
+            mb_symbol: format!("{}-{}", data.base_asset, data.quote_asset),
+            base_asset: data.base_asset.clone(),
+            quote_asset: data.quote_asset.clone(),
+            tick_size: 0.0,
+            step_size: 0.0,
+            min_qty: 0.0,
+            min_notional: 0.0,
+        };
+        for filter in &data.filters {
+            match filter {
+                BinanceSymbolFilter::Price { tick_size } => {
+                    info.tick_size = tick_size.parse().unwrap_or(0.0);
+                }
+                BinanceSymbolFilter::LotSize { step_size, min_qty } => {
+                    info.step_size = step_size.parse().unwrap_or(0.0);
+                    info.min_qty = min_qty.parse().unwrap_or(0.0);
+                }
+                BinanceSymbolFilter::Notional { min_notional } => {
+                    info.min_notional = min_notional.parse().unwrap_or(0.0);
+                }
+                BinanceSymbolFilter::Other => {}
+            }
+        }
+        // without a LOT_SIZE filter, step by the base currency's precision when it's known
+        if info.step_size == 0.0 {
+            if let Some(base) = Currency::from_asset(&info.base_asset) {
+                info.step_size = base.unit();
+            }
+        }
+        info
+    }
+}
+
+// symbols trading on Binance that MB also lists, keyed by the Binance symbol
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct SymbolRegistry {
+    infos: HashMap<String, SymbolInfo>,
+}
+
+// the registry as last fetched, kept on disk since the filters rarely change
+#[derive(Serialize, Deserialize)]
+struct CachedRegistry {
+    fetched_at_ms: u64,
+    registry: SymbolRegistry,
+}
+
+impl SymbolRegistry {
+    pub fn from_exchange_info(exchange_info: &BinanceExchangeInfo, mb_symbols: &[String]) -> Self {
+        let infos = exchange_info
+            .symbols
+            .iter()
+            .filter(|data| data.status == "TRADING")
+            .map(SymbolInfo::from_binance)
+            .filter(|info| mb_symbols.contains(&info.mb_symbol))
+            .map(|info| (info.binance_symbol.clone(), info))
+            .collect();
+        Self { infos }
+    }
+
+    pub async fn fetch(binance: &Binance, mb: &MercadoBitcoin) -> Result<Self, Error> {
+        let exchange_info = binance.exchange_info().await?;
+        let mb_symbols = mb.listed_symbols().await?;
+        Ok(Self::from_exchange_info(&exchange_info, &mb_symbols))
+    }
+
+    // the cache at `path` while it's younger than `ttl_ms`, otherwise a fresh fetch that then
+    // replaces it; a failed fetch falls back to a stale cache when there is one
+    pub async fn fetch_cached(
+        binance: &Binance,
+        mb: &MercadoBitcoin,
+        path: &str,
+        ttl_ms: u64,
+    ) -> Result<Self, Error> {
+        let cached = fs::read_to_string(path)
+            .ok()
+            .and_then(|contents| serde_json::from_str::<CachedRegistry>(&contents).ok());
+        let now_ms = unix_millis();
+        if let Some(cached) = &cached {
+            if now_ms.saturating_sub(cached.fetched_at_ms) < ttl_ms {
+                return Ok(cached.registry.clone());
+            }
+        }
+
+        match Self::fetch(binance, mb).await {
+            Ok(registry) => {
+                let cache = CachedRegistry {
+                    fetched_at_ms: now_ms,
+                    registry,
+                };
+                match serde_json::to_string(&cache) {
+                    Ok(contents) => {
+                        if let Err(error) = fs::write(path, contents) {
+                            log!("could not write the symbol cache to {}: {}", path, error);
+                        }
+                    }
+                    Err(error) => log!("could not serialize the symbol cache: {}", error),
+                }
+                Ok(cache.registry)
+            }
+            Err(error) => match cached {
+                Some(cached) => {
+                    log!(
+                        "symbol refresh failed, using the cache from {} ms ago: {}",
+                        now_ms.saturating_sub(cached.fetched_at_ms),
+                        error
+                    );
+                    Ok(cached.registry)
+                }
+                None => Err(error),
+            },
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.infos.len()
+    }
+
+    pub fn get(&self, symbol: Symbol) -> Option<&SymbolInfo> {
+        self.infos.get(symbol.get_binance_symbol_param())
+    }
+
+    // the well-known pairs that are currently tradeable on both exchanges
+    pub fn known_symbols(&self) -> Vec<Symbol> {
+        self.infos
+            .keys()
+            .filter_map(|binance_symbol| Symbol::from_binance_symbol_param(binance_symbol))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXCHANGE_INFO: &str = r#"{"symbols": [
+        {"symbol": "BTCBRL", "status": "TRADING", "baseAsset": "BTC", "quoteAsset": "BRL",
+         "filters": [
+            {"filterType": "PRICE_FILTER", "minPrice": "1", "tickSize": "1.00000000"},
+            {"filterType": "LOT_SIZE", "minQty": "0.00001", "stepSize": "0.00001000"},
+            {"filterType": "NOTIONAL", "minNotional": "10.00000000"},
+            {"filterType": "ICEBERG_PARTS", "limit": 10}
+         ]},
+        {"symbol": "ETHBRL", "status": "BREAK", "baseAsset": "ETH", "quoteAsset": "BRL",
+         "filters": []},
+        {"symbol": "DOGEBRL", "status": "TRADING", "baseAsset": "DOGE", "quoteAsset": "BRL",
+         "filters": []}
+    ]}"#;
+
+    #[test]
+    fn keeps_the_pairs_trading_on_binance_and_listed_on_mb() {
+        let exchange_info: BinanceExchangeInfo = serde_json::from_str(EXCHANGE_INFO).unwrap();
+        let registry = SymbolRegistry::from_exchange_info(
+            &exchange_info,
+            &["BTC-BRL".to_string(), "ETH-BRL".to_string()],
+        );
+        assert_eq!(registry.len(), 1);
+        assert_eq!(registry.known_symbols(), vec![Symbol::BTCBRL]);
+        assert_eq!(
+            registry.get(Symbol::BTCBRL),
+            Some(&SymbolInfo {
+                binance_symbol: "BTCBRL".to_string(),
+                mb_symbol: "BTC-BRL".to_string(),
+                base_asset: "BTC".to_string(),
+                quote_asset: "BRL".to_string(),
+                tick_size: 1.0,
+                step_size: 0.00001,
+                min_qty: 0.00001,
+                min_notional: 10.0,
+            })
+        );
+    }
+}
@@ -0,0 +1,146 @@
+use serde::{Deserialize, Deserializer, Serialize};
+
+use crate::{Currency, Fill, Side, Symbol, Venue};
+
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct WithdrawalFee {
+    pub venue: Venue,
+    pub currency: Currency,
+    // charged in the withdrawn currency
+    pub flat: f64,
+    #[serde(default)]
+    pub percentage: f64,
+}
+
+impl WithdrawalFee {
+    fn new(venue: Venue, currency: Currency, flat: f64) -> Self {
+        Self {
+            venue,
+            currency,
+            flat,
+            percentage: 0.0,
+        }
+    }
+
+    // fee in the withdrawn currency for moving `amount` of it
+    pub fn cost(&self, amount: f64) -> f64 {
+        self.flat + amount * self.percentage
+    }
+}
+
+// configured entries replace the defaults for the same venue and currency, the rest are kept
+#[derive(Clone, Debug, Serialize)]
+#[serde(transparent)]
+pub struct WithdrawalFees {
+    fees: Vec<WithdrawalFee>,
+}
+
+impl Default for WithdrawalFees {
+    fn default() -> Self {
+        // published fees at the time of writing; BTC/ETH on their native chains, USDT on TRON
+        Self {
+            fees: vec![
+                WithdrawalFee::new(Venue::Binance, Currency::BRL, 0.0),
+                WithdrawalFee::new(Venue::Binance, Currency::BTC, 0.0002),
+                WithdrawalFee::new(Venue::Binance, Currency::ETH, 0.0016),
+                WithdrawalFee::new(Venue::Binance, Currency::USDT, 1.0),
+                WithdrawalFee::new(Venue::MercadoBitcoin, Currency::BRL, 1.99),
+                WithdrawalFee::new(Venue::MercadoBitcoin, Currency::BTC, 0.0001),
+                WithdrawalFee::new(Venue::MercadoBitcoin, Currency::ETH, 0.01),
+                WithdrawalFee::new(Venue::MercadoBitcoin, Currency::USDT, 1.0),
+            ],
+        }
+    }
+}
+
+impl<'de> Deserialize<'de> for WithdrawalFees {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let overrides = Vec::<WithdrawalFee>::deserialize(deserializer)?;
+        let mut withdrawal_fees = Self::default();
+        for fee in overrides {
+            withdrawal_fees
+                .fees
+                .retain(|f| f.venue != fee.venue || f.currency != fee.currency);
+            withdrawal_fees.fees.push(fee);
+        }
+        Ok(withdrawal_fees)
+    }
+}
+
+impl WithdrawalFees {
+    pub fn get(&self, venue: Venue, currency: Currency) -> Option<&WithdrawalFee> {
+        self.fees
+            .iter()
+            .find(|fee| fee.venue == venue && fee.currency == currency)
+    }
+
+    // quote cost of moving the bought asset to the sell venue and the proceeds back
+    pub fn transfer_cost(
+        &self,
+        buy_venue: Venue,
+        sell_venue: Venue,
+        symbol: Symbol,
+        qty: f64,
+        price: f64,
+    ) -> f64 {
+        let asset_cost = self
+            .get(buy_venue, symbol.base_currency())
+            .map(|fee| fee.cost(qty) * price)
+            .unwrap_or(0.0);
+        let quote_cost = self
+            .get(sell_venue, symbol.quote_currency())
+            .map(|fee| fee.cost(qty * price))
+            .unwrap_or(0.0);
+        asset_cost + quote_cost
+    }
+}
+
+// base gained on Binance minus base gained on MB since the last settlement; alternating
+// directions offset each other so only the net has to be moved
+#[derive(Clone, Debug, Default)]
+pub struct Netting {
+    net_binance_qty: f64,
+    // price of the latest fill, used to value the net transfer
+    price: f64,
+    trades: u32,
+}
+
+impl Netting {
+    pub fn record(&mut self, fills: &[Fill]) {
+        for fill in fills {
+            let qty = match fill.leg.side {
+                Side::Buy => fill.qty,
+                Side::Sell => -fill.qty,
+            };
+            match fill.leg.venue {
+                Venue::Binance => self.net_binance_qty += qty,
+                Venue::MercadoBitcoin => self.net_binance_qty -= qty,
+            }
+            self.price = fill.price;
+        }
+        self.trades += 1;
+    }
+
+    // once `every` trades have been recorded, the quote cost of rebalancing the net quantity,
+    // after which netting starts over
+    pub fn settle(&mut self, every: u32, fees: &WithdrawalFees, symbol: Symbol) -> Option<f64> {
+        if self.trades < every.max(1) {
+            return None;
+        }
+        // the venue that accumulated the asset withdraws it to the other
+        let (from, to) = if self.net_binance_qty > 0.0 {
+            (Venue::Binance, Venue::MercadoBitcoin)
+        } else {
+            (Venue::MercadoBitcoin, Venue::Binance)
+        };
+        // each leg adds or removes qty on both venues, so half the difference has to move
+        let qty = self.net_binance_qty.abs() / 2.0;
+        let cost = if qty > 0.0 {
+            fees.transfer_cost(from, to, symbol, qty, self.price)
+        } else {
+            0.0
+        };
+        *self = Self::default();
+        Some(cost)
+    }
+}
@@ -0,0 +1,133 @@
+use std::{
+    fmt,
+    iter::Sum,
+    ops::{Add, Mul, Sub},
+};
+
+use serde::{Deserialize, Serialize};
+
+// quote currency per unit of base currency
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Price(pub f64);
+
+// amount of base currency
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Qty(pub f64);
+
+// amount of quote currency, i.e. price times quantity
+#[derive(Clone, Copy, Debug, Default, PartialEq, PartialOrd, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Notional(pub f64);
+
+fn checked(value: f64) -> f64 {
+    debug_assert!(value.is_finite(), "non-finite amount: {}", value);
+    value
+}
+
+impl Qty {
+    pub fn min(self, other: Qty) -> Qty {
+        Qty(f64::min(self.0, other.0))
+    }
+}
+
+impl Price {
+    // relative distance from `base`, e.g. a spread as a fraction of the buy price; None for a
+    // zero base, which only a bad feed quotes
+    pub fn relative_to(self, base: Price) -> Option<f64> {
+        (base.0 != 0.0).then(|| checked((self.0 - base.0) / base.0))
+    }
+}
+
+impl Sub for Price {
+    type Output = Price;
+
+    fn sub(self, rhs: Price) -> Price {
+        Price(checked(self.0 - rhs.0))
+    }
+}
+
+impl Mul<f64> for Price {
+    type Output = Price;
+
+    fn mul(self, rhs: f64) -> Price {
+        Price(checked(self.0 * rhs))
+    }
+}
+
+impl Mul<Qty> for Price {
+    type Output = Notional;
+
+    fn mul(self, rhs: Qty) -> Notional {
+        Notional(checked(self.0 * rhs.0))
+    }
+}
+
+impl Mul<Price> for Qty {
+    type Output = Notional;
+
+    fn mul(self, rhs: Price) -> Notional {
+        rhs * self
+    }
+}
+
+impl Sum for Qty {
+    fn sum<I: Iterator<Item = Qty>>(iter: I) -> Qty {
+        Qty(checked(iter.map(|qty| qty.0).sum()))
+    }
+}
+
+impl Add for Notional {
+    type Output = Notional;
+
+    fn add(self, rhs: Notional) -> Notional {
+        Notional(checked(self.0 + rhs.0))
+    }
+}
+
+impl Sub for Notional {
+    type Output = Notional;
+
+    fn sub(self, rhs: Notional) -> Notional {
+        Notional(checked(self.0 - rhs.0))
+    }
+}
+
+// fee and slippage rates
+impl Mul<f64> for Notional {
+    type Output = Notional;
+
+    fn mul(self, rhs: f64) -> Notional {
+        Notional(checked(self.0 * rhs))
+    }
+}
+
+impl fmt::Display for Price {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for Qty {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl fmt::Display for Notional {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn relative_to_a_zero_base_is_none() {
+        assert_eq!(Price(105.0).relative_to(Price(100.0)), Some(0.05));
+        assert_eq!(Price(105.0).relative_to(Price(0.0)), None);
+    }
+}
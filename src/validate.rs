@@ -0,0 +1,181 @@
+use crate::{
+    config::{Config, ExecutionMode},
+    credentials::Credentials,
+    volume::check_tiers,
+};
+
+// every problem found with the config, each naming the setting to fix; empty when it is
+// coherent
+pub fn validate(config: &Config) -> Vec<String> {
+    let mut problems = Vec::new();
+
+    if let Err(message) = check_tiers(&config.binance_vip_tiers) {
+        problems.push(format!("binance_vip_tiers: {}", message));
+    }
+    if let Some(Err(message)) = config
+        .adaptive_poll
+        .map(|adaptive_poll| adaptive_poll.check())
+    {
+        problems.push(format!("adaptive_poll: {}", message));
+    }
+    if let Some(Err(message)) = config
+        .profit_hysteresis
+        .map(|profit_hysteresis| profit_hysteresis.check())
+    {
+        problems.push(format!("profit_hysteresis: {}", message));
+    }
+
+    let amounts = [
+        ("min_profit", Some(config.min_profit)),
+        ("safety_margin", Some(config.safety_margin)),
+        ("safety_margin_pct", Some(config.safety_margin_pct)),
+        ("slippage_bps", Some(config.slippage_bps)),
+        ("binance_fee_rate", Some(config.binance_fee_rate)),
+        ("mm_edge_bps", Some(config.mm_edge_bps)),
+        ("holding_cost_rate", Some(config.holding_cost_rate)),
+        ("poll_jitter_pct", Some(config.poll_jitter_pct)),
+        ("reconcile_tolerance", Some(config.reconcile_tolerance)),
+        (
+            "leg_imbalance_tolerance",
+            Some(config.leg_imbalance_tolerance),
+        ),
+        ("starting_balance", Some(config.starting_balance)),
+        ("max_spread", config.max_spread),
+        ("max_spread_pct", config.max_spread_pct),
+        (
+            "max_reference_deviation_pct",
+            config.max_reference_deviation_pct,
+        ),
+        ("max_trade_qty", config.max_trade_qty),
+        ("hedged_inventory", config.hedged_inventory),
+    ];
+    for (name, value) in amounts {
+        if let Some(value) = value {
+            if !value.is_finite() || value < 0.0 {
+                problems.push(format!("{} is {}, it can't be negative", name, value));
+            }
+        }
+    }
+    if !config.fee_buffer.is_finite() || config.fee_buffer <= 0.0 {
+        problems.push(format!(
+            "fee_buffer is {}, it multiplies the modeled fees and must be above 0",
+            config.fee_buffer
+        ));
+    }
+    if !(0.0..=1.0).contains(&config.profit_reserve_fraction) {
+        problems.push(format!(
+            "profit_reserve_fraction is {}, it must be between 0 and 1",
+            config.profit_reserve_fraction
+        ));
+    }
+    if let Err(message) = config.check_market_making() {
+        problems.push(message);
+    }
+    if config.ladder_slices == 0 {
+        problems.push("ladder_slices is 0, use 1 to send trades whole".to_string());
+    }
+
+    let enabled = |symbol| config.enabled_symbols.get(&symbol).copied().unwrap_or(true);
+    if !enabled(config.symbol) {
+        problems.push(format!(
+            "symbol {:?} is disabled in enabled_symbols, so nothing would be traded",
+            config.symbol
+        ));
+    }
+    for symbol in &config.scan_symbols {
+        if !enabled(*symbol) {
+            problems.push(format!(
+                "scan_symbols lists {:?}, which is disabled in enabled_symbols",
+                symbol
+            ));
+        }
+    }
+    if config.cross_quote && config.symbol.get_binance_usdt_symbol_param().is_none() {
+        problems.push(format!(
+            "cross_quote is set but Binance has no USDT-quoted pair for {:?}",
+            config.symbol
+        ));
+    }
+
+    if config.mode == ExecutionMode::Live {
+        match Credentials::load(config.secrets_path.as_deref()) {
+            Ok(credentials) => {
+                let missing = credentials.missing(&config.accounts);
+                if !missing.is_empty() {
+                    problems.push(format!(
+                        "live mode requires API credentials, missing {}",
+                        missing.join(", ")
+                    ));
+                }
+            }
+            Err(error) => problems.push(format!("secrets_path can't be read: {}", error)),
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{config::AdaptivePoll, hysteresis::ProfitHysteresis};
+
+    #[test]
+    fn inverted_adaptive_poll_bounds_are_reported() {
+        let config = Config {
+            adaptive_poll: Some(AdaptivePoll {
+                min_interval_ms: 2_000,
+                max_interval_ms: 500,
+                volatile_bps: 10.0,
+                calm_bps: 1.0,
+            }),
+            ..Config::default()
+        };
+        let problems = validate(&config);
+        assert!(
+            problems
+                .iter()
+                .any(|problem| problem.starts_with("adaptive_poll: min_interval_ms 2000")),
+            "{:?}",
+            problems
+        );
+    }
+
+    #[test]
+    fn market_making_is_refused_outside_simulated_mode() {
+        let config = Config {
+            market_making: true,
+            ..Config::default()
+        };
+        assert!(validate(&config).is_empty());
+
+        let config = Config {
+            mode: ExecutionMode::Live,
+            secrets_path: None,
+            ..config
+        };
+        assert!(validate(&config)
+            .iter()
+            .any(|problem| problem.starts_with("market_making is experimental")));
+    }
+
+    #[test]
+    fn an_inverted_profit_hysteresis_is_reported() {
+        let config = Config {
+            profit_hysteresis: Some(ProfitHysteresis {
+                enter_profit: 5.0,
+                exit_profit: 10.0,
+            }),
+            ..Config::default()
+        };
+        assert_eq!(
+            validate(&config),
+            ["profit_hysteresis: enter_profit 5 is below exit_profit 10"]
+        );
+    }
+
+    #[test]
+    fn the_default_config_is_coherent() {
+        assert_eq!(validate(&Config::default()), Vec::<String>::new());
+    }
+}
@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use crate::Currency;
+
+// latest observed price of one currency in another, used to value holdings and profits in
+// the configured base currency
+#[derive(Clone, Debug, Default)]
+pub struct Marks {
+    prices: HashMap<(Currency, Currency), f64>,
+}
+
+impl Marks {
+    pub fn observe(&mut self, base: Currency, quote: Currency, price: f64) {
+        if price > 0.0 {
+            self.prices.insert((base, quote), price);
+        }
+    }
+
+    fn direct_rate(&self, from: Currency, to: Currency) -> Option<f64> {
+        self.prices
+            .get(&(from, to))
+            .copied()
+            .or_else(|| self.prices.get(&(to, from)).map(|price| 1.0 / price))
+    }
+
+    // direct, inverse or through one intermediate currency
+    pub fn rate(&self, from: Currency, to: Currency) -> Option<f64> {
+        if from == to {
+            return Some(1.0);
+        }
+        self.direct_rate(from, to).or_else(|| {
+            self.prices
+                .keys()
+                .flat_map(|(base, quote)| [*base, *quote])
+                .find_map(|via| Some(self.direct_rate(from, via)? * self.direct_rate(via, to)?))
+        })
+    }
+
+    pub fn convert(&self, amount: f64, from: Currency, to: Currency) -> Option<f64> {
+        if amount == 0.0 {
+            return Some(0.0);
+        }
+        Some(amount * self.rate(from, to)?)
+    }
+}
+
+// change in the value of the holdings across a trade, in `base_currency`, with both sides at
+// the same post-trade marks so that only what the trade moved counts; None when a changed
+// holding can't be priced
+pub fn realized_net_profit(
+    before: &HashMap<Currency, f64>,
+    after: &HashMap<Currency, f64>,
+    base_currency: Currency,
+    marks: &Marks,
+) -> Option<f64> {
+    let mut currencies: Vec<Currency> = before.keys().chain(after.keys()).copied().collect();
+    currencies.sort();
+    currencies.dedup();
+    currencies.into_iter().try_fold(0.0, |total, currency| {
+        let delta = after.get(&currency).copied().unwrap_or(0.0)
+            - before.get(&currency).copied().unwrap_or(0.0);
+        Some(total + marks.convert(delta, currency, base_currency)?)
+    })
+}
@@ -0,0 +1,103 @@
+use std::{collections::VecDeque, error::Error, fs, io::ErrorKind};
+
+use serde::{Deserialize, Serialize};
+
+// a row of Binance's VIP table: the rate charged once the rolling volume reaches min_volume
+#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+pub struct VipTier {
+    pub min_volume: f64,
+    pub fee_rate: f64,
+}
+
+// rate of the highest tier reached, or of the lowest tier below all of them
+pub fn tier_fee_rate(tiers: &[VipTier], volume: f64) -> Option<f64> {
+    tiers
+        .iter()
+        .filter(|tier| tier.min_volume <= volume)
+        .max_by(|a, b| a.min_volume.total_cmp(&b.min_volume))
+        .or_else(|| {
+            tiers
+                .iter()
+                .min_by(|a, b| a.min_volume.total_cmp(&b.min_volume))
+        })
+        .map(|tier| tier.fee_rate)
+}
+
+// a usable table starts at zero volume, so that every volume has a tier, and its rates never
+// rise with volume
+pub fn check_tiers(tiers: &[VipTier]) -> Result<(), String> {
+    let mut sorted = tiers.to_vec();
+    sorted.sort_by(|a, b| a.min_volume.total_cmp(&b.min_volume));
+    if let Some(first) = sorted.first() {
+        if first.min_volume != 0.0 {
+            return Err(format!(
+                "the lowest tier starts at {} instead of 0",
+                first.min_volume
+            ));
+        }
+    }
+    for tier in &sorted {
+        if !tier.fee_rate.is_finite() || tier.fee_rate < 0.0 {
+            return Err(format!(
+                "the tier at {} has a fee rate of {}",
+                tier.min_volume, tier.fee_rate
+            ));
+        }
+    }
+    for pair in sorted.windows(2) {
+        let [lower, higher] = pair else { continue };
+        if lower.min_volume == higher.min_volume {
+            return Err(format!("two tiers start at {}", lower.min_volume));
+        }
+        if higher.fee_rate > lower.fee_rate {
+            return Err(format!(
+                "the tier at {} charges {}, more than the {} charged from {}",
+                higher.min_volume, higher.fee_rate, lower.fee_rate, lower.min_volume
+            ));
+        }
+    }
+    Ok(())
+}
+
+// notional executed on Binance, in the symbol's quote currency, kept for as long as the
+// tier window so that a restart doesn't reset the tier
+#[derive(Clone, Debug, Default, Serialize, Deserialize)]
+pub struct RollingVolume {
+    // (executed at, notional), oldest first
+    trades: VecDeque<(u64, f64)>,
+}
+
+impl RollingVolume {
+    // a missing file starts from zero volume
+    pub fn load(path: &str) -> Result<Self, Box<dyn Error>> {
+        match fs::read_to_string(path) {
+            Ok(contents) => Ok(serde_json::from_str(&contents)?),
+            Err(error) if error.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    pub fn save(&self, path: &str) -> Result<(), Box<dyn Error>> {
+        fs::write(path, serde_json::to_string(self)?)?;
+        Ok(())
+    }
+
+    pub fn record(&mut self, now_ms: u64, notional: f64) {
+        if notional > 0.0 {
+            self.trades.push_back((now_ms, notional));
+        }
+    }
+
+    // drops trades older than the window and sums the rest
+    pub fn total(&mut self, now_ms: u64, window_ms: u64) -> f64 {
+        let cutoff = now_ms.saturating_sub(window_ms);
+        while self
+            .trades
+            .front()
+            .is_some_and(|(executed_at_ms, _)| *executed_at_ms < cutoff)
+        {
+            self.trades.pop_front();
+        }
+        self.trades.iter().map(|(_, notional)| notional).sum()
+    }
+}
@@ -0,0 +1,104 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+
+use serde_json::json;
+use tokio::time::sleep;
+use tokio_util::sync::CancellationToken;
+
+use crate::{clock::Clock, output};
+
+// beaten by the trade loop around every cycle; the time in between cycles is the loop
+// sleeping on purpose, so only a cycle that hasn't finished counts as a stall
+#[derive(Clone, Default)]
+pub struct Heartbeat {
+    // when the cycle in progress started, 0 between cycles
+    cycle_started_ms: Arc<AtomicU64>,
+}
+
+impl Heartbeat {
+    pub fn start_cycle(&self, now_ms: u64) {
+        self.cycle_started_ms
+            .store(now_ms.max(1), Ordering::Relaxed);
+    }
+
+    pub fn end_cycle(&self) {
+        self.cycle_started_ms.store(0, Ordering::Relaxed);
+    }
+
+    pub fn stalled_for_ms(&self, now_ms: u64) -> Option<u64> {
+        match self.cycle_started_ms.load(Ordering::Relaxed) {
+            0 => None,
+            started_ms => Some(now_ms.saturating_sub(started_ms)),
+        }
+    }
+}
+
+// cancels `halt` once a cycle has been running for longer than `stall_after`, checking a few
+// times per period so that it trips close to the limit
+pub async fn watch(
+    heartbeat: Heartbeat,
+    stall_after: Duration,
+    halt: CancellationToken,
+    clock: Arc<dyn Clock>,
+) {
+    let stall_after_ms = stall_after.as_millis() as u64;
+    loop {
+        sleep(stall_after / 4).await;
+        let Some(stalled_for_ms) = heartbeat.stalled_for_ms(clock.now_ms()) else {
+            continue;
+        };
+        if stalled_for_ms > stall_after_ms {
+            output::error(
+                "loop_stalled",
+                json!({ "stalled_for_ms": stalled_for_ms, "limit_ms": stall_after_ms }),
+                format_args!(
+                    "CRITICAL: trade loop stalled for {} ms (limit {} ms), halting",
+                    stalled_for_ms, stall_after_ms
+                ),
+            );
+            halt.cancel();
+            return;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use tokio::time::timeout;
+
+    use super::*;
+    use crate::clock::MockClock;
+
+    #[tokio::test]
+    async fn a_stalled_cycle_trips_the_halt() {
+        let clock = Arc::new(MockClock::new(1_000));
+        let heartbeat = Heartbeat::default();
+        let halt = CancellationToken::new();
+        tokio::spawn(watch(
+            heartbeat.clone(),
+            Duration::from_millis(40),
+            halt.clone(),
+            clock.clone(),
+        ));
+
+        // a cycle within the limit, then time between cycles, never trips it
+        heartbeat.start_cycle(clock.now_ms());
+        clock.advance(30);
+        sleep(Duration::from_millis(60)).await;
+        heartbeat.end_cycle();
+        clock.advance(1_000);
+        sleep(Duration::from_millis(60)).await;
+        assert!(!halt.is_cancelled());
+
+        heartbeat.start_cycle(clock.now_ms());
+        clock.advance(41);
+        timeout(Duration::from_secs(1), halt.cancelled())
+            .await
+            .expect("the watchdog halts a stalled cycle");
+    }
+}
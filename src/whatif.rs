@@ -0,0 +1,71 @@
+use std::{error::Error, fs, path::Path};
+
+use serde::Serialize;
+
+use crate::{best_arbitrage, import::import_csv, Config, DecisionInput};
+
+// Binance fee rates tried when none are given: zero, the VIP range and the default 0.1% up
+pub const DEFAULT_FEE_RATES: [f64; 6] = [0.0, 0.00025, 0.0005, 0.00075, 0.001, 0.002];
+
+// how the recorded opportunities would have fared at one Binance fee rate
+#[derive(Clone, Copy, Debug, Serialize)]
+pub struct FeeScenario {
+    pub fee_rate: f64,
+    pub opportunities: usize,
+    pub net_profit: f64,
+}
+
+// a single capture, every capture in a decision_capture_dir, oldest first, or a CSV of books
+// recorded elsewhere, imported under `config`
+pub fn load_session(path: &str, config: &Config) -> Result<Vec<DecisionInput>, Box<dyn Error>> {
+    if Path::new(path)
+        .extension()
+        .is_some_and(|extension| extension == "csv")
+    {
+        return import_csv(path, config);
+    }
+    if !Path::new(path).is_dir() {
+        return Ok(vec![DecisionInput::load(path)?]);
+    }
+    let mut paths: Vec<String> = fs::read_dir(path)?
+        .filter_map(|entry| entry.ok())
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "json")
+        })
+        .filter_map(|path| path.to_str().map(str::to_string))
+        .collect();
+    paths.sort();
+    paths.iter().map(|path| DecisionInput::load(path)).collect()
+}
+
+// re-evaluates every capture at each rate, ignoring balances since the question is what the
+// market offered; MB keeps its own fee schedule
+pub fn fee_grid(session: &[DecisionInput], fee_rates: &[f64]) -> Vec<FeeScenario> {
+    fee_rates
+        .iter()
+        .map(|&fee_rate| {
+            let actions: Vec<f64> = session
+                .iter()
+                .filter_map(|input| {
+                    let mut config = input.config.clone();
+                    config.binance_fee_rate = fee_rate;
+                    best_arbitrage(
+                        &input.binance_order_book,
+                        &input.mb_order_book,
+                        config.symbol,
+                        &config,
+                        input.min_profit,
+                    )
+                })
+                .map(|action| action.net_profit())
+                .collect();
+            FeeScenario {
+                fee_rate,
+                opportunities: actions.len(),
+                net_profit: actions.iter().fold(0.0, |total, profit| total + profit),
+            }
+        })
+        .collect()
+}